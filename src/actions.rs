@@ -1,5 +1,5 @@
+use super::config::{self, ConfigError, ACTIONS};
 use serde::{Deserialize, Serialize};
-use std::fs;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Actions {
@@ -18,8 +18,6 @@ impl std::fmt::Display for Action {
     }
 }
 
-pub fn gen_actions() -> Actions {
-    let actions_toml =
-        &fs::read_to_string("config/actions.toml").expect("unable to open actions.toml");
-    toml::from_str::<Actions>(actions_toml).unwrap()
+pub fn gen_actions() -> Result<Actions, ConfigError> {
+    config::read(ACTIONS)
 }
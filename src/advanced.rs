@@ -1,7 +1,12 @@
 use super::actions::{Action, Actions};
+use super::graph::Graph;
 use super::logger::Logger;
-use super::nodes::{get_nodemap, Node, Nodes};
+use super::nodes::{get_nodemap, is_generated, Edge, Node, Nodes, HOVER_Z};
+use super::paths::{gen_node_paths, DEFAULT_FEED};
 use super::run::do_nothing;
+use super::status::Status;
+use super::traveler;
+use super::validate::{validate, Report};
 use super::style::style::Theme;
 use crate::{NodeTracker, TabState as ParentTabState, CQ_MONO};
 use iced::{
@@ -11,7 +16,7 @@ use iced::{
 };
 
 use super::build::{delete_icon, down_icon, okay_icon, right_icon};
-use super::grbl::{Command as Cmd, Grbl};
+use super::grbl::{Command as Cmd, Grbl, GrblError, Response};
 use chrono::prelude::*;
 use chrono::DateTime;
 use regex::Regex;
@@ -106,64 +111,12 @@ impl Advanced {
         match message {
             AdvancedMessage::TabBar(TabBarMessage::Grbl) => {
                 if !self.grbl_tab.unsaved {
-                    self.grbl_tab.grbl.push_command(Cmd::new("$I".to_string()));
-                    loop {
-                        if let Some(num) = self.grbl_tab.grbl.queue_len() {
-                            if num < 2 {
-                                self.grbl_tab.grbl.push_command(Cmd::new("$I".to_string()));
-                            }
-                        }
-                        if let Some(cmd) = self.grbl_tab.grbl.safe_pop() {
-                            if cmd.command == "$I".to_string() {
-                                self.grbl_tab.grbl.clear_all();
-                                let r = Regex::new(r"[0-9]*\.+[0-9]*[a-z]*").unwrap();
-                                let r2 = Regex::new(r"[0-9]{8}").unwrap();
-                                if let Some(caps) = r.captures(&(cmd.result.as_ref().unwrap()[..]))
-                                {
-                                    self.grbl_tab.version = Some(caps[0].to_string());
-                                }
-                                if let Some(caps2) =
-                                    r2.captures(&(cmd.result.as_ref().unwrap()[..]))
-                                {
-                                    self.grbl_tab.version_release_date = Some(date(&caps2[0]));
-                                }
-
-                                break;
-                            }
-                        }
-                    }
-                    self.grbl_tab.grbl.push_command(Cmd::new("$$".to_string()));
-                    loop {
-                        if let Some(num) = self.grbl_tab.grbl.queue_len() {
-                            if num < 2 {
-                                self.grbl_tab.grbl.push_command(Cmd::new("$$".to_string()));
-                            }
-                        }
-                        if let Some(cmd) = self.grbl_tab.grbl.safe_pop() {
-                            if cmd.command == "$$".to_string() {
-                                self.grbl_tab.grbl.clear_all();
-                                self.grbl_tab.modified_settings = cmd.result.unwrap().lines().fold(
-                                    Vec::new(),
-                                    |mut v, response| {
-                                        let r: Vec<&str> = response.split("=").collect();
-                                        if r.len() > 1 {
-                                            v.push(GrblSetting::new(
-                                                format!("{}", r[0]),
-                                                r[1].to_string(),
-                                            ));
-                                        }
-                                        v
-                                    },
-                                );
-                                break;
-                            }
-                        }
-                    }
+                    command = Command::perform(
+                        read_grbl(self.grbl_tab.grbl.clone()),
+                        move |answer| AdvancedMessage::GrblTab(GrblMessage::Loaded(answer)),
+                    );
                 }
                 self.update_logs();
-                if self.grbl_tab.settings.len() == 0 {
-                    self.grbl_tab.settings = self.grbl_tab.modified_settings.clone()
-                }
                 self.state = TabState::Grbl;
                 self.tab_bar.change_state(TabState::Grbl)
             }
@@ -188,8 +141,15 @@ impl Advanced {
                 self.state = TabState::Logs;
                 self.tab_bar.change_state(TabState::Logs)
             }
+            AdvancedMessage::GrblTab(GrblMessage::Saved(result)) => {
+                self.grbl_tab.update(GrblMessage::Saved(result));
+                self.update_logs();
+            }
             AdvancedMessage::GrblTab(msg) => {
-                self.grbl_tab.update(msg);
+                command = self
+                    .grbl_tab
+                    .update(msg)
+                    .map(move |msg| AdvancedMessage::GrblTab(msg))
             }
             AdvancedMessage::NodesTab(NodeTabMessage::AddConfigNode) => {
                 command = self
@@ -496,6 +456,10 @@ pub struct GrblTab {
     version: Option<String>,
     version_release_date: Option<String>,
     unsaved_tabs: Rc<RefCell<HashMap<TabState, bool>>>,
+    // latest status report, set every tick in main.rs for the pin panel
+    pub status: Option<Status>,
+    // why $I or $$ could not be read
+    load_error: Option<GrblError>,
 }
 
 #[derive(Debug, Clone)]
@@ -509,6 +473,10 @@ struct GrblSetting {
 pub enum GrblMessage {
     SettingChanged(usize, GrblSettingMessage),
     SaveMessage(SaveBarMessage),
+    // grbl's answers to $I and $$
+    Loaded(Result<(Response, Response), GrblError>),
+    // the setting grbl refused and why, the old settings were written back
+    Saved(Result<(), String>),
 }
 
 impl GrblTab {
@@ -528,65 +496,64 @@ impl GrblTab {
             version_release_date: None,
             logger,
             unsaved_tabs,
+            status: None,
+            load_error: None,
         }
     }
 
-    fn update(&mut self, message: GrblMessage) {
+    fn update(&mut self, message: GrblMessage) -> Command<GrblMessage> {
         match message {
+            // edits made while waiting are kept
+            GrblMessage::Loaded(_) if self.unsaved => {}
+            GrblMessage::Loaded(Ok((info, settings))) => {
+                self.load_error = None;
+                let info = info.lines.join("\n");
+                let r = Regex::new(r"[0-9]*\.+[0-9]*[a-z]*").unwrap();
+                let r2 = Regex::new(r"[0-9]{8}").unwrap();
+                if let Some(caps) = r.captures(&info) {
+                    self.version = Some(caps[0].to_string());
+                }
+                if let Some(caps2) = r2.captures(&info) {
+                    self.version_release_date = Some(date(&caps2[0]));
+                }
+                self.modified_settings =
+                    settings.lines.iter().fold(Vec::new(), |mut v, response| {
+                        let r: Vec<&str> = response.split("=").collect();
+                        if r.len() > 1 {
+                            v.push(GrblSetting::new(format!("{}", r[0]), r[1].to_string()));
+                        }
+                        v
+                    });
+                if self.settings.len() == 0 {
+                    self.settings = self.modified_settings.clone()
+                }
+            }
+            GrblMessage::Loaded(Err(err)) => self.load_error = Some(err),
             GrblMessage::SettingChanged(i, GrblSettingMessage::TextChanged(val)) => {
                 self.unsaved = true;
                 self.unsaved_tabs.borrow_mut().insert(TabState::Grbl, true);
                 self.modified_settings[i].input_value = val
             }
             GrblMessage::SaveMessage(SaveBarMessage::Save) => {
-                for setting in &self.modified_settings {
-                    self.grbl.push_command(Cmd::new(format!(
-                        "{}={}",
-                        &setting.text, &setting.input_value
-                    )));
-                }
-                let mut error = false;
-                loop {
-                    if let Some(cmd) = self.grbl.pop_command() {
-                        if cmd
-                            .result
-                            .as_ref()
-                            .unwrap_or(&String::new())
-                            .contains("error")
-                        {
-                            error = true;
-                            self.save_bar.message = format!(
-                                "{} {}. Settings Reverted.",
-                                cmd.command,
-                                cmd.result.unwrap()
-                            );
-                            self.modified_settings = self.settings.clone();
-                        }
-                        if let Some(num) = self.grbl.recv_queue_len() {
-                            if num == 0 {
-                                break;
-                            }
-                        }
-                    }
-                }
-                if error {
-                    for setting in &self.settings {
-                        self.grbl.push_command(Cmd::new(format!(
-                            "{}={}",
-                            &setting.text, &setting.input_value
-                        )));
-                    }
-                    if let Some(final_cmd) = self.settings.last() {
-                        loop {
-                            if let Some(cmd) = self.grbl.pop_command() {
-                                if cmd.command
-                                    == format!("{}={}", final_cmd.text, final_cmd.input_value)
-                                {
-                                    break;
-                                }
-                            }
-                        }
-                    }
+                let commands = |settings: &Vec<GrblSetting>| {
+                    settings
+                        .iter()
+                        .map(|setting| format!("{}={}", &setting.text, &setting.input_value))
+                        .collect()
+                };
+                return Command::perform(
+                    save_settings(
+                        self.grbl.clone(),
+                        commands(&self.modified_settings),
+                        commands(&self.settings),
+                    ),
+                    GrblMessage::Saved,
+                );
+            }
+            GrblMessage::Saved(result) => {
+                if let Err(refused) = result {
+                    self.save_bar.message = format!("{}. Settings Reverted.", refused);
+                    self.modified_settings = self.settings.clone();
                 } else {
                     self.save_bar.message = "Unsaved Changes!".to_string();
                     self.settings = self.modified_settings.clone();
@@ -626,6 +593,7 @@ impl GrblTab {
             }
             _ => {}
         }
+        Command::none()
     }
 
     fn view(&mut self) -> Element<'_, GrblMessage> {
@@ -668,6 +636,7 @@ impl GrblTab {
                 .size(20)
                 .width(Length::Units(505)),
             )
+            .push(pin_panel(&self.status))
             .push(if self.modified_settings.len() > 0 {
                 self.modified_settings.iter_mut().enumerate().fold(
                     Column::new(),
@@ -681,12 +650,94 @@ impl GrblTab {
                 )
             } else {
                 Column::new().push(
-                Container::new(Row::with_children(vec![Space::with_width(Length::Fill).into(), Text::new("Error loading settings.\nPlease press 'Grbl tab button again to attempt reload.'").horizontal_alignment(HorizontalAlignment::Center).into(), Space::with_width(Length::Fill).into()]).padding(10)).style(Theme::Red))
+                Container::new(Row::with_children(vec![Space::with_width(Length::Fill).into(), Text::new(format!("Error loading settings{}.\nPlease press 'Grbl tab button again to attempt reload.'", match &self.load_error { Some(err) => format!(", {}", err), None => String::new() })).horizontal_alignment(HorizontalAlignment::Center).into(), Space::with_width(Length::Fill).into()]).padding(10)).style(Theme::Red))
             })
             .into()
     }
 }
 
+// $I for the version and $$ for the settings
+async fn read_grbl(grbl: Grbl) -> Result<(Response, Response), GrblError> {
+    let info = grbl.request(Cmd::new("$I".to_string())).await?;
+    let settings = grbl.request(Cmd::new("$$".to_string())).await?;
+    Ok((info, settings))
+}
+
+// writes each setting, the first one grbl refuses has the previous settings written back
+async fn save_settings(
+    grbl: Grbl,
+    settings: Vec<String>,
+    previous: Vec<String>,
+) -> Result<(), String> {
+    for setting in settings {
+        if let Err(err) = grbl.request(Cmd::new(setting.clone())).await {
+            // a revert that fails too is left for the next save
+            for setting in previous {
+                grbl.request(Cmd::new(setting)).await.ok();
+            }
+            return Err(format!("{} {}", setting, err));
+        }
+    }
+    Ok(())
+}
+
+// live inputs and buffers from grbl's status reports, to check switches without moving anything
+fn pin_panel<'a>(status: &Option<Status>) -> Element<'a, GrblMessage> {
+    let mut panel = Column::new()
+        .spacing(10)
+        .padding(20)
+        .width(Length::Units(505))
+        .push(Text::new("Pins").font(CQ_MONO).size(30));
+    let status = match status {
+        Some(status) => status,
+        None => return panel.push(Text::new("No status from GRBL").size(20)).into(),
+    };
+    let pins = status.pins.all();
+    for row in pins.chunks(4) {
+        panel = panel.push(row.iter().fold(Row::new().spacing(10), |r, (name, on)| {
+            r.push(
+                Container::new(Text::new(*name).size(16))
+                    .padding(8)
+                    .width(Length::Units(115))
+                    .center_x()
+                    .style(if *on { Theme::Red } else { Theme::LightGray }),
+            )
+        }));
+    }
+    let work = status.work_position();
+    panel
+        .push(Text::new(format!("State: {}", status.state_text())).size(20))
+        .push(
+            Text::new(format!(
+                "Machine ({:.3}, {:.3}, {:.3})\nWork ({:.3}, {:.3}, {:.3})",
+                status.x, status.y, status.z, work[0], work[1], work[2]
+            ))
+            .size(20),
+        )
+        .push(
+            Text::new(match status.buffer {
+                Some((blocks, bytes)) => format!(
+                    "Planner: {} blocks free, receive buffer: {} bytes free",
+                    blocks, bytes
+                ),
+                None => "Buffer state not reported, turn it on in $10".to_string(),
+            })
+            .size(20),
+        )
+        .push(
+            Text::new(format!(
+                "Feed {} Spindle {}\nOverrides: feed {}%, rapid {}%, spindle {}%",
+                status.feed,
+                status.spindle,
+                status.feed_override,
+                status.rapid_override,
+                status.spindle_override
+            ))
+            .size(20),
+        )
+        .into()
+}
+
 #[derive(Debug, Clone)]
 pub enum GrblSettingMessage {
     TextChanged(String),
@@ -737,17 +788,30 @@ struct NodeTab {
     ref_nodes: Rc<RefCell<Nodes>>,
     modified_nodes: Rc<RefCell<Nodes>>,
     config_nodes: Vec<ConfigNode>,
+    feed: String,
+    feed_state: text_input::State,
     add_config_node_btn: button::State,
     logger: Logger,
     unsaved_tabs: Rc<RefCell<HashMap<TabState, bool>>>,
     node_map: Rc<RefCell<HashMap<String, usize>>>,
     homing_required: Rc<RefCell<bool>>,
     node_tracker: Arc<Mutex<NodeTracker>>,
+    top_graph: Graph,
+    side_graph: Graph,
+    path_from: Option<String>,
+    path_from_state: pick_list::State<String>,
+    path_to: Option<String>,
+    path_to_state: pick_list::State<String>,
+    path_message: String,
+    report: Report,
 }
 
 #[derive(Debug, Clone)]
 pub enum NodeTabMessage {
     AddConfigNode,
+    FeedChanged(String),
+    PathFrom(String),
+    PathTo(String),
     ConfigNode((usize, ConfigNodeMessage)),
     SaveMessage(SaveBarMessage),
     Saved(()),
@@ -769,7 +833,7 @@ impl NodeTab {
             .clone()
             .node
             .into_iter()
-            .filter(|n| !n.name.contains("_hover") || n.hide)
+            .filter(|n| !is_generated(&n.name) || n.hide)
             .zip(
                 ref_nodes
                     .borrow()
@@ -779,6 +843,8 @@ impl NodeTab {
                     .filter(|n| n.name.contains("_hover") || n.hide),
             );
         let modified_nodes = Rc::new(RefCell::new(Nodes {
+            feed: ref_nodes.borrow().feed,
+            limits: ref_nodes.borrow().limits.clone(),
             node: node_pairs
                 .into_iter()
                 .fold(Vec::new(), |mut v, (n, n_hover)| {
@@ -787,13 +853,25 @@ impl NodeTab {
                             .neighbors
                             .into_iter()
                             .map(|name| name.replace("_hover", ""))
-                            .filter(|name| &n.name != name)
+                            .filter(|name| &n.name != name && *name != format!("{}_approach", n.name))
                             .collect(),
+                        edges: n_hover.edges.map(|edges| {
+                            edges
+                                .into_iter()
+                                .map(|edge| Edge {
+                                    to: edge.to.replace("_hover", ""),
+                                    ..edge
+                                })
+                                .collect()
+                        }),
                         name: n.name,
                         x: n.x,
                         y: n.y,
                         z: n.z,
                         hide: n.hide,
+                        feed: n.feed,
+                        hover: n.hover,
+                        approach: n.approach,
                     });
                     v
                 }),
@@ -810,29 +888,91 @@ impl NodeTab {
                     n.x,
                     n.y,
                     n.z,
+                    n.feed,
+                    n.hover,
+                    n.approach,
                     n.neighbors.clone(),
+                    n.edges.clone(),
                     Rc::clone(&modified_nodes),
                 ));
                 v
             });
+        let report = validate(&modified_nodes.borrow());
         NodeTab {
             unsaved: false,
             save_bar: SaveBar::new(),
             ref_nodes: Rc::clone(&ref_nodes),
             modified_nodes: Rc::clone(&modified_nodes),
             config_nodes,
+            feed: feed_string(ref_nodes.borrow().feed),
+            feed_state: text_input::State::new(),
             add_config_node_btn: button::State::new(),
             logger,
             unsaved_tabs,
             node_map,
             homing_required,
             node_tracker,
+            top_graph: Graph::new(ref_nodes.borrow().clone(), false),
+            side_graph: Graph::new(ref_nodes.borrow().clone(), true),
+            path_from: None,
+            path_from_state: pick_list::State::default(),
+            path_to: None,
+            path_to_state: pick_list::State::default(),
+            path_message: "Choose two nodes to see the path between them".to_string(),
+            report,
         }
     }
 
+    // draw the path gen_node_paths would take between the chosen nodes
+    fn update_path(&mut self) {
+        let nodes = self.ref_nodes.borrow();
+        let find = |name: &Option<String>| {
+            nodes
+                .node
+                .iter()
+                .find(|n| Some(&n.name) == name.as_ref())
+        };
+        let path = match (find(&self.path_from), find(&self.path_to)) {
+            (Some(from), Some(to)) => match gen_node_paths(&nodes, from, to) {
+                Ok(path) => {
+                    let mut path = path.node;
+                    if from.name != to.name {
+                        path.insert(0, from.clone());
+                    }
+                    self.path_message = path
+                        .iter()
+                        .map(|n| n.name.clone())
+                        .collect::<Vec<String>>()
+                        .join(" > ");
+                    path
+                }
+                Err(err) => {
+                    self.path_message = err.to_string();
+                    Vec::new()
+                }
+            },
+            _ => Vec::new(),
+        };
+        self.top_graph.set_path(path.clone());
+        self.side_graph.set_path(path);
+    }
+
     fn update(&mut self, message: NodeTabMessage) -> Command<NodeTabMessage> {
         let mut command = Command::none();
         match message {
+            NodeTabMessage::FeedChanged(feed) => {
+                self.unsaved = true;
+                self.unsaved_tabs.borrow_mut().insert(TabState::Nodes, true);
+                self.feed = feed;
+            }
+            NodeTabMessage::PathFrom(name) => {
+                self.path_from = Some(name);
+                self.update_path();
+            }
+            NodeTabMessage::PathTo(name) => {
+                self.path_to = Some(name);
+                self.update_path();
+            }
             NodeTabMessage::ConfigNode((i, ConfigNodeMessage::Okay)) => {
                 self.save_bar.message = "Unsaved Changes!".to_string();
                 self.config_nodes[i].update(ConfigNodeMessage::Okay);
@@ -860,6 +1000,13 @@ impl NodeTab {
                         .position(|n| n.name == original_name)
                         .unwrap();
                     self.modified_nodes.borrow_mut().node[index].name = name.clone();
+                    for node in &mut self.modified_nodes.borrow_mut().node {
+                        for edge in node.edges.iter_mut().flatten() {
+                            if edge.to == original_name {
+                                edge.to = name.clone();
+                            }
+                        }
+                    }
                     for node in &mut self.config_nodes {
                         for pick_list in &mut node.neighbors_pick_lists {
                             if &pick_list.parent == &original_name {
@@ -928,7 +1075,7 @@ impl NodeTab {
                     .clone()
                     .node
                     .into_iter()
-                    .filter(|n| !n.name.contains("_hover") || n.hide)
+                    .filter(|n| !is_generated(&n.name) || n.hide)
                     .zip(
                         self.ref_nodes
                             .borrow()
@@ -938,6 +1085,8 @@ impl NodeTab {
                             .filter(|n| n.name.contains("_hover") || n.hide),
                     );
                 self.modified_nodes = Rc::new(RefCell::new(Nodes {
+                    feed: self.ref_nodes.borrow().feed,
+                    limits: self.ref_nodes.borrow().limits.clone(),
                     node: node_pairs
                         .into_iter()
                         .fold(Vec::new(), |mut v, (n, n_hover)| {
@@ -946,13 +1095,25 @@ impl NodeTab {
                                     .neighbors
                                     .into_iter()
                                     .map(|name| name.replace("_hover", ""))
-                                    .filter(|name| &n.name != name)
+                                    .filter(|name| &n.name != name && *name != format!("{}_approach", n.name))
                                     .collect(),
+                                edges: n_hover.edges.map(|edges| {
+                                    edges
+                                        .into_iter()
+                                        .map(|edge| Edge {
+                                            to: edge.to.replace("_hover", ""),
+                                            ..edge
+                                        })
+                                        .collect()
+                                }),
                                 name: n.name,
                                 x: n.x,
                                 y: n.y,
                                 z: n.z,
                                 hide: n.hide,
+                                feed: n.feed,
+                                hover: n.hover,
+                                approach: n.approach,
                             });
                             v
                         }),
@@ -969,11 +1130,16 @@ impl NodeTab {
                             n.x,
                             n.y,
                             n.z,
+                            n.feed,
+                            n.hover,
+                            n.approach,
                             n.neighbors.clone(),
+                            n.edges.clone(),
                             Rc::clone(&self.modified_nodes),
                         ));
                         v
                     });
+                self.feed = feed_string(self.ref_nodes.borrow().feed);
                 self.unsaved = false;
                 self.unsaved_tabs
                     .borrow_mut()
@@ -1000,11 +1166,14 @@ impl NodeTab {
                     .any(|n| n.errors.all().iter().any(|e| *e))
                 {
                     self.save_bar.message = "Clear all errors below, and save again.".to_string();
+                } else if parse_feed(&self.feed).is_err() {
+                    self.save_bar.message = "Default feed must be a positive number.".to_string();
                 } else {
                     self.save_bar.message = "Unsaved Changes!".to_string();
                     // because this code is bad, merge the data stored in two
                     // separate locations.
                     let mut nodes = self.modified_nodes.borrow().clone();
+                    nodes.feed = parse_feed(&self.feed).unwrap();
                     for i in 0..self.config_nodes.len() {
                         nodes.node[i].neighbors = self.config_nodes[i]
                             .neighbors_pick_lists
@@ -1013,20 +1182,56 @@ impl NodeTab {
                                 v.push(pick_list.value.clone().unwrap());
                                 v
                             });
+                        // keep edge costs from baths.toml, feeds come from the neighbor inputs
+                        let old_edges = nodes.node[i].edges.clone().unwrap_or(Vec::new());
+                        let edges: Vec<Edge> = self.config_nodes[i]
+                            .neighbors_pick_lists
+                            .iter()
+                            .map(|pick_list| {
+                                let to = pick_list.value.clone().unwrap();
+                                Edge {
+                                    cost: old_edges
+                                        .iter()
+                                        .find(|edge| edge.to == to)
+                                        .and_then(|edge| edge.cost),
+                                    feed: parse_feed(&pick_list.feed).unwrap(),
+                                    to,
+                                }
+                            })
+                            .filter(|edge| edge.cost.is_some() || edge.feed.is_some())
+                            .collect();
+                        nodes.node[i].edges = if edges.is_empty() { None } else { Some(edges) };
+                        nodes.node[i].feed = parse_feed(&self.config_nodes[i].feed).unwrap();
+                        nodes.node[i].hover = parse_height(&self.config_nodes[i].hover).unwrap();
+                        nodes.node[i].approach =
+                            parse_height(&self.config_nodes[i].approach).unwrap();
                         nodes.node[i].x = self.config_nodes[i].x.parse().unwrap();
                         nodes.node[i].y = self.config_nodes[i].y.parse().unwrap();
                         nodes.node[i].z = self.config_nodes[i].z.parse().unwrap();
                     }
+                    // warnings are only listed, errors would break path finding
+                    self.report = validate(&nodes);
+                    if let Some(error) = self.report.errors.first() {
+                        self.save_bar.message = error.clone();
+                        return Command::none();
+                    }
                     let new_toml = toml::to_string_pretty(&nodes).unwrap();
                     nodes.add_height_nodes();
+                    // neighbors that cross a keep-out zone are lifted over it, unless that is blocked too
+                    if let Some(edge) = nodes.limits.blocked_edges(&nodes).first() {
+                        self.save_bar.message = format!("Unsafe neighbor {}", edge);
+                        return Command::none();
+                    }
                     let old_toml = toml::to_string_pretty(&Nodes {
+                        feed: self.ref_nodes.borrow().feed,
+                        limits: self.ref_nodes.borrow().limits.clone(),
                         node: self
                             .ref_nodes
                             .borrow()
                             .clone()
                             .node
                             .into_iter()
-                            .filter(|n| !n.name.contains("_hover"))
+                            .filter(|n| !is_generated(&n.name))
                             .collect(),
                     })
                     .unwrap_or(String::new());
@@ -1041,7 +1246,11 @@ impl NodeTab {
                         .unwrap()
                         .clone()]
                     .clone();
+                    (*self.ref_nodes.borrow_mut()).feed = nodes.feed;
                     (*self.ref_nodes.borrow_mut()).node = nodes.node;
+                    self.top_graph.set_nodes(self.ref_nodes.borrow().clone());
+                    self.side_graph.set_nodes(self.ref_nodes.borrow().clone());
+                    self.update_path();
 
                     // log the changes
                     self.logger.set_log_file(format!(
@@ -1098,6 +1307,10 @@ impl NodeTab {
                     y: 0.0,
                     z: 0.0,
                     neighbors: Vec::new(),
+                    feed: None,
+                    edges: None,
+                    hover: None,
+                    approach: None,
                 });
                 self.config_nodes.push(ConfigNode::new(
                     name,
@@ -1105,7 +1318,11 @@ impl NodeTab {
                     0.0,
                     0.0,
                     0.0,
+                    None,
+                    None,
+                    None,
                     Vec::new(),
+                    None,
                     Rc::clone(&self.modified_nodes),
                 ));
             }
@@ -1126,6 +1343,41 @@ impl NodeTab {
                 .push(Space::with_height(Length::Units(50))),
         };
         content
+            .push(report_view(&self.report))
+            .push(
+                Row::new()
+                    .max_width(400)
+                    .padding(20)
+                    .align_items(Align::Center)
+                    .push(Text::new("Default Feed (cm/min):"))
+                    .push(Space::with_width(Length::Units(10)))
+                    .push(
+                        TextInput::new(
+                            &mut self.feed_state,
+                            &DEFAULT_FEED.to_string(),
+                            &self.feed,
+                            NodeTabMessage::FeedChanged,
+                        )
+                        .style(Theme::Blue)
+                        .font(CQ_MONO)
+                        .padding(10),
+                    ),
+            )
+            .push(graph_view(
+                &mut self.top_graph,
+                &mut self.side_graph,
+                &mut self.path_from_state,
+                &mut self.path_to_state,
+                &self.path_from,
+                &self.path_to,
+                &self.path_message,
+                self.ref_nodes
+                    .borrow()
+                    .node
+                    .iter()
+                    .map(|n| n.name.clone())
+                    .collect(),
+            ))
             .push(self.config_nodes.iter_mut().enumerate().fold(
                 Column::new(),
                 |col, (i, config_node)| {
@@ -1156,6 +1408,88 @@ impl NodeTab {
     }
 }
 
+// problems found in the nodes when they were loaded or last saved
+fn report_view<'a>(report: &Report) -> Element<'a, NodeTabMessage> {
+    report
+        .errors
+        .iter()
+        .map(|e| (format!("Error: {}", e), Theme::Red))
+        .chain(
+            report
+                .warnings
+                .iter()
+                .map(|w| (format!("Warning: {}", w), Theme::Yellow)),
+        )
+        .fold(
+            Column::new().max_width(800).spacing(5),
+            |col, (text, theme)| {
+                col.push(
+                    Container::new(Text::new(text))
+                        .style(theme)
+                        .padding(10)
+                        .width(Length::Fill),
+                )
+            },
+        )
+        .into()
+}
+
+// saved graph from above and the front, with the path between two chosen nodes
+fn graph_view<'a>(
+    top_graph: &'a mut Graph,
+    side_graph: &'a mut Graph,
+    from_state: &'a mut pick_list::State<String>,
+    to_state: &'a mut pick_list::State<String>,
+    from: &Option<String>,
+    to: &Option<String>,
+    message: &str,
+    names: Vec<String>,
+) -> Element<'a, NodeTabMessage> {
+    Column::new()
+        .max_width(800)
+        .padding(20)
+        .spacing(10)
+        .push(Text::new("Top").size(20))
+        .push(
+            Container::new(top_graph.view(400))
+                .style(Theme::LighterGray)
+                .width(Length::Fill),
+        )
+        .push(Text::new("Front").size(20))
+        .push(
+            Container::new(side_graph.view(250))
+                .style(Theme::LighterGray)
+                .width(Length::Fill),
+        )
+        .push(Text::new("Hidden nodes are outlined, the planned path is green.").size(16))
+        .push(
+            Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(Text::new("Path from"))
+                .push(
+                    PickList::new(
+                        from_state,
+                        names.clone(),
+                        from.clone(),
+                        NodeTabMessage::PathFrom,
+                    )
+                    .style(Theme::Blue)
+                    .padding(10)
+                    .width(Length::Fill),
+                )
+                .push(Text::new("to"))
+                .push(
+                    PickList::new(to_state, names, to.clone(), NodeTabMessage::PathTo)
+                        .style(Theme::Blue)
+                        .padding(10)
+                        .width(Length::Fill),
+                ),
+        )
+        .push(Text::new(message).font(CQ_MONO))
+        .into()
+}
+
 struct ConfigNode {
     name: String,
     error_message: Option<String>,
@@ -1165,11 +1499,17 @@ struct ConfigNode {
     x: String,
     y: String,
     z: String,
+    feed: String,
+    hover: String,
+    approach: String,
     modified_nodes: Rc<RefCell<Nodes>>,
     neighbors_pick_lists: Vec<StringPickList>,
     x_state: text_input::State,
     y_state: text_input::State,
     z_state: text_input::State,
+    feed_state: text_input::State,
+    hover_state: text_input::State,
+    approach_state: text_input::State,
     add_neighbor_btn: button::State,
     state: ConfigNodeState,
     edit_btn: button::State,
@@ -1185,6 +1525,8 @@ struct ConfigNodeErrors {
     number_blank: bool,
     not_numbers: bool,
     too_many_decimals: bool,
+    bad_feed: bool,
+    bad_heights: bool,
     home_required: bool,
     try_save_while_edit: bool,
 }
@@ -1197,6 +1539,8 @@ impl ConfigNodeErrors {
             number_blank: false,
             not_numbers: false,
             too_many_decimals: false,
+            bad_feed: false,
+            bad_heights: false,
             home_required: false,
             try_save_while_edit: false,
         }
@@ -1220,6 +1564,8 @@ impl ConfigNodeErrors {
             self.number_blank,
             self.not_numbers,
             self.too_many_decimals,
+            self.bad_feed,
+            self.bad_heights,
             self.home_required,
             self.try_save_while_edit,
         ]
@@ -1239,6 +1585,9 @@ pub enum ConfigNodeMessage {
     XChanged(String),
     YChanged(String),
     ZChanged(String),
+    FeedChanged(String),
+    HoverChanged(String),
+    ApproachChanged(String),
     Neighbors(usize, StringPickListMessage),
     AddNeighbor,
     Edit,
@@ -1253,7 +1602,11 @@ impl ConfigNode {
         x: f32,
         y: f32,
         z: f32,
+        feed: Option<f32>,
+        hover: Option<f32>,
+        approach: Option<f32>,
         neighbors: Vec<String>,
+        edges: Option<Vec<Edge>>,
         modified_nodes: Rc<RefCell<Nodes>>,
     ) -> Self {
         ConfigNode {
@@ -1267,11 +1620,21 @@ impl ConfigNode {
             x: x.to_string(),
             y: y.to_string(),
             z: z.to_string(),
+            feed: feed_string(feed),
+            hover: feed_string(hover),
+            approach: feed_string(approach),
             neighbors_pick_lists: neighbors.iter().filter(|n| !n.contains("_hover")).fold(
                 Vec::new(),
                 |mut v, n| {
                     v.push(StringPickList::new(
                         n.clone(),
+                        feed_string(
+                            edges
+                                .iter()
+                                .flatten()
+                                .find(|edge| edge.to == *n)
+                                .and_then(|edge| edge.feed),
+                        ),
                         Rc::clone(&modified_nodes),
                         name.clone(),
                         neighbors
@@ -1288,6 +1651,9 @@ impl ConfigNode {
             x_state: text_input::State::new(),
             y_state: text_input::State::new(),
             z_state: text_input::State::new(),
+            feed_state: text_input::State::new(),
+            hover_state: text_input::State::new(),
+            approach_state: text_input::State::new(),
             add_neighbor_btn: button::State::new(),
             state: ConfigNodeState::Idle,
             edit_btn: button::State::new(),
@@ -1305,6 +1671,27 @@ impl ConfigNode {
         self.error_message = None;
     }
 
+    fn check_feeds(&mut self) {
+        self.errors.bad_feed = parse_feed(&self.feed).is_err()
+            || self
+                .neighbors_pick_lists
+                .iter()
+                .any(|pick_list| parse_feed(&pick_list.feed).is_err());
+    }
+
+    // hover and approach are optional, but must be above the node with approach below hover
+    fn check_heights(&mut self) {
+        let z = self.z.parse::<f32>().unwrap_or(f32::MIN);
+        self.errors.bad_heights = match (parse_height(&self.hover), parse_height(&self.approach))
+        {
+            (Ok(hover), Ok(approach)) => {
+                let hover = hover.unwrap_or(HOVER_Z);
+                hover <= z || approach.map_or(false, |a| a <= z || a >= hover)
+            }
+            _ => true,
+        };
+    }
+
     fn update(&mut self, message: ConfigNodeMessage) {
         self.errors.clear_temp();
         // TODO: Disallow save if multiple nodes have same name
@@ -1349,6 +1736,7 @@ impl ConfigNode {
             }
             ConfigNodeMessage::ZChanged(z) => {
                 self.z = z;
+                self.check_heights();
                 if self.z.is_empty() {
                     self.errors.number_blank = true;
                 } else {
@@ -1362,8 +1750,25 @@ impl ConfigNode {
                     }
                 }
             }
+            ConfigNodeMessage::FeedChanged(feed) => {
+                self.feed = feed;
+                self.check_feeds();
+            }
+            ConfigNodeMessage::HoverChanged(hover) => {
+                self.hover = hover;
+                self.check_heights();
+            }
+            ConfigNodeMessage::ApproachChanged(approach) => {
+                self.approach = approach;
+                self.check_heights();
+            }
+            ConfigNodeMessage::Neighbors(i, StringPickListMessage::FeedChanged(feed)) => {
+                self.neighbors_pick_lists[i].update(StringPickListMessage::FeedChanged(feed));
+                self.check_feeds();
+            }
             ConfigNodeMessage::Neighbors(i, StringPickListMessage::Delete) => {
                 self.neighbors_pick_lists.remove(i);
+                self.check_feeds();
                 let siblings =
                     self.neighbors_pick_lists
                         .iter()
@@ -1406,6 +1811,7 @@ impl ConfigNode {
             }
             ConfigNodeMessage::AddNeighbor => self.neighbors_pick_lists.push(StringPickList::new(
                 "Choose Neighbor".to_string(),
+                String::new(),
                 Rc::clone(&self.modified_nodes),
                 self.name.clone(),
                 self.neighbors_pick_lists
@@ -1445,6 +1851,10 @@ impl ConfigNode {
             self.set_error("Positions cannot be empty.");
         } else if self.errors.too_many_decimals {
             self.set_error("Limit to 3 decimals.");
+        } else if self.errors.bad_feed {
+            self.set_error("Feeds must be positive numbers.");
+        } else if self.errors.bad_heights {
+            self.set_error("Approach must be between Z Pos and Hover.");
         } else if self.errors.try_save_while_edit {
             self.set_error("Click green checkbox before saving.");
         } else {
@@ -1579,6 +1989,60 @@ impl ConfigNode {
                                 .max_width(400),
                             ),
                     )
+                    .push(
+                        Row::new()
+                            .padding(5)
+                            .push(Text::new("Feed (cm/min):"))
+                            .push(Space::with_width(Length::Units(10)))
+                            .push(
+                                TextInput::new(
+                                    &mut self.feed_state,
+                                    "Default",
+                                    &self.feed,
+                                    ConfigNodeMessage::FeedChanged,
+                                )
+                                .style(Theme::Blue)
+                                .font(CQ_MONO)
+                                .padding(10)
+                                .max_width(400),
+                            ),
+                    )
+                    .push(
+                        Row::new()
+                            .padding(5)
+                            .push(Text::new("Hover (cm):"))
+                            .push(Space::with_width(Length::Units(10)))
+                            .push(
+                                TextInput::new(
+                                    &mut self.hover_state,
+                                    &HOVER_Z.to_string(),
+                                    &self.hover,
+                                    ConfigNodeMessage::HoverChanged,
+                                )
+                                .style(Theme::Blue)
+                                .font(CQ_MONO)
+                                .padding(10)
+                                .max_width(400),
+                            ),
+                    )
+                    .push(
+                        Row::new()
+                            .padding(5)
+                            .push(Text::new("Approach (cm):"))
+                            .push(Space::with_width(Length::Units(10)))
+                            .push(
+                                TextInput::new(
+                                    &mut self.approach_state,
+                                    "None",
+                                    &self.approach,
+                                    ConfigNodeMessage::ApproachChanged,
+                                )
+                                .style(Theme::Blue)
+                                .font(CQ_MONO)
+                                .padding(10)
+                                .max_width(400),
+                            ),
+                    )
                     .push(
                         Row::new()
                             .padding(5)
@@ -1684,6 +2148,48 @@ impl ConfigNode {
                         .push(Space::with_width(Length::Units(10)))
                         .push(Text::new(&self.z).font(CQ_MONO)),
                 )
+                .push(
+                    Row::new()
+                        .padding(5)
+                        .push(Text::new("Feed (cm/min):"))
+                        .push(Space::with_width(Length::Units(10)))
+                        .push(
+                            Text::new(if self.feed.is_empty() {
+                                "Default"
+                            } else {
+                                &self.feed
+                            })
+                            .font(CQ_MONO),
+                        ),
+                )
+                .push(
+                    Row::new()
+                        .padding(5)
+                        .push(Text::new("Hover (cm):"))
+                        .push(Space::with_width(Length::Units(10)))
+                        .push(
+                            Text::new(if self.hover.is_empty() {
+                                HOVER_Z.to_string()
+                            } else {
+                                self.hover.clone()
+                            })
+                            .font(CQ_MONO),
+                        ),
+                )
+                .push(
+                    Row::new()
+                        .padding(5)
+                        .push(Text::new("Approach (cm):"))
+                        .push(Space::with_width(Length::Units(10)))
+                        .push(
+                            Text::new(if self.approach.is_empty() {
+                                "None"
+                            } else {
+                                &self.approach
+                            })
+                            .font(CQ_MONO),
+                        ),
+                )
                 .push(
                     Row::new()
                         .padding(5)
@@ -1693,7 +2199,15 @@ impl ConfigNode {
                             self.neighbors_pick_lists.iter().fold(
                                 Column::new(),
                                 |col, pick_list| {
-                                    col.push(Text::new(pick_list.value.as_ref().unwrap()))
+                                    col.push(Text::new(if pick_list.feed.is_empty() {
+                                        pick_list.value.clone().unwrap()
+                                    } else {
+                                        format!(
+                                            "{} (F{})",
+                                            pick_list.value.as_ref().unwrap(),
+                                            pick_list.feed
+                                        )
+                                    }))
                                 },
                             ),
                         ),
@@ -1708,6 +2222,8 @@ impl ConfigNode {
 struct StringPickList {
     value: Option<String>,
     state: pick_list::State<String>,
+    feed: String,
+    feed_state: text_input::State,
     modified_nodes: Rc<RefCell<Nodes>>,
     delete_btn: button::State,
     parent: String,
@@ -1718,12 +2234,14 @@ struct StringPickList {
 #[derive(Debug, Clone)]
 pub enum StringPickListMessage {
     Changed(String),
+    FeedChanged(String),
     Delete,
 }
 
 impl StringPickList {
     fn new(
         value: String,
+        feed: String,
         modified_nodes: Rc<RefCell<Nodes>>,
         parent: String,
         siblings: Vec<String>,
@@ -1731,6 +2249,8 @@ impl StringPickList {
         StringPickList {
             value: Some(value),
             state: pick_list::State::default(),
+            feed,
+            feed_state: text_input::State::new(),
             modified_nodes,
             parent,
             siblings,
@@ -1750,6 +2270,7 @@ impl StringPickList {
     fn update(&mut self, message: StringPickListMessage) {
         match message {
             StringPickListMessage::Changed(s) => self.value = Some(s),
+            StringPickListMessage::FeedChanged(s) => self.feed = s,
             _ => {}
         }
     }
@@ -1782,6 +2303,18 @@ impl StringPickList {
                 .width(Length::Fill)
                 .padding(10),
             )
+            .push(
+                TextInput::new(
+                    &mut self.feed_state,
+                    "Feed",
+                    &self.feed,
+                    StringPickListMessage::FeedChanged,
+                )
+                .style(Theme::Blue)
+                .font(CQ_MONO)
+                .padding(10)
+                .width(Length::Units(70)),
+            )
             .push(if self.delete_disabled {
                 Tooltip::new(
                     Button::new(&mut self.delete_btn, delete_icon())
@@ -2396,6 +2929,8 @@ struct LogTab {
     unsearched_files: Vec<String>,
     search_bars: Vec<SearchBar>,
     date_regex: Regex,
+    export_btn: button::State,
+    export_message: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -2403,6 +2938,7 @@ pub enum LogTabMessage {
     SearchChanged(usize, SearchBarMessage),
     Log(usize, LogMessage),
     AddLog((Vec<String>, Option<Log>)),
+    Export,
 }
 
 impl LogTab {
@@ -2431,6 +2967,8 @@ impl LogTab {
             unsearched_files: Vec::new(),
             search_bars: vec![SearchBar::new(0)],
             date_regex,
+            export_btn: button::State::new(),
+            export_message: None,
         }
     }
 
@@ -2554,6 +3092,22 @@ impl LogTab {
                 self.logs[i].update(msg);
                 Command::none()
             }
+            LogTabMessage::Export => {
+                // export the runs that are currently listed, search to narrow them down
+                let titles = self
+                    .logs
+                    .iter()
+                    .take(LOG_MAX)
+                    .filter(|log| log.title.contains("; Run - "))
+                    .map(|log| log.title.clone())
+                    .collect();
+                self.export_message = Some(match traveler::export(&titles) {
+                    Ok((_, 0)) => "No runs listed below to export.".to_string(),
+                    Ok((path, count)) => format!("Exported {} run(s) to {}", count, path),
+                    Err(_) => "Unable to write the traveler CSV.".to_string(),
+                });
+                Command::none()
+            }
         }
     }
 
@@ -2586,6 +3140,27 @@ impl LogTab {
                         )
                     }),
             )
+            .push(
+                Row::new()
+                    .padding(10)
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(
+                        Button::new(
+                            &mut self.export_btn,
+                            Text::new("Export Traveler CSV")
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .style(Theme::Blue)
+                        .padding(10)
+                        .width(Length::Units(250))
+                        .on_press(LogTabMessage::Export),
+                    )
+                    .push(
+                        Text::new(self.export_message.as_ref().unwrap_or(&String::new()))
+                            .font(CQ_MONO),
+                    ),
+            )
             .push(logs.enumerate().fold(Column::new(), |col, (i, log)| {
                 log.set_style(if i % 2 == 0 {
                     Theme::Blue
@@ -2875,6 +3450,29 @@ pub fn validate_nums(nums: Vec<&String>, count: usize) -> ValidateNums {
     }
 }
 
+// blank feeds fall back to the next feed rate in line, see paths::feed_rate
+fn parse_feed(feed: &str) -> Result<Option<f32>, ()> {
+    if feed.trim().is_empty() {
+        return Ok(None);
+    }
+    match feed.trim().parse::<f32>() {
+        Ok(f) if f > 0.0 => Ok(Some(f)),
+        _ => Err(()),
+    }
+}
+
+// blank for none, any number otherwise since heights are usually negative
+fn parse_height(height: &str) -> Result<Option<f32>, ()> {
+    if height.trim().is_empty() {
+        return Ok(None);
+    }
+    height.trim().parse::<f32>().map(Some).map_err(|_| ())
+}
+
+fn feed_string(feed: Option<f32>) -> String {
+    feed.map(|f| f.to_string()).unwrap_or(String::new())
+}
+
 pub enum ValidateNums {
     Okay,
     NotNums,
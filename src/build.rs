@@ -1,7 +1,9 @@
 use super::actions::Actions;
 use super::advanced::{validate_nums, SaveBar, SaveBarMessage, ValidateNums};
+use super::dryrun::dry_run;
+use super::history::{self, changed_fields, describe_step, diff_steps, DiffKind, Revision};
 use super::logger::{replace_os_char, Logger};
-use super::nodes::Nodes;
+use super::nodes::{get_nodemap, is_generated, Nodes};
 use super::run::{do_nothing, Step};
 use super::style::style::Theme;
 use crate::{TabState, CQ_MONO};
@@ -16,8 +18,6 @@ use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
-use std::fs::OpenOptions;
-use std::io::Write;
 use std::path::Path;
 use std::rc::Rc;
 //use std::thread;
@@ -54,6 +54,11 @@ pub struct Build {
     logger: Logger,
     unsaved_tabs: Rc<RefCell<HashMap<TabState, bool>>>,
     recipe_regex: Regex,
+    review_confirm_btn: button::State,
+    history_btn: button::State,
+    history: Vec<Revision>,
+    history_btns: Vec<(button::State, button::State)>,
+    selected_revision: Option<usize>,
 }
 
 enum BuildState {
@@ -62,6 +67,8 @@ enum BuildState {
     DeleteConfirm,
     EnterName,
     OverwriteConfirm,
+    History,
+    Review,
 }
 
 #[derive(Debug, Clone)]
@@ -84,6 +91,10 @@ pub enum BuildMessage {
     ConfirmDelete,
     SaveWithName,
     NameEntryChanged(String),
+    History,
+    ShowRevision(usize),
+    Rollback(usize),
+    ReviewConfirmed,
 }
 
 impl Build {
@@ -124,6 +135,11 @@ impl Build {
             logger,
             unsaved_tabs,
             recipe_regex: Regex::new(r"^[^.]+").unwrap(),
+            review_confirm_btn: button::State::new(),
+            history_btn: button::State::new(),
+            history: Vec::new(),
+            history_btns: Vec::new(),
+            selected_revision: None,
         }
     }
 
@@ -132,6 +148,8 @@ impl Build {
         match message {
             BuildMessage::Cancel => {
                 self.name_entry_value = String::new();
+                // a failed rollback leaves its reason here
+                self.save_bar.message = "Unsaved Changes!".to_string();
                 self.state = BuildState::Steps;
             }
             BuildMessage::StepMessage(i, StepMessage::Delete) => {
@@ -265,6 +283,27 @@ impl Build {
             BuildMessage::ConfirmDelete => {
                 self.state = BuildState::DeleteConfirm;
             }
+            BuildMessage::History => {
+                if self.unsaved {
+                    self.save_bar.message = "Save or Cancel before viewing history!".to_string();
+                } else {
+                    update_history(self);
+                    self.selected_revision = None;
+                    self.save_bar.message = String::new();
+                    self.state = BuildState::History;
+                }
+            }
+            BuildMessage::ShowRevision(i) => {
+                self.selected_revision = if self.selected_revision == Some(i) {
+                    None
+                } else {
+                    Some(i)
+                };
+            }
+            BuildMessage::Rollback(i) => match rollback(self, i) {
+                Ok(()) => command = Command::perform(do_nothing(), BuildMessage::Saved),
+                Err(err) => self.save_bar.message = err,
+            },
             BuildMessage::DeleteConfirmed => {
                 std::fs::remove_file(format!(
                     "./recipes/{}.toml",
@@ -319,11 +358,34 @@ impl Build {
                 {
                     self.save_bar.message = "'Ok' all steps before saving".to_string();
                 } else {
-                    self.save_bar.message = "Unsaved Changes!".to_string();
-                    self.name_entry_value = self.search_value.clone().unwrap_or(String::new());
-                    self.state = BuildState::EnterName;
+                    // moves outside the soft limits are never saved, the run tab would refuse them
+                    let steps = recipe_data(
+                        &self.modified_steps,
+                        &self.modified_before_inputs,
+                        &self.modified_after_inputs,
+                    )
+                    .steps
+                    .unwrap_or(Vec::new());
+                    let nodes = self.nodes_ref.borrow();
+                    let preview = dry_run(
+                        &steps,
+                        &*nodes,
+                        &get_nodemap(&*nodes),
+                        &*self.actions_ref.borrow(),
+                    );
+                    match preview.violations.first() {
+                        Some(violation) => self.save_bar.message = violation.clone(),
+                        None => {
+                            self.save_bar.message = "Unsaved Changes!".to_string();
+                            self.state = BuildState::Review;
+                        }
+                    }
                 }
             }
+            BuildMessage::ReviewConfirmed => {
+                self.name_entry_value = self.search_value.clone().unwrap_or(String::new());
+                self.state = BuildState::EnterName;
+            }
             BuildMessage::ConfirmOverWrite => {
                 save(self);
                 command = Command::perform(do_nothing(), BuildMessage::Saved);
@@ -341,6 +403,8 @@ impl Build {
         command
     }
     pub fn view(&mut self) -> Element<BuildMessage> {
+        // the save bar is borrowed for the rest of view, History shows its message on its own
+        let save_message = self.save_bar.message.clone();
         let save_bar = match self.unsaved {
             true => Column::new().align_items(Align::Center).push(
                 self.save_bar
@@ -365,14 +429,24 @@ impl Build {
                 .width(Length::Fill),
             )
             .push(match self.search_value {
-                Some(_) => Row::with_children(vec![Button::new(
-                    &mut self.confirm_delete_btn,
-                    Text::new("Delete Recipe").font(CQ_MONO).size(20),
-                )
-                .padding(10)
-                .style(Theme::Red)
-                .on_press(BuildMessage::ConfirmDelete)
-                .into()]),
+                Some(_) => Row::with_children(vec![
+                    Button::new(
+                        &mut self.history_btn,
+                        Text::new("History").font(CQ_MONO).size(20),
+                    )
+                    .padding(10)
+                    .style(Theme::Blue)
+                    .on_press(BuildMessage::History)
+                    .into(),
+                    Button::new(
+                        &mut self.confirm_delete_btn,
+                        Text::new("Delete Recipe").font(CQ_MONO).size(20),
+                    )
+                    .padding(10)
+                    .style(Theme::Red)
+                    .on_press(BuildMessage::ConfirmDelete)
+                    .into(),
+                ]),
                 None => Row::new(),
             });
         let tab_btns = Column::new().align_items(Align::Center).push(
@@ -668,6 +742,160 @@ impl Build {
                     .push(Container::new(content).width(Length::Fill).center_x())
                     .into()
             }
+            BuildState::Review => {
+                let old = match self.search_value {
+                    Some(_) => Some(recipe_data(
+                        &self.steps,
+                        &self.before_inputs,
+                        &self.after_inputs,
+                    )),
+                    None => None,
+                };
+                let new = recipe_data(
+                    &self.modified_steps,
+                    &self.modified_before_inputs,
+                    &self.modified_after_inputs,
+                );
+                let content = Column::new()
+                    .max_width(1000)
+                    .spacing(20)
+                    .align_items(Align::Center)
+                    .push(Text::new("REVIEW CHANGES").font(CQ_MONO).size(40))
+                    .push(
+                        Text::new(format!(
+                            "'{}'",
+                            self.search_value.as_ref().unwrap_or(&"New Recipe".to_string())
+                        ))
+                        .size(30),
+                    )
+                    .push(diff_view(old.as_ref(), &new))
+                    .push(Row::with_children(vec![
+                        Space::with_width(Length::Fill).into(),
+                        Button::new(
+                            &mut self.review_confirm_btn,
+                            Text::new("Continue to Save")
+                                .font(CQ_MONO)
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .style(Theme::Green)
+                        .on_press(BuildMessage::ReviewConfirmed)
+                        .padding(10)
+                        .width(Length::Units(200))
+                        .into(),
+                        Space::with_width(Length::Units(100)).into(),
+                        Button::new(
+                            &mut self.cancel_btn,
+                            Text::new("Back")
+                                .font(CQ_MONO)
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .style(Theme::Blue)
+                        .on_press(BuildMessage::Cancel)
+                        .width(Length::Units(200))
+                        .padding(10)
+                        .into(),
+                        Space::with_width(Length::Fill).into(),
+                    ]));
+                Scrollable::new(&mut self.scroll)
+                    .padding(40)
+                    .push(Container::new(content).width(Length::Fill).center_x())
+                    .into()
+            }
+            BuildState::History => {
+                let selected = self.selected_revision;
+                let history = &self.history;
+                let mut content = Column::new()
+                    .max_width(800)
+                    .spacing(20)
+                    .align_items(Align::Center)
+                    .push(Text::new("HISTORY").font(CQ_MONO).size(40))
+                    .push(
+                        Text::new(format!(
+                            "'{}'",
+                            self.search_value.as_ref().unwrap_or(&String::new())
+                        ))
+                        .size(30),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.cancel_btn,
+                            Text::new("Back")
+                                .font(CQ_MONO)
+                                .horizontal_alignment(HorizontalAlignment::Center),
+                        )
+                        .style(Theme::Blue)
+                        .on_press(BuildMessage::Cancel)
+                        .width(Length::Units(200))
+                        .padding(10),
+                    );
+                if !save_message.is_empty() {
+                    content = content.push(
+                        Text::new(save_message)
+                            .size(20)
+                            .color([0.8, 0.0, 0.0]),
+                    );
+                }
+                if history.is_empty() {
+                    content = content.push(Text::new("No revisions saved yet.").size(20));
+                }
+                for (i, (revision, (diff_btn, rollback_btn))) in history
+                    .iter()
+                    .zip(self.history_btns.iter_mut())
+                    .enumerate()
+                {
+                    let row = Row::new()
+                        .spacing(10)
+                        .align_items(Align::Center)
+                        .push(
+                            Text::new(format!(
+                                "Revision {}{}\n{} by {}",
+                                revision.revision,
+                                if i == 0 { " (current)" } else { "" },
+                                revision.time,
+                                revision.author
+                            ))
+                            .width(Length::Fill),
+                        )
+                        .push(
+                            Button::new(
+                                diff_btn,
+                                Text::new(match selected == Some(i) {
+                                    true => "Hide Changes",
+                                    false => "Changes",
+                                })
+                                .font(CQ_MONO),
+                            )
+                            .style(Theme::Blue)
+                            .on_press(BuildMessage::ShowRevision(i))
+                            .padding(10),
+                        )
+                        .push(match i {
+                            0 => Button::new(rollback_btn, Text::new("Roll Back").font(CQ_MONO))
+                                .style(Theme::RedDisabled)
+                                .padding(10),
+                            _ => Button::new(rollback_btn, Text::new("Roll Back").font(CQ_MONO))
+                                .style(Theme::Red)
+                                .on_press(BuildMessage::Rollback(i))
+                                .padding(10),
+                        });
+                    content = content.push(
+                        Container::new(row)
+                            .padding(10)
+                            .width(Length::Fill)
+                            .style(match i % 2 {
+                                0 => Theme::LightGray,
+                                _ => Theme::LighterGray,
+                            }),
+                    );
+                    if selected == Some(i) {
+                        content = content.push(revision_diff(revision, history.get(i + 1)));
+                    }
+                }
+                Scrollable::new(&mut self.scroll)
+                    .padding(40)
+                    .push(Container::new(content).width(Length::Fill).center_x())
+                    .into()
+            }
             BuildState::OverwriteConfirm => {
                 let content = Column::new()
                     .max_width(800)
@@ -713,6 +941,7 @@ impl Build {
 #[derive(Serialize, Deserialize, Debug)]
 // Step found in ./run.rs
 pub struct Recipe {
+    pub revision: Option<u32>,
     pub required_inputs: Input,
     pub steps: Vec<Step>,
 }
@@ -723,14 +952,16 @@ pub struct Input {
     pub after: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 // Step found in ./run.rs
 pub struct SaveRecipe {
+    // set by history::save_revision, None for recipes saved before revisions were kept
+    pub revision: Option<u32>,
     pub required_inputs: SaveInput,
     pub steps: Option<Vec<Step>>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SaveInput {
     pub before: Option<Vec<String>>,
     pub after: Option<Vec<String>>,
@@ -1148,7 +1379,7 @@ impl BuildStep {
                                                 .borrow()
                                                 .node
                                                 .iter()
-                                                .filter(|n| !n.name.contains("_hover") && !n.hide)
+                                                .filter(|n| !is_generated(&n.name) && !n.hide)
                                                 .fold(Vec::new(), |mut v, n| {
                                                     v.push(n.name.clone());
                                                     v
@@ -1667,7 +1898,7 @@ impl AddStep {
                                     .borrow()
                                     .node
                                     .iter()
-                                    .filter(|n| !n.name.contains("_hover") && !n.hide)
+                                    .filter(|n| !is_generated(&n.name) && !n.hide)
                                     .fold(Vec::new(), |mut v, n| {
                                         v.push(n.name.clone());
                                         v
@@ -1861,6 +2092,7 @@ fn update_recipe(tab: &mut Build) {
         Ok(toml_str) => {
             let save_rec: SaveRecipe = toml::from_str(toml_str).unwrap();
             let rec = Recipe {
+                revision: save_rec.revision,
                 required_inputs: Input {
                     before: if let Some(b) = save_rec.required_inputs.before {
                         b
@@ -1938,52 +2170,11 @@ fn save(tab: &mut Build) {
             .retain(|input| input.value != "".to_string());
         tab.modified_after_inputs
             .retain(|input| input.value != "".to_string());
-        let save_data = SaveRecipe {
-            required_inputs: SaveInput {
-                before: if tab.modified_before_inputs.len() > 0 {
-                    Some(tab.modified_before_inputs.iter().fold(
-                        Vec::with_capacity(tab.modified_before_inputs.len()),
-                        |mut v, input| {
-                            v.push(input.value.clone());
-                            v
-                        },
-                    ))
-                } else {
-                    None
-                },
-                after: if tab.modified_after_inputs.len() > 0 {
-                    Some(tab.modified_after_inputs.iter().fold(
-                        Vec::with_capacity(tab.modified_after_inputs.len()),
-                        |mut v, input| {
-                            v.push(input.value.clone());
-                            v
-                        },
-                    ))
-                } else {
-                    None
-                },
-            },
-            steps: if tab.modified_steps.len() > 0 {
-                Some(tab.modified_steps.iter().fold(
-                    Vec::with_capacity(tab.modified_steps.len()),
-                    |mut v, step| {
-                        v.push(Step {
-                            step_num: step.step_num.unwrap().to_string(),
-                            selected_destination: step.selected_destination.clone().unwrap(),
-                            hover: step.hover,
-                            selected_action: step.selected_action.clone().unwrap(),
-                            secs_value: step.secs_value.clone(),
-                            mins_value: step.mins_value.clone(),
-                            hours_value: step.hours_value.clone(),
-                            wait: step.wait,
-                        });
-                        v
-                    },
-                ))
-            } else {
-                None
-            },
-        };
+        let mut save_data = recipe_data(
+            &tab.modified_steps,
+            &tab.modified_before_inputs,
+            &tab.modified_after_inputs,
+        );
         // TODO: add error is unable to build toml
         let old_recipe = fs::read_to_string(Path::new(&format!(
             "./recipes/{}.toml",
@@ -1991,28 +2182,38 @@ fn save(tab: &mut Build) {
         )))
         .unwrap_or(format!("No old recipe '{}' is new", tab.name_entry_value));
         tab.name_entry_value = replace_os_char(tab.name_entry_value.clone());
+        // keep the recipe being replaced if it was saved before history was kept
+        if history::revisions(&tab.name_entry_value).is_empty() {
+            if let Ok(toml_str) =
+                fs::read_to_string(format!("./recipes/{}.toml", tab.name_entry_value))
+            {
+                if let Ok(mut old_data) = toml::from_str::<SaveRecipe>(&toml_str) {
+                    history::save_revision(&tab.name_entry_value, &mut old_data).unwrap_or(0);
+                }
+            }
+        }
+        save_data.revision = Some(history::next_revision(&tab.name_entry_value));
         let new_recipe = toml::to_string_pretty(&save_data).unwrap();
-        match OpenOptions::new().write(true).open(Path::new(&format!(
-            "./recipes/{}.toml",
-            &tab.name_entry_value.replace(" ", ""),
-        ))) {
-            Ok(_file) => {
+        let path = format!("./recipes/{}.toml", tab.name_entry_value);
+        let existed = Path::new(&path).exists();
+        if let Err(err) = fs::write(&path, new_recipe.clone()) {
+            // still unsaved, the save bar shows why
+            tab.save_bar.message = format!("Unable to save '{}', {}", tab.name_entry_value, err);
+            tab.state = BuildState::Steps;
+            return;
+        }
+        // only numbered in the history once the recipe it belongs to is written
+        let revision = history::record_revision(&tab.name_entry_value, &save_data);
+        match existed {
+            true => {
                 // file already exists, thus we need to log that recipe was changed
-                // write!(file, "{}", new_recipe).unwrap();
-                fs::write(
-                    format!(
-                        "./recipes/{}.toml",
-                        replace_os_char(tab.name_entry_value.clone())
-                    ),
-                    new_recipe.clone(),
-                )
-                .unwrap();
                 tab.logger.set_log_file(format!(
                     "{}; Build - Changed '{}'",
                     Local::now().to_rfc2822(),
                     tab.name_entry_value,
                 ));
                 tab.logger.send_line(String::new()).unwrap();
+                log_revision(tab, revision);
                 tab.logger
                     .send_line("Recipe changed from:".to_string())
                     .unwrap();
@@ -2022,23 +2223,15 @@ fn save(tab: &mut Build) {
                     .unwrap();
                 tab.logger.send_line(new_recipe).unwrap();
             }
-            Err(_) => {
+            false => {
                 // new file thus new recip/e, log should state created recipe
-                let mut file = OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .open(Path::new(&format!(
-                        "./recipes/{}.toml",
-                        &tab.name_entry_value
-                    )))
-                    .unwrap();
-                write!(file, "{}", new_recipe).unwrap();
                 tab.logger.set_log_file(format!(
                     "{}; Build - Created '{}'",
                     Local::now().to_rfc2822(),
                     tab.name_entry_value
                 ));
                 tab.logger.send_line(String::new()).unwrap();
+                log_revision(tab, revision);
                 tab.logger
                     .send_line(format!("Created Recipe '{}' as:", tab.name_entry_value))
                     .unwrap();
@@ -2067,6 +2260,253 @@ fn save(tab: &mut Build) {
         update_recipe(tab);
     }
 }
+fn log_revision(tab: &Build, revision: Result<u32, ()>) {
+    tab.logger
+        .send_line(match revision {
+            Ok(revision) => format!("Saved as revision {}", revision),
+            Err(_) => "Unable to save revision history!".to_string(),
+        })
+        .unwrap();
+}
+
+// changes made by new compared to the revision before it, old is None for the first revision
+fn revision_diff<'a>(new: &Revision, old: Option<&Revision>) -> Column<'a, BuildMessage> {
+    Column::new()
+        .spacing(5)
+        .width(Length::Fill)
+        .push(
+            Text::new(match old {
+                Some(old) => format!(
+                    "Changes from revision {} to revision {}",
+                    old.revision, new.revision
+                ),
+                None => format!("Revision {} as first saved", new.revision),
+            })
+            .font(CQ_MONO),
+        )
+        .push(diff_view(old.map(|old| &old.recipe), &new.recipe))
+}
+
+// side by side step level diff of two recipes, old on the left
+fn diff_view<'a>(old: Option<&SaveRecipe>, new: &SaveRecipe) -> Column<'a, BuildMessage> {
+    let no_steps = Vec::new();
+    let new_steps = new.steps.as_ref().unwrap_or(&no_steps);
+    let old_steps = match old {
+        Some(old) => old.steps.as_ref().unwrap_or(&no_steps),
+        None => &no_steps,
+    };
+    let no_inputs = SaveInput {
+        before: None,
+        after: None,
+    };
+    let old_inputs = old.map(|old| &old.required_inputs).unwrap_or(&no_inputs);
+    let new_inputs = &new.required_inputs;
+    let mut changed = false;
+    let mut diff_column = Column::new()
+        .spacing(5)
+        .width(Length::Fill)
+        .push(diff_row(
+            "Saved".to_string(),
+            "New".to_string(),
+            Theme::LightGray,
+        ));
+    for (title, old_list, new_list) in vec![
+        ("Before", &old_inputs.before, &new_inputs.before),
+        ("After", &old_inputs.after, &new_inputs.after),
+    ] {
+        if old_list != new_list {
+            changed = true;
+            diff_column = diff_column.push(diff_row(
+                format!(
+                    "{} inputs: {}",
+                    title,
+                    old_list.clone().unwrap_or(Vec::new()).join(", ")
+                ),
+                format!(
+                    "{} inputs: {}",
+                    title,
+                    new_list.clone().unwrap_or(Vec::new()).join(", ")
+                ),
+                Theme::Yellow,
+            ));
+        }
+    }
+    for step_diff in diff_steps(old_steps, new_steps) {
+        let old_text = step_diff
+            .old
+            .as_ref()
+            .map(|step| describe_step(step))
+            .unwrap_or(String::new());
+        let new_text = step_diff
+            .new
+            .as_ref()
+            .map(|step| describe_step(step))
+            .unwrap_or(String::new());
+        diff_column = diff_column.push(match step_diff.kind {
+            DiffKind::Same => diff_row(old_text, new_text, Theme::LighterGray),
+            DiffKind::Added => diff_row(old_text, format!("+ {}", new_text), Theme::Green),
+            DiffKind::Removed => diff_row(format!("- {}", old_text), new_text, Theme::Red),
+            DiffKind::Changed => diff_row(
+                old_text,
+                format!(
+                    "~ {}\nchanged {}",
+                    new_text,
+                    changed_fields(
+                        step_diff.old.as_ref().unwrap(),
+                        step_diff.new.as_ref().unwrap()
+                    )
+                    .join(", ")
+                ),
+                Theme::Yellow,
+            ),
+            DiffKind::Moved => diff_row(
+                old_text,
+                format!(
+                    "> {}\nmoved from step {}",
+                    new_text,
+                    step_diff.old.as_ref().unwrap().step_num
+                ),
+                Theme::LightGrayHighlight,
+            ),
+        });
+        if step_diff.kind != DiffKind::Same {
+            changed = true;
+        }
+    }
+    if !changed {
+        diff_column = diff_column.push(Text::new("No changes to steps or required inputs."));
+    }
+    diff_column
+}
+
+fn diff_row<'a>(old: String, new: String, style: Theme) -> Row<'a, BuildMessage> {
+    Row::new()
+        .spacing(5)
+        .push(
+            Container::new(Text::new(old).font(CQ_MONO))
+                .padding(10)
+                .width(Length::FillPortion(1))
+                .style(style),
+        )
+        .push(
+            Container::new(Text::new(new).font(CQ_MONO))
+                .padding(10)
+                .width(Length::FillPortion(1))
+                .style(style),
+        )
+}
+
+// the recipe as it would be written by save
+fn recipe_data(
+    steps: &Vec<BuildStep>,
+    before_inputs: &Vec<RequiredInput>,
+    after_inputs: &Vec<RequiredInput>,
+) -> SaveRecipe {
+    let input_values = |inputs: &Vec<RequiredInput>| {
+        let values: Vec<String> = inputs
+            .iter()
+            .filter(|input| input.value != "".to_string())
+            .map(|input| input.value.clone())
+            .collect();
+        if values.len() > 0 {
+            Some(values)
+        } else {
+            None
+        }
+    };
+    SaveRecipe {
+        revision: None,
+        required_inputs: SaveInput {
+            before: input_values(before_inputs),
+            after: input_values(after_inputs),
+        },
+        steps: if steps.len() > 0 {
+            Some(
+                steps
+                    .iter()
+                    .map(|step| Step {
+                        step_num: step.step_num.unwrap_or(0).to_string(),
+                        selected_destination: step
+                            .selected_destination
+                            .clone()
+                            .unwrap_or(String::new()),
+                        hover: step.hover,
+                        selected_action: step.selected_action.clone().unwrap_or(String::new()),
+                        secs_value: step.secs_value.clone(),
+                        mins_value: step.mins_value.clone(),
+                        hours_value: step.hours_value.clone(),
+                        wait: step.wait,
+                    })
+                    .collect(),
+            )
+        } else {
+            None
+        },
+    }
+}
+
+fn update_history(tab: &mut Build) {
+    tab.history = history::revisions(tab.search_value.as_ref().unwrap_or(&String::new()));
+    tab.history_btns = tab
+        .history
+        .iter()
+        .map(|_| (button::State::new(), button::State::new()))
+        .collect();
+}
+
+// save an old revision as the newest one, so the rollback itself is kept in the history
+fn rollback(tab: &mut Build, i: usize) -> Result<(), String> {
+    let name = match &tab.search_value {
+        Some(name) => name.clone(),
+        None => return Ok(()),
+    };
+    let old_revision = tab.history[i].revision;
+    let old_recipe = fs::read_to_string(format!("./recipes/{}.toml", name))
+        .unwrap_or(format!("No old recipe '{}'", name));
+    let mut recipe = tab.history[i].recipe.clone();
+    // the same check as Save, the nodes or limits may have changed since this revision
+    {
+        let nodes = tab.nodes_ref.borrow();
+        let preview = dry_run(
+            &recipe.steps.clone().unwrap_or(Vec::new()),
+            &*nodes,
+            &get_nodemap(&*nodes),
+            &*tab.actions_ref.borrow(),
+        );
+        if let Some(violation) = preview.violations.first() {
+            return Err(violation.clone());
+        }
+    }
+    recipe.revision = Some(history::next_revision(&name));
+    let new_recipe = toml::to_string_pretty(&recipe).unwrap();
+    fs::write(format!("./recipes/{}.toml", name), new_recipe.clone())
+        .map_err(|err| format!("Unable to roll back '{}', {}", name, err))?;
+    let revision = history::record_revision(&name, &recipe);
+    tab.logger.set_log_file(format!(
+        "{}; Build - Rolled back '{}'",
+        Local::now().to_rfc2822(),
+        name
+    ));
+    tab.logger.send_line(String::new()).unwrap();
+    tab.logger
+        .send_line(format!("Rolled back to revision {}", old_revision))
+        .unwrap();
+    log_revision(tab, revision);
+    tab.logger
+        .send_line("Recipe changed from:".to_string())
+        .unwrap();
+    tab.logger.send_line(old_recipe).unwrap();
+    tab.logger
+        .send_line("\n\n\nRecipe changed to:".to_string())
+        .unwrap();
+    tab.logger.send_line(new_recipe).unwrap();
+    update_recipe(tab);
+    update_history(tab);
+    tab.selected_revision = None;
+    tab.save_bar.message = String::new();
+    Ok(())
+}
+
 fn update_search(tab: &mut Build) {
     tab.search_options = fs::read_dir("./recipes")
         .unwrap()
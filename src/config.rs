@@ -0,0 +1,127 @@
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+pub const BATHS: &str = "config/baths.toml";
+pub const ACTIONS: &str = "config/actions.toml";
+pub const LIMITS: &str = "config/limits.toml";
+pub const SERIAL: &str = "config/serial.toml";
+// a HOME node, enough to start Bathtub and set the rest up in Advanced
+pub const DEFAULT_BATHS: &str =
+    "[[node]]\nname = 'HOME'\nx = -1.0\ny = -1.0\nz = -1.0\nhide = true\nneighbors = []\n";
+// copies of the config files as they were the last time Bathtub started with them
+const BACKUP_DIR: &str = "config/last_good";
+
+// a config file that could not be read or parsed
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    pub file: String,
+    // line number and text of the mistake, if toml could tell where it is
+    pub line: Option<(usize, String)>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.line {
+            Some((number, text)) => write!(
+                f,
+                "{} line {}: {}\n    {}",
+                self.file,
+                number,
+                self.message,
+                text.trim()
+            ),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+impl ConfigError {
+    pub fn new(file: &str, message: &str) -> ConfigError {
+        ConfigError {
+            file: file.to_string(),
+            line: None,
+            message: message.to_string(),
+        }
+    }
+}
+
+pub fn read<T: DeserializeOwned>(file: &str) -> Result<T, ConfigError> {
+    let toml_str = match fs::read_to_string(file) {
+        Ok(toml_str) => toml_str,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(ConfigError::new(file, "File not found"))
+        }
+        Err(err) => return Err(ConfigError::new(file, &err.to_string())),
+    };
+    toml::from_str(&toml_str).map_err(|err| ConfigError {
+        file: file.to_string(),
+        // toml counts lines from 0
+        line: err.line_col().map(|(line, _)| {
+            (
+                line + 1,
+                toml_str.lines().nth(line).unwrap_or("").to_string(),
+            )
+        }),
+        // the line is already given, drop toml's " at line 3 column 5"
+        message: match err.to_string().rfind(" at line ") {
+            Some(i) => err.to_string()[..i].to_string(),
+            None => err.to_string(),
+        },
+    })
+}
+
+fn backup_path(file: &str) -> String {
+    format!(
+        "{}/{}",
+        BACKUP_DIR,
+        Path::new(file).file_name().unwrap().to_string_lossy()
+    )
+}
+
+// keep a copy of a file that loaded without errors
+pub fn save_backup(file: &str) {
+    if Path::new(file).exists() {
+        fs::create_dir_all(BACKUP_DIR).unwrap_or(());
+        fs::copy(file, backup_path(file)).unwrap_or(0);
+    }
+}
+
+pub fn has_backup(file: &str) -> bool {
+    Path::new(&backup_path(file)).exists()
+}
+
+// the broken file is kept as <file>.broken so nothing typed into it is lost
+fn set_aside(file: &str) {
+    if Path::new(file).exists() {
+        fs::copy(file, format!("{}.broken", file)).unwrap_or(0);
+    }
+}
+
+pub fn restore_backup(file: &str) -> Result<(), ()> {
+    set_aside(file);
+    match fs::copy(backup_path(file), file) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(()),
+    }
+}
+
+// no actions and no limits are valid too
+pub fn default(file: &str) -> &'static str {
+    match file {
+        BATHS => DEFAULT_BATHS,
+        ACTIONS => "action = []\n",
+        _ => "",
+    }
+}
+
+pub fn create_default(file: &str) -> Result<(), ()> {
+    set_aside(file);
+    fs::create_dir_all("config").unwrap_or(());
+    match fs::write(file, default(file)) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(()),
+    }
+}
@@ -0,0 +1,180 @@
+use super::serial::{self, PortId, SerialSettings, DEFAULT_BAUD};
+use super::style::style::Theme;
+use crate::CQ_MONO;
+use iced::{
+    button, pick_list, text_input, Align, Button, Column, Element, HorizontalAlignment, Length,
+    PickList, Row, Space, Text, TextInput,
+};
+
+// shown while grbl cannot be reached, lets the user choose the port and baud rate
+pub struct Connect {
+    ports: Vec<(PortId, String)>,
+    selected: Option<String>,
+    port_state: pick_list::State<String>,
+    baud: String,
+    baud_state: text_input::State,
+    refresh_btn: button::State,
+    connect_btn: button::State,
+    message: String,
+    // why the last attempt did not find grbl
+    pub problem: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConnectMessage {
+    PortSelected(String),
+    BaudChanged(String),
+    Refresh,
+    Connect,
+}
+
+impl Connect {
+    pub fn new() -> Self {
+        let settings = SerialSettings::load();
+        let mut connect = Connect {
+            ports: Vec::new(),
+            selected: None,
+            port_state: pick_list::State::default(),
+            baud: settings.baud().to_string(),
+            baud_state: text_input::State::new(),
+            refresh_btn: button::State::new(),
+            connect_btn: button::State::new(),
+            message: String::new(),
+            problem: None,
+        };
+        connect.refresh();
+        connect
+    }
+
+    fn refresh(&mut self) {
+        self.ports = serial::available();
+        let settings = SerialSettings::load();
+        self.selected = settings.port.and_then(|preferred| {
+            self.ports
+                .iter()
+                .find(|(id, _)| preferred.matches(id))
+                .map(|(_, label)| label.clone())
+        });
+    }
+
+    pub fn update(&mut self, message: ConnectMessage) {
+        match message {
+            ConnectMessage::PortSelected(label) => self.selected = Some(label),
+            ConnectMessage::BaudChanged(baud) => self.baud = baud,
+            ConnectMessage::Refresh => {
+                self.refresh();
+                self.message = String::new();
+            }
+            ConnectMessage::Connect => match self.baud.trim().parse::<u32>() {
+                Ok(baud) if baud > 0 => {
+                    // tried first on the next attempt, and remembered once grbl answers on it
+                    let mut settings = SerialSettings::load();
+                    settings.baud = Some(baud);
+                    if let Some((id, label)) = self
+                        .ports
+                        .iter()
+                        .find(|(_, label)| Some(label) == self.selected.as_ref())
+                    {
+                        settings.port = Some(id.clone());
+                        self.message = format!("Looking for GRBL on {} . . .", label);
+                    } else {
+                        self.message = "Looking for GRBL . . .".to_string();
+                    }
+                    settings.save();
+                }
+                _ => self.message = "Baud rate must be a positive whole number.".to_string(),
+            },
+        }
+    }
+
+    pub fn view(&mut self) -> Element<'_, ConnectMessage> {
+        let mut content = Column::new()
+            .spacing(10)
+            .push(
+                Text::new("Unable to connect to GRBL.")
+                    .font(CQ_MONO)
+                    .size(50),
+            )
+            .push(Text::new("Here are some things to check:").size(25))
+            .push(Text::new("1) GRBL is powered on.").size(25))
+            .push(
+                Text::new("2) The USB cable is connected between this computer and GRBL.")
+                    .size(25),
+            )
+            .push(
+                Text::new("3) There are no other GRBL realted applications open on this PC.")
+                    .size(25),
+            )
+            .push(Text::new("4) You have asked GRBL to please work.").size(25))
+            .push(Space::with_height(Length::Units(20)))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new("Port:").size(25))
+                    .push(
+                        PickList::new(
+                            &mut self.port_state,
+                            self.ports
+                                .iter()
+                                .map(|(_, label)| label.clone())
+                                .collect::<Vec<String>>(),
+                            self.selected.clone(),
+                            ConnectMessage::PortSelected,
+                        )
+                        .style(Theme::Blue)
+                        .padding(10)
+                        .width(Length::Units(400)),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.refresh_btn,
+                            Text::new("Refresh")
+                                .horizontal_alignment(HorizontalAlignment::Center)
+                                .font(CQ_MONO),
+                        )
+                        .style(Theme::Blue)
+                        .padding(10)
+                        .on_press(ConnectMessage::Refresh),
+                    ),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new("Baud:").size(25))
+                    .push(
+                        TextInput::new(
+                            &mut self.baud_state,
+                            &DEFAULT_BAUD.to_string(),
+                            &self.baud,
+                            ConnectMessage::BaudChanged,
+                        )
+                        .style(Theme::Blue)
+                        .font(CQ_MONO)
+                        .padding(10)
+                        .width(Length::Units(200)),
+                    )
+                    .push(
+                        Button::new(
+                            &mut self.connect_btn,
+                            Text::new("Connect")
+                                .horizontal_alignment(HorizontalAlignment::Center)
+                                .font(CQ_MONO),
+                        )
+                        .style(Theme::Yellow)
+                        .padding(10)
+                        .on_press(ConnectMessage::Connect),
+                    ),
+            );
+        if !self.message.is_empty() {
+            content = content.push(Text::new(&self.message).size(20));
+        }
+        if let Some(problem) = &self.problem {
+            content = content
+                .push(Text::new("Last attempt:").size(20))
+                .push(Text::new(problem).font(CQ_MONO).size(16));
+        }
+        content.into()
+    }
+}
@@ -0,0 +1,196 @@
+use super::actions::Actions;
+use super::limits::position;
+use super::nodes::{Node, Nodes};
+use super::paths::{distance, feed_rate, gen_node_paths, jog_gcode};
+use super::run::Step;
+use std::collections::HashMap;
+
+// what the gantry would do for a recipe, without sending anything to grbl
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    pub steps: Vec<DryRunStep>,
+    pub errors: Vec<String>,
+    // moves outside the soft limits or through a keep-out zone, also listed in errors
+    pub violations: Vec<String>,
+    pub waits_for_input: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DryRunStep {
+    pub step_num: String,
+    pub destination: String,
+    pub action: String,
+    pub path: Vec<String>,
+    pub gcode: Vec<String>,
+    pub travel_secs: f32,
+    pub action_secs: f32,
+    pub repeats: bool,
+}
+
+impl DryRun {
+    pub fn total_secs(&self) -> f32 {
+        self.steps
+            .iter()
+            .fold(0.0, |total, step| total + step.travel_secs + step.action_secs)
+    }
+}
+
+// walk the recipe from HOME the same way run_recipe does
+pub fn dry_run(
+    steps: &Vec<Step>,
+    nodes: &Nodes,
+    node_map: &HashMap<String, usize>,
+    actions: &Actions,
+) -> DryRun {
+    let mut dry_run = DryRun {
+        steps: Vec::with_capacity(steps.len()),
+        errors: Vec::new(),
+        violations: Vec::new(),
+        waits_for_input: false,
+    };
+    let mut current = match node_map.get(&"HOME".to_string()) {
+        Some(i) => nodes.node[*i].clone(),
+        None => {
+            dry_run
+                .errors
+                .push("'HOME' required by Bathtub, not found in nodes".to_string());
+            return dry_run;
+        }
+    };
+    for (i, step) in steps.iter().enumerate() {
+        let destination = format!(
+            "{}{}",
+            step.selected_destination,
+            if step.hover { "_hover" } else { "" }
+        );
+        let mut dry_step = DryRunStep {
+            step_num: step.step_num.clone(),
+            destination: destination.clone(),
+            action: step.selected_action.clone(),
+            path: Vec::new(),
+            gcode: Vec::new(),
+            travel_secs: 0.0,
+            action_secs: (step.hours_value.parse::<u64>().unwrap_or(0) * 3600
+                + step.mins_value.parse::<u64>().unwrap_or(0) * 60
+                + step.secs_value.parse::<u64>().unwrap_or(0)) as f32,
+            repeats: false,
+        };
+        if i == 0 && current.name == "HOME" {
+            dry_step.gcode.push("$H".to_string());
+        }
+        if step.wait {
+            dry_run.waits_for_input = true;
+        }
+        match node_map.get(&destination) {
+            Some(index) => {
+                let next = &nodes.node[*index];
+                match gen_node_paths(nodes, &current, next) {
+                    Ok(path) => {
+                        if let Err(err) = nodes.limits.check_path(&current, &path.node) {
+                            dry_run
+                                .violations
+                                .push(format!("Step {}) {}", step.step_num, err));
+                        }
+                        let mut from = current.clone();
+                        dry_step.path.push(current.name.clone());
+                        for node in path.node {
+                            let feed = feed_rate(nodes, &from, &node);
+                            dry_step.travel_secs += travel_secs(&from, &node, feed);
+                            dry_step.gcode.push(jog_gcode(&node, feed));
+                            if node.name != current.name {
+                                dry_step.path.push(node.name.clone());
+                            }
+                            from = node;
+                        }
+                        current = next.clone();
+                    }
+                    Err(_) => dry_run.errors.push(format!(
+                        "Step {}) No safe path from '{}' to '{}'",
+                        step.step_num, current.name, destination
+                    )),
+                }
+            }
+            None => dry_run.errors.push(format!(
+                "Step {}) Destination '{}' does not exist",
+                step.step_num, destination
+            )),
+        }
+        match actions
+            .action
+            .iter()
+            .find(|action| action.name == step.selected_action)
+        {
+            Some(action) => {
+                dry_step.repeats = !action.commands.iter().any(|c| c == "WAIT");
+                if let Err(err) = nodes.limits.check_action(
+                    position(&current),
+                    &action.commands,
+                    dry_step.repeats,
+                ) {
+                    dry_run.violations.push(format!(
+                        "Step {}) Action '{}' {}",
+                        step.step_num, action.name, err
+                    ));
+                }
+                for command in &action.commands {
+                    if command != "WAIT" {
+                        dry_step.gcode.push(command.clone());
+                    }
+                }
+                dry_step.gcode.push("0x85 (jog cancel)".to_string());
+            }
+            None => dry_run.errors.push(format!(
+                "Step {}) Action '{}' does not exist",
+                step.step_num, step.selected_action
+            )),
+        }
+        dry_run.steps.push(dry_step);
+    }
+    let violations = dry_run.violations.clone();
+    dry_run.errors.extend(violations);
+    dry_run
+}
+
+// seconds to jog in a straight line between two nodes, feed is in units per minute
+pub fn travel_secs(from: &Node, to: &Node, feed: f32) -> f32 {
+    distance(from, to) / feed * 60.0
+}
+
+pub fn format_secs(secs: f32) -> String {
+    let secs = secs.round() as u64;
+    format!("{}h {}m {}s", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
+impl std::fmt::Display for DryRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "ERROR: {}", error)?;
+        }
+        for step in &self.steps {
+            writeln!(
+                f,
+                "Step {}) {} @ {} (travel {}, action {}{})",
+                step.step_num,
+                step.action,
+                step.destination,
+                format_secs(step.travel_secs),
+                format_secs(step.action_secs),
+                if step.repeats { ", commands repeat" } else { "" }
+            )?;
+            writeln!(f, "    path: {}", step.path.join(" > "))?;
+            for gcode in &step.gcode {
+                writeln!(f, "    {}", gcode)?;
+            }
+        }
+        write!(
+            f,
+            "Estimated duration: {}{}",
+            format_secs(self.total_secs()),
+            if self.waits_for_input {
+                " plus time waiting for user input"
+            } else {
+                ""
+            }
+        )
+    }
+}
@@ -0,0 +1,183 @@
+use super::advanced::LOGS;
+use super::dryrun::DryRun;
+use super::logger::replace_os_char;
+use super::run::Step;
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// counts down the time left in a recipe, time spent paused or waiting for input is not counted
+#[derive(Debug, Clone)]
+pub struct Eta {
+    estimates: Vec<f32>,
+    step: Option<usize>,
+    step_started: Instant,
+    paused_at: Option<Instant>,
+    paused: Duration,
+}
+
+impl Eta {
+    pub fn new(estimates: Vec<f32>) -> Eta {
+        Eta {
+            estimates,
+            step: None,
+            step_started: Instant::now(),
+            paused_at: None,
+            paused: Duration::from_secs(0),
+        }
+    }
+
+    pub fn set_step(&mut self, step: Option<usize>) {
+        if step != self.step {
+            self.step = step;
+            self.step_started = Instant::now();
+            self.paused = Duration::from_secs(0);
+            if self.paused_at.is_some() {
+                self.paused_at = Some(Instant::now());
+            }
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        match (paused, self.paused_at) {
+            (true, None) => self.paused_at = Some(Instant::now()),
+            (false, Some(at)) => {
+                self.paused += at.elapsed();
+                self.paused_at = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn step_elapsed(&self) -> f32 {
+        let paused = match self.paused_at {
+            Some(at) => self.paused + at.elapsed(),
+            None => self.paused,
+        };
+        (self.step_started.elapsed().as_secs_f32() - paused.as_secs_f32()).max(0.0)
+    }
+
+    // None for finished steps, time left for the current step, full estimate for the rest
+    pub fn step_remaining(&self, i: usize) -> Option<f32> {
+        let estimate = *self.estimates.get(i)?;
+        match self.step {
+            Some(step) if i < step => None,
+            Some(step) if i == step => Some((estimate - self.step_elapsed()).max(0.0)),
+            _ => Some(estimate),
+        }
+    }
+
+    pub fn total_remaining(&self) -> f32 {
+        (0..self.estimates.len()).fold(0.0, |total, i| {
+            total + self.step_remaining(i).unwrap_or(0.0)
+        })
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+}
+
+// seconds per step, the average of past runs of this revision of the recipe when there are
+// any, otherwise calculated from the dry run
+pub fn step_estimates(
+    recipe_name: &str,
+    revision: Option<u32>,
+    steps: &Vec<Step>,
+    dry_run: &DryRun,
+) -> Vec<f32> {
+    let historic = historic_secs(recipe_name, revision);
+    steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| match historic.get(&step.step_num) {
+            Some(secs) if secs.len() > 0 => secs.iter().sum::<f32>() / secs.len() as f32,
+            _ => match dry_run.steps.get(i) {
+                Some(dry_step) => dry_step.travel_secs + dry_step.action_secs,
+                None => 0.0,
+            },
+        })
+        .collect()
+}
+
+// how long each step took in every finished step of previous runs, by step number, runs of
+// other revisions are skipped since their steps may not be the same. A recipe saved before
+// revisions were kept matches the logs that have no revision either
+fn historic_secs(recipe_name: &str, revision: Option<u32>) -> HashMap<String, Vec<f32>> {
+    let mut secs: HashMap<String, Vec<f32>> = HashMap::new();
+    let title = replace_os_char(format!("; Run - {}", recipe_name));
+    let files = match fs::read_dir(Path::new(LOGS)) {
+        Ok(files) => files,
+        Err(_) => return secs,
+    };
+    for file in files.filter_map(|f| f.ok()) {
+        if !file.file_name().to_str().unwrap_or("").ends_with(&title) {
+            continue;
+        }
+        let log = match fs::read_to_string(file.path()) {
+            Ok(log) => log,
+            Err(_) => continue,
+        };
+        if log_revision(&log) != revision {
+            continue;
+        }
+        let mut started: Option<(String, DateTime<FixedOffset>)> = None;
+        let mut paused_at: Option<DateTime<FixedOffset>> = None;
+        let mut paused = chrono::Duration::zero();
+        for line in log.lines() {
+            let mut split = line.splitn(2, " => ");
+            let time = match split
+                .next()
+                .and_then(|t| DateTime::parse_from_rfc2822(&log_time(t)).ok())
+            {
+                Some(time) => time,
+                None => continue,
+            };
+            let msg = split.next().unwrap_or("");
+            if msg.starts_with("Paused by user") {
+                paused_at = Some(time);
+            } else if msg.starts_with("Resumed by user") {
+                if let Some(at) = paused_at {
+                    paused = paused + (time - at);
+                }
+                paused_at = None;
+            } else if msg.starts_with("Stopped") {
+                started = None;
+            } else if msg.starts_with("Step ") {
+                let step_num = msg[5..].splitn(2, ')').next().unwrap_or("").to_string();
+                if msg.contains(") Going to ") {
+                    started = Some((step_num, time));
+                    paused = chrono::Duration::zero();
+                } else if msg.contains(") finished ") {
+                    if let Some((num, start)) = &started {
+                        if *num == step_num {
+                            let took = (time - *start - paused).num_milliseconds() as f32 / 1000.0;
+                            secs.entry(step_num).or_insert(Vec::new()).push(took.max(0.0));
+                        }
+                    }
+                    started = None;
+                }
+            }
+        }
+    }
+    secs
+}
+
+fn log_revision(log: &str) -> Option<u32> {
+    log.lines()
+        .find_map(|line| line.splitn(2, " => Recipe revision: ").nth(1))
+        .and_then(|revision| revision.trim().parse().ok())
+}
+
+// on windows ':' in log lines is replaced with '_' before they are written
+fn log_time(time: &str) -> String {
+    time.replace("_", ":")
+}
+
+pub fn clock(secs_from_now: f32) -> String {
+    (Local::now() + chrono::Duration::seconds(secs_from_now.round() as i64))
+        .format("%l:%M %p")
+        .to_string()
+}
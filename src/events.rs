@@ -0,0 +1,108 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// one line in the json lines copy of a log, written by Logger::send_event
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Record {
+    pub time: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl Record {
+    pub fn new(event: Event) -> Record {
+        Record::at(Local::now(), event)
+    }
+
+    pub fn at(time: DateTime<Local>, event: Event) -> Record {
+        Record {
+            time: time.to_rfc3339(),
+            event,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    RunStarted {
+        recipe: String,
+        operator: Option<String>,
+        // None for recipes saved before revisions were kept
+        #[serde(default)]
+        revision: Option<u32>,
+    },
+    Input {
+        when: InputWhen,
+        title: String,
+        value: String,
+    },
+    StepStarted {
+        step: String,
+        destination: String,
+        action: String,
+        // how long the action is set to run for
+        #[serde(default)]
+        planned_secs: u64,
+    },
+    WaitingForInput {
+        step: String,
+    },
+    InputReceived {
+        step: String,
+    },
+    PathChosen {
+        step: String,
+        from: String,
+        to: String,
+        path: Vec<String>,
+    },
+    GcodeSent {
+        step: String,
+        gcode: String,
+    },
+    GrblResponse {
+        step: String,
+        gcode: String,
+        response: String,
+    },
+    Arrived {
+        node: String,
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+    ActionStarted {
+        step: String,
+        action: String,
+    },
+    StepFinished {
+        step: String,
+        action: String,
+    },
+    Paused,
+    Resumed,
+    Stopped,
+    // GRBL was lost or bathtub closed mid run, progress was kept so the run can be resumed
+    Interrupted {
+        step: String,
+    },
+    // an interrupted run picked up again, skipped_secs of the step's action had already run
+    RunResumed {
+        step: String,
+        skipped_secs: u64,
+    },
+    // GRBL raised an alarm or refused a command and the run was halted, ie. "GRBL ALARM:1"
+    Fault {
+        fault: String,
+        description: String,
+    },
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputWhen {
+    Before,
+    After,
+}
@@ -0,0 +1,195 @@
+// grbl 1.1 error and alarm codes, from https://github.com/gnea/grbl/blob/master/doc/csv
+const ERRORS: [(u8, &str); 36] = [
+    (1, "G-code words consist of a letter and a value. Letter was not found."),
+    (2, "Missing the expected G-code word value or numeric value format is not valid."),
+    (3, "Grbl '$' system command was not recognized or supported."),
+    (4, "Negative value received for an expected positive value."),
+    (5, "Homing cycle failure. Homing is not enabled via settings."),
+    (6, "Minimum step pulse time must be greater than 3usec."),
+    (7, "An EEPROM read failed. Auto-restoring affected EEPROM to default values."),
+    (8, "Grbl '$' command cannot be used unless Grbl is IDLE."),
+    (9, "G-code commands are locked out during alarm or jog state."),
+    (10, "Soft limits cannot be enabled without homing also enabled."),
+    (11, "Max characters per line exceeded. Received command line was not executed."),
+    (12, "Grbl '$' setting value cause the step rate to exceed the maximum supported."),
+    (13, "Safety door detected as opened and door state initiated."),
+    (14, "Build info or startup line exceeded EEPROM line length limit. Line not stored."),
+    (15, "Jog target exceeds machine travel. Jog command has been ignored."),
+    (16, "Jog command has no '=' or contains prohibited g-code."),
+    (17, "Laser mode requires PWM output."),
+    (20, "Unsupported or invalid g-code command found in block."),
+    (21, "More than one g-code command from same modal group found in block."),
+    (22, "Feed rate has not yet been set or is undefined."),
+    (23, "G-code command in block requires an integer value."),
+    (24, "More than one g-code command that requires axis words found in block."),
+    (25, "Repeated g-code word found in block."),
+    (26, "No axis words found in block for g-code command or current modal state which requires them."),
+    (27, "Line number value is invalid."),
+    (28, "G-code command is missing a required value word."),
+    (29, "G59.x work coordinate systems are not supported."),
+    (30, "G53 only allowed with G0 and G1 motion modes."),
+    (31, "Axis words found in block when no command or current modal state uses them."),
+    (32, "G2 and G3 arcs require at least one in-plane axis word."),
+    (33, "Motion command target is invalid."),
+    (34, "Arc radius value is invalid."),
+    (35, "G2 and G3 arcs require at least one in-plane offset word."),
+    (36, "Unused value words found in block."),
+    (37, "G43.1 dynamic tool length offset is not assigned to configured tool length axis."),
+    (38, "Tool number greater than max supported value."),
+];
+
+const ALARMS: [(u8, &str); 10] = [
+    (1, "Hard limit triggered. Position is likely lost due to the sudden halt."),
+    (2, "Soft limit. G-code motion target exceeds machine travel. Position was kept."),
+    (3, "Reset while in motion. Position is likely lost due to the sudden halt."),
+    (4, "Probe fail. Probe is not in the expected initial state before starting the probe cycle."),
+    (5, "Probe fail. Probe did not contact the workpiece within the programmed travel."),
+    (6, "Homing fail. The active homing cycle was reset."),
+    (7, "Homing fail. Safety door was opened during the homing cycle."),
+    (8, "Homing fail. Pull off travel failed to clear the limit switch."),
+    (9, "Homing fail. Could not find a limit switch within the search distance."),
+    (10, "Homing fail. Second dual axis limit switch failed to trigger."),
+];
+
+// something grbl reported that stops a recipe
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fault {
+    Alarm(u8),
+    // command grbl refused while a recipe was running
+    Error { code: u8, command: String },
+}
+
+// what the recovery dialog suggests doing next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Remedy {
+    // $X, grbl still knows where it is
+    Unlock,
+    // $H, the position was lost
+    Home,
+    // a switch is stuck or wired wrong, look at the pins before homing
+    CheckLimits,
+    // the gantry stopped where it was, nothing to clear in grbl
+    Acknowledge,
+}
+
+pub fn error_text(code: u8) -> &'static str {
+    lookup(&ERRORS, code)
+}
+
+pub fn alarm_text(code: u8) -> &'static str {
+    lookup(&ALARMS, code)
+}
+
+fn lookup(table: &[(u8, &'static str)], code: u8) -> &'static str {
+    table
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, text)| *text)
+        .unwrap_or("Unknown code, not part of GRBL 1.1.")
+}
+
+// code of a response like 'error:15' or 'ALARM:1'
+fn code(response: &str, prefix: &str) -> Option<u8> {
+    response
+        .trim()
+        .strip_prefix(prefix)
+        .and_then(|code| code.parse::<u8>().ok())
+}
+
+pub fn parse_error(response: &str) -> Option<u8> {
+    response.lines().last().and_then(|line| code(line, "error:"))
+}
+
+pub fn parse_alarm(line: &str) -> Option<u8> {
+    code(line, "ALARM:")
+}
+
+// a response with the meaning of any error or alarm in it added, for logs and the terminal
+pub fn explain(response: &str) -> String {
+    match (parse_error(response), parse_alarm(response)) {
+        (Some(code), _) => format!("{} ({})", response, error_text(code)),
+        (_, Some(code)) => format!("{} ({})", response, alarm_text(code)),
+        _ => response.to_string(),
+    }
+}
+
+impl Fault {
+    pub fn title(&self) -> String {
+        match self {
+            Fault::Alarm(code) => format!("GRBL ALARM:{}", code),
+            Fault::Error { code, .. } => format!("GRBL error:{}", code),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Fault::Alarm(code) => alarm_text(*code).to_string(),
+            Fault::Error { code, command } => format!("'{}' was refused. {}", command, error_text(*code)),
+        }
+    }
+
+    pub fn remedy(&self) -> Remedy {
+        match self {
+            Fault::Alarm(2) | Fault::Alarm(4) | Fault::Alarm(5) => Remedy::Unlock,
+            Fault::Alarm(1) | Fault::Alarm(8) | Fault::Alarm(9) | Fault::Alarm(10) => {
+                Remedy::CheckLimits
+            }
+            Fault::Alarm(_) => Remedy::Home,
+            Fault::Error { .. } => Remedy::Acknowledge,
+        }
+    }
+
+    pub fn is_alarm(&self) -> bool {
+        match self {
+            Fault::Alarm(_) => true,
+            _ => false,
+        }
+    }
+
+    // hard and soft limits lock grbl until it is reset, even $X and $H are ignored
+    pub fn needs_reset(&self) -> bool {
+        match self {
+            Fault::Alarm(1) | Fault::Alarm(2) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_codes() {
+        assert_eq!(parse_error("error:15"), Some(15));
+        assert_eq!(parse_error("$130=200.000\nerror:3"), Some(3));
+        assert_eq!(parse_error("ok"), None);
+        assert_eq!(parse_alarm("ALARM:2"), Some(2));
+        assert_eq!(parse_alarm("[MSG:Reset to continue]"), None);
+    }
+
+    #[test]
+    fn explains_responses() {
+        assert_eq!(
+            explain("error:15"),
+            "error:15 (Jog target exceeds machine travel. Jog command has been ignored.)"
+        );
+        assert!(explain("ALARM:1").starts_with("ALARM:1 (Hard limit"));
+        assert_eq!(explain("ok"), "ok");
+    }
+
+    #[test]
+    fn remedies() {
+        assert_eq!(Fault::Alarm(2).remedy(), Remedy::Unlock);
+        assert_eq!(Fault::Alarm(1).remedy(), Remedy::CheckLimits);
+        assert_eq!(Fault::Alarm(3).remedy(), Remedy::Home);
+        let refused = Fault::Error {
+            code: 15,
+            command: "$J=X1 F250".to_string(),
+        };
+        assert_eq!(refused.remedy(), Remedy::Acknowledge);
+        assert!(!refused.is_alarm());
+        assert!(Fault::Alarm(2).needs_reset());
+        assert!(!Fault::Alarm(3).needs_reset());
+    }
+}
@@ -0,0 +1,145 @@
+use super::nodes::{is_generated, Node, Nodes};
+use iced::canvas::{self, Cache, Canvas, Cursor, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Element, Length, Point, Rectangle, VerticalAlignment};
+
+// space kept around the drawing so labels and circles at the edges are not cut off
+const MARGIN: f32 = 40.0;
+const RADIUS: f32 = 6.0;
+
+// drawing of the node graph, looking down (x, y) or from the front (x, z)
+pub struct Graph {
+    side: bool,
+    nodes: Nodes,
+    // planned path, starting node first
+    path: Vec<Node>,
+    cache: Cache,
+}
+
+impl Graph {
+    pub fn new(nodes: Nodes, side: bool) -> Graph {
+        Graph {
+            side,
+            nodes,
+            path: Vec::new(),
+            cache: Cache::new(),
+        }
+    }
+
+    pub fn set_nodes(&mut self, nodes: Nodes) {
+        self.nodes = nodes;
+        self.cache.clear();
+    }
+
+    pub fn set_path(&mut self, path: Vec<Node>) {
+        self.path = path;
+        self.cache.clear();
+    }
+
+    pub fn view<'a, Message: 'a>(&'a mut self, height: u16) -> Element<'a, Message> {
+        Canvas::new(self)
+            .width(Length::Fill)
+            .height(Length::Units(height))
+            .into()
+    }
+
+    // the top view shows the nodes from baths.toml, the side view every generated height too
+    fn shown(&self) -> Vec<&Node> {
+        self.nodes
+            .node
+            .iter()
+            .filter(|n| self.side || !is_generated(&n.name))
+            .collect()
+    }
+
+    fn vertical(&self, node: &Node) -> f32 {
+        if self.side {
+            node.z
+        } else {
+            node.y
+        }
+    }
+
+    fn draw_graph(&self, frame: &mut Frame) {
+        let shown = self.shown();
+        if shown.is_empty() {
+            return;
+        }
+        let min_x = shown.iter().map(|n| n.x).fold(f32::MAX, f32::min);
+        let max_x = shown.iter().map(|n| n.x).fold(f32::MIN, f32::max);
+        let min_v = shown.iter().map(|n| self.vertical(n)).fold(f32::MAX, f32::min);
+        let max_v = shown.iter().map(|n| self.vertical(n)).fold(f32::MIN, f32::max);
+        // same scale on both axes so distances look right, higher y or z is drawn higher up
+        let scale = ((frame.width() - 2.0 * MARGIN) / (max_x - min_x).max(0.001))
+            .min((frame.height() - 2.0 * MARGIN) / (max_v - min_v).max(0.001));
+        let point = |n: &Node| {
+            Point::new(
+                MARGIN + (n.x - min_x) * scale,
+                MARGIN + (max_v - self.vertical(n)) * scale,
+            )
+        };
+        // generated nodes are drawn at the node they were made for in the top view
+        let find = |name: &str| {
+            let name = if self.side {
+                name
+            } else {
+                name.trim_end_matches("_hover").trim_end_matches("_approach")
+            };
+            shown.iter().find(|n| n.name == name)
+        };
+
+        let edge = Stroke::default()
+            .with_color(Color::from_rgb8(162, 168, 214))
+            .with_width(2.0);
+        for node in &self.nodes.node {
+            if let Some(from) = find(&node.name) {
+                for neighbor in &node.neighbors {
+                    if let Some(to) = find(neighbor) {
+                        if from.name != to.name {
+                            frame.stroke(&Path::line(point(from), point(to)), edge);
+                        }
+                    }
+                }
+            }
+        }
+
+        let path = Stroke::default()
+            .with_color(Color::from_rgb8(96, 196, 23))
+            .with_width(5.0);
+        for leg in self.path.windows(2) {
+            frame.stroke(&Path::line(point(&leg[0]), point(&leg[1])), path);
+        }
+
+        for node in shown {
+            let circle = Path::circle(point(node), RADIUS);
+            if node.hide {
+                // hidden nodes are only an outline
+                frame.fill(&circle, Color::WHITE);
+                frame.stroke(
+                    &circle,
+                    Stroke::default()
+                        .with_color(Color::from_rgb8(255, 191, 10))
+                        .with_width(3.0),
+                );
+            } else {
+                frame.fill(&circle, Color::from_rgb8(37, 171, 236));
+            }
+            if !self.side || !is_generated(&node.name) {
+                frame.fill_text(Text {
+                    content: node.name.clone(),
+                    position: Point::new(point(node).x + RADIUS + 4.0, point(node).y),
+                    size: 16.0,
+                    vertical_alignment: VerticalAlignment::Center,
+                    ..Text::default()
+                });
+            }
+        }
+    }
+}
+
+impl<Message> canvas::Program<Message> for Graph {
+    fn draw(&self, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        vec![self
+            .cache
+            .draw(bounds.size(), |frame| self.draw_graph(frame))]
+    }
+}
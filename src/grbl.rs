@@ -1,11 +1,30 @@
-use regex::Regex;
-use std::io::Write;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 use std::time::{Duration, Instant};
-use std::{str, thread};
 
 use chrono::prelude::*;
-use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+use iced::futures::channel::oneshot;
+
+use crate::faults::{self, Fault};
+use crate::serial;
+use crate::simulator::Simulator;
+use crate::status::{self, Status};
+
+// size of grbl's serial receive buffer, one byte of it is always left free
+const RX_BUFFER_SIZE: usize = 128;
+
+// grbl ignores everything after a soft reset until its banner is out
+pub const RESET_WAIT: Duration = Duration::from_millis(500);
+
+// how long halt_blocking() gives a feed hold to bring the gantry to a stop
+const HOLD_WAIT: Duration = Duration::from_secs(5);
+
+// tells the answers to requests apart
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(0);
 
 // used to clean up code when this file is imporded into another
 #[derive(Debug, Clone)]
@@ -14,13 +33,78 @@ pub struct Grbl {
     pub response_buffer: Arc<Mutex<Vec<Command>>>,
     pub mutex_status: Arc<Mutex<Option<Status>>>,
     ok_tx: mpsc::Sender<()>,
+    realtime_tx: mpsc::Sender<Realtime>,
+    link: Arc<Mutex<Link>>,
+    // alarm from grbl or error from a recipe, until the recovery dialog takes it
+    fault: Arc<Mutex<Option<Fault>>>,
 }
 
+// commands grbl acts on as soon as the byte arrives, sent ahead of anything queued
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Realtime {
+    FeedHold,
+    CycleStart,
+    SoftReset,
+    JogCancel,
+    FeedReset,
+    FeedPlus10,
+    FeedMinus10,
+    FeedPlus1,
+    FeedMinus1,
+    RapidReset,
+    RapidHalf,
+    RapidQuarter,
+}
+
+impl Realtime {
+    pub fn byte(&self) -> u8 {
+        match self {
+            Realtime::FeedHold => b'!',
+            Realtime::CycleStart => b'~',
+            Realtime::SoftReset => 0x18,
+            Realtime::JogCancel => 0x85,
+            Realtime::FeedReset => 0x90,
+            Realtime::FeedPlus10 => 0x91,
+            Realtime::FeedMinus10 => 0x92,
+            Realtime::FeedPlus1 => 0x93,
+            Realtime::FeedMinus1 => 0x94,
+            Realtime::RapidReset => 0x95,
+            Realtime::RapidHalf => 0x96,
+            Realtime::RapidQuarter => 0x97,
+        }
+    }
+}
+
+// what the thread from new() did about finding grbl
+#[derive(Debug, Clone, PartialEq)]
+pub enum Link {
+    Probing,
+    // name of the port grbl answered on
+    Connected(String),
+    // why no port was used, the thread has ended
+    Failed(String),
+}
+
+// anything grbl can be reached through, the usb serial port or the simulator
+pub trait Transport: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Transport for T {}
+
 #[derive(Debug, Clone)]
 pub struct Command {
     pub response_time: Option<chrono::DateTime<chrono::Local>>,
     pub command: String,
     pub result: Option<String>,
+    // where a request waits for the answer, None for commands answered in the response buffer
+    reply: Option<(u64, Reply)>,
+}
+
+// request() awaits its answer on the executor, request_blocking() on a thread of its own
+#[derive(Debug, Clone)]
+enum Reply {
+    // a oneshot can only be sent once, whoever answers first takes it
+    Async(Arc<Mutex<Option<oneshot::Sender<Command>>>>),
+    Blocking(mpsc::Sender<Command>),
 }
 
 impl Command {
@@ -29,53 +113,177 @@ impl Command {
             response_time: None,
             command: command.replace("\n", "").replace(" ", ""),
             result: None,
+            reply: None,
         }
     }
 }
 
+// what grbl answered to a request
 #[derive(Debug, Clone)]
-pub struct Status {
-    pub status: String,
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
+pub struct Response {
+    // lines grbl sent before the ok, ie. the settings for $$
+    pub lines: Vec<String>,
+    pub time: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrblError {
+    // error:N
+    Refused(u8),
+    // dropped by a jog cancel, soft reset or alarm before grbl answered
+    Cancelled,
+    // no answer in time, the command may still run if it was already sent
+    Timeout,
+    // the thread talking to grbl has ended
+    Disconnected,
+}
+
+impl fmt::Display for GrblError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrblError::Refused(code) => write!(f, "error:{} ({})", code, faults::error_text(*code)),
+            GrblError::Cancelled => write!(f, "cancelled before GRBL answered"),
+            GrblError::Timeout => write!(f, "no answer from GRBL in time"),
+            GrblError::Disconnected => write!(f, "GRBL is not connected"),
+        }
+    }
+}
+
+// how long request() waits for an answer to each kind of command
+fn timeout(command: &str) -> Duration {
+    match command {
+        // answered once every axis has found its switch
+        "$H" => Duration::from_secs(120),
+        // everything else is answered as soon as grbl has read it, or planned it
+        _ => Duration::from_secs(5),
+    }
 }
 
 impl Grbl {
     pub fn push_command(&self, command: Command) {
         let mut cb = self.command_buffer.lock().unwrap();
-        if command.command == "\u{85}".to_string() {
-            *cb = Vec::new();
-        }
         cb.insert(0, command)
     }
-    pub fn pop_command(&self) -> Option<Command> {
-        if let Ok(mut rb) = self.response_buffer.try_lock() {
-            rb.pop()
-        } else {
-            None
+    // queue a command and wait for grbl's answer to it, without anything else in the response
+    // buffer getting in the way. Nothing is blocked while waiting
+    pub async fn request(&self, mut command: Command) -> Result<Response, GrblError> {
+        let timeout = timeout(&command.command);
+        let id = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        command.reply = Some((id, Reply::Async(Arc::new(Mutex::new(Some(tx))))));
+        self.push_command(command);
+        if !self.is_ok() {
+            self.forget(id);
+            return Err(GrblError::Disconnected);
+        }
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(answered)) => response(answered),
+            // dropped unanswered, only done once the thread talking to grbl has ended
+            Ok(Err(_)) => Err(GrblError::Disconnected),
+            Err(_) => {
+                self.forget(id);
+                Err(GrblError::Timeout)
+            }
         }
     }
-    pub fn safe_pop(&self) -> Option<Command> {
-        if let Ok(mut rb) = self.response_buffer.try_lock() {
-            if rb.len() > 0 {
-                rb.pop()
-            } else {
-                None
+    // same as request() but blocks the calling thread until grbl answers, only for threads that
+    // are not running an executor, like the recipe thread or the headless runner. Async code
+    // uses request(), or spawn_blocking() for anything that calls this
+    pub fn request_blocking(&self, mut command: Command) -> Result<Response, GrblError> {
+        let timeout = timeout(&command.command);
+        let id = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        command.reply = Some((id, Reply::Blocking(tx)));
+        self.push_command(command);
+        if !self.is_ok() {
+            self.forget(id);
+            return Err(GrblError::Disconnected);
+        }
+        match rx.recv_timeout(timeout) {
+            Ok(answered) => response(answered),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(GrblError::Disconnected),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.forget(id);
+                Err(GrblError::Timeout)
             }
-        } else {
-            None
         }
     }
-    pub fn clear_all(&self) {
-        let mut rb = self.response_buffer.lock().unwrap();
-        let mut cb = self.command_buffer.lock().unwrap();
-        rb.clear();
-        cb.clear();
+    // a request that is still queued, nobody wants it sent any more
+    fn forget(&self, id: u64) {
+        self.command_buffer
+            .lock()
+            .unwrap()
+            .retain(|cmd| cmd.reply.as_ref().map_or(true, |(i, _)| *i != id));
+    }
+    // a soft reset drops whatever has not been sent yet and a jog cancel the jogs that have not,
+    // answered as cancelled. Other commands and requests queued behind a jog are still sent
+    pub fn realtime(&self, command: Realtime) {
+        if command == Realtime::JogCancel || command == Realtime::SoftReset {
+            let mut cb = self.command_buffer.lock().unwrap();
+            let mut rb = self.response_buffer.lock().unwrap();
+            let (dropped, kept): (Vec<Command>, Vec<Command>) = cb.drain(..).partition(|cmd| {
+                command == Realtime::SoftReset || cmd.command.starts_with("$J=")
+            });
+            cb.extend(kept);
+            // oldest first, the same order they would have been answered in
+            for cmd in dropped.into_iter().rev() {
+                deliver(&mut rb, cancelled(cmd));
+            }
+        }
+        self.realtime_tx.send(command).unwrap_or(());
+    }
+    // drops everything queued that is not a request, answered as cancelled, and cancels the jog
+    // grbl is on. Moves grbl already planned keep going, halt_blocking() stops those too
+    pub fn drop_queued(&self) {
+        {
+            let mut cb = self.command_buffer.lock().unwrap();
+            let mut rb = self.response_buffer.lock().unwrap();
+            let (dropped, kept): (Vec<Command>, Vec<Command>) =
+                cb.drain(..).partition(|cmd| cmd.reply.is_none());
+            cb.extend(kept);
+            for cmd in dropped.into_iter().rev() {
+                deliver(&mut rb, cancelled(cmd));
+            }
+        }
+        self.realtime(Realtime::JogCancel);
+    }
+    // drop_queued() then throw away the moves grbl has planned, held first so the reset keeps the
+    // position. Blocks until grbl is ready again, only for the recipe thread or the headless runner
+    pub fn halt_blocking(&self) {
+        self.drop_queued();
+        if !self.is_ok() || !self.get_status().map_or(false, |s| s.status == "Run") {
+            return;
+        }
+        self.realtime(Realtime::FeedHold);
+        let start = Instant::now();
+        while start.elapsed() < HOLD_WAIT {
+            match self.get_status() {
+                Some(s) if s.status == "Hold" && s.sub_state == Some(0) => break,
+                _ => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        self.realtime(Realtime::SoftReset);
+        thread::sleep(RESET_WAIT);
     }
     pub fn is_ok(&self) -> bool {
         self.ok_tx.send(()).is_ok()
     }
+    pub fn set_fault(&self, fault: Fault) {
+        *self.fault.lock().unwrap() = Some(fault);
+    }
+    pub fn take_fault(&self) -> Option<Fault> {
+        self.fault.lock().unwrap().take()
+    }
+    pub fn link(&self) -> Link {
+        self.link.lock().unwrap().clone()
+    }
+    // probing every port can take a few seconds each
+    pub fn wait_for_link(&self) -> Link {
+        while self.link() == Link::Probing {
+            thread::sleep(Duration::from_millis(100));
+        }
+        self.link()
+    }
     pub fn get_status(&self) -> Option<Status> {
         if let Ok(status) = self.mutex_status.try_lock() {
             status.clone()
@@ -89,185 +297,390 @@ impl Grbl {
         rb.clear();
         rb_c
     }
-    pub fn queue_len(&self) -> Option<usize> {
-        match self.command_buffer.lock() {
-            Ok(cb) => return Some(cb.len()),
-            Err(_) => None,
-        }
-    }
-    pub fn recv_queue_len(&self) -> Option<usize> {
-        if let Ok(rb) = self.response_buffer.try_lock() {
-            Some(rb.len())
-        } else {
-            None
-        }
-    }
 }
 
 // Create new thread that, locks usb serial connection + used to send+recv gcode
 pub fn new() -> Grbl {
+    if simulated() {
+        with_transport(|| Ok((Box::new(Simulator::new(port_timeout())), "simulator".to_string())))
+    } else {
+        with_transport(get_port)
+    }
+}
+
+// run with the simulated gantry instead of the usb serial connection
+pub fn simulated() -> bool {
+    std::env::args().any(|arg| arg == "--simulate") || std::env::var("BATHTUB_SIMULATE").is_ok()
+}
+
+// same as new() but grbl is reached through whatever connect() opens, with a name for it
+pub fn with_transport<F>(connect: F) -> Grbl
+where
+    F: FnOnce() -> Result<(Box<dyn Transport>, String), String> + Send + 'static,
+{
     let command_buffer: Arc<Mutex<Vec<Command>>> = Arc::new(Mutex::new(Vec::new()));
     let response_buffer = Arc::new(Mutex::new(Vec::new()));
     let cb_c = Arc::clone(&command_buffer);
     let rb_c = Arc::clone(&response_buffer);
-    let status = Arc::new(Mutex::new(None));
-    let mutex_status = Arc::clone(&status);
+    let mutex_status = Arc::new(Mutex::new(None));
+    let mutex_status_c = Arc::clone(&mutex_status);
     let (ok_tx, ok_rx) = mpsc::channel();
+    let (realtime_tx, realtime_rx) = mpsc::channel::<Realtime>();
+    let link = Arc::new(Mutex::new(Link::Probing));
+    let link_c = Arc::clone(&link);
+    let fault = Arc::new(Mutex::new(None));
+    let fault_c = Arc::clone(&fault);
     thread::spawn(move || {
-        let mut port = get_port();
-        let mut now = Instant::now();
-        let r =
-            Regex::new(r"(?P<status>[A-Za-z]+).{6}(?P<X>[-\d.]+),(?P<Y>[-\d.]+),(?P<Z>[-\d.]+)")
-                .unwrap();
-        let mut current_status = Command::new("?".to_string());
+        // ending the thread is how everything else learns there is no grbl
+        let mut port = match connect() {
+            Ok((port, name)) => {
+                *link_c.lock().unwrap() = Link::Connected(name);
+                port
+            }
+            Err(err) => {
+                *link_c.lock().unwrap() = Link::Failed(err);
+                hang_up(ok_rx, &cb_c);
+                return;
+            }
+        };
+        // wco and overrides are only in some reports, each report is read on top of the last
+        let mut last: Option<Status> = None;
+        let mut jogging = false;
+        let mut status_polled = Instant::now();
+        // bytes of a line grbl has not finished sending
+        let mut pending = Vec::new();
+        // sent and not yet answered, in the order grbl will answer them, with their length
+        let mut in_flight: VecDeque<(Command, usize)> = VecDeque::new();
+        // lines grbl sent before the ok of the oldest command in flight, ie. $$ or $I
+        let mut answer: Vec<String> = Vec::new();
         loop {
             // does nothing in this thread used to test if died in other threads
             match ok_rx.try_recv() {
                 _ => {}
             };
-            if now.elapsed().as_millis() >= 100
-                && if let Ok(cb) = cb_c.try_lock() {
-                    cb.len() == 0
-                } else {
-                    false
+            if status_polled.elapsed().as_millis() >= 100 {
+                status_polled = Instant::now();
+                if port.write_all(b"?").is_err() {
+                    break;
+                }
+            }
+            let mut lost = false;
+            while let Ok(command) = realtime_rx.try_recv() {
+                if port.write_all(&[command.byte()]).is_err() {
+                    lost = true;
+                    break;
                 }
-            {
-                now = Instant::now();
-                port.flush().unwrap();
-                send(&mut port, &mut current_status);
-                if let Some(caps) = r.captures(&current_status.result.as_ref().unwrap()[..]) {
-                    let loc = Status {
-                        status: caps["status"].to_string(),
-                        x: caps["X"].parse::<f32>().unwrap(),
-                        y: caps["Y"].parse::<f32>().unwrap(),
-                        z: caps["Z"].parse::<f32>().unwrap(),
-                    };
-                    current_status.response_time = None;
-                    current_status.result = None;
-                    port.flush().unwrap();
-                    if let Ok(mut lctn) = status.try_lock() {
-                        *lctn = Some(loc);
+                let jog_in_flight = in_flight
+                    .front()
+                    .map_or(false, |(cmd, _)| cmd.command.starts_with("$J="));
+                let mut dropped = Vec::new();
+                match command {
+                    // a feed hold while jogging cancels the jog, queued jogs would start it again
+                    Realtime::FeedHold if jogging || jog_in_flight => {
+                        let mut cb = cb_c.lock().unwrap();
+                        cb.retain(|cmd| {
+                            if cmd.command.starts_with("$J=") {
+                                dropped.push(cmd.clone());
+                                false
+                            } else {
+                                true
+                            }
+                        });
+                        dropped.extend(drop_jogs(&mut in_flight));
+                    }
+                    // grbl drops the jogs it has not started, they are never answered
+                    Realtime::JogCancel => dropped.extend(drop_jogs(&mut in_flight)),
+                    Realtime::SoftReset => {
+                        dropped.extend(in_flight.drain(..).map(|(cmd, _)| cmd));
+                        answer.clear();
+                    }
+                    _ => {}
+                }
+                if dropped.len() > 0 {
+                    let mut rb = rb_c.lock().unwrap();
+                    for cmd in dropped {
+                        deliver(&mut rb, cancelled(cmd));
                     }
                 }
-            } else {
-                if let Ok(mut cb) = cb_c.try_lock() {
-                    if let Some(mut cmd) = cb.pop() {
-                        send(&mut port, &mut cmd);
-                        let mut rb = rb_c.lock().unwrap();
-                        rb.push(cmd);
+            }
+            if lost {
+                break;
+            }
+            if let Ok(mut cb) = cb_c.try_lock() {
+                if stream(&mut port, &mut cb, &mut in_flight).is_err() {
+                    break;
+                }
+            }
+            let lines = match read_lines(&mut port, &mut pending) {
+                Ok(lines) => lines,
+                Err(_) => break,
+            };
+            for line in lines {
+                if line.starts_with('<') {
+                    if let Some(loc) = status::parse(&line, last.as_ref()) {
+                        jogging = loc.status == "Jog";
+                        last = Some(loc.clone());
+                        if let Ok(mut lctn) = mutex_status_c.try_lock() {
+                            *lctn = Some(loc);
+                        }
+                    }
+                } else if line == "ok" || line.starts_with("error:") {
+                    // grbl answers every line in order, so this is the oldest one sent
+                    if let Some((mut cmd, _)) = in_flight.pop_front() {
+                        answer.push(line);
+                        cmd.result = Some(answer.join("\n"));
+                        cmd.response_time = Some(Local::now());
+                        answer.clear();
+                        deliver(&mut rb_c.lock().unwrap(), cmd);
+                    }
+                } else if let Some(code) = faults::parse_alarm(&line) {
+                    // grbl stops and throws away whatever it had not run yet
+                    *fault_c.lock().unwrap() = Some(Fault::Alarm(code));
+                    let mut rb = rb_c.lock().unwrap();
+                    for (cmd, _) in in_flight.drain(..) {
+                        deliver(&mut rb, cancelled(cmd));
+                    }
+                    answer.clear();
+                } else if line.starts_with("Grbl ") {
+                    // grbl was reset, anything still in its buffer is gone
+                    let mut rb = rb_c.lock().unwrap();
+                    for (cmd, _) in in_flight.drain(..) {
+                        deliver(&mut rb, cancelled(cmd));
                     }
+                    answer.clear();
+                } else if !line.is_empty() && !in_flight.is_empty() {
+                    answer.push(line);
                 }
             }
         }
+        hang_up(ok_rx, &cb_c);
     });
     Grbl {
         command_buffer,
         response_buffer,
         mutex_status,
         ok_tx,
+        realtime_tx,
+        link,
+        fault,
     }
 }
 
+// how long a read waits for grbl before the thread goes back to sending
+fn port_timeout() -> Duration {
+    Duration::from_millis(20)
+}
+
 // used by new() to get the usb serial connection
-fn get_port() -> Box<dyn SerialPort> {
-    let ports = serialport::available_ports().expect("no ports available");
-    for p in ports {
-        if let Ok(port) = serialport::new(p.port_name, 115_200)
-            .parity(Parity::None)
-            .data_bits(DataBits::Eight)
-            .stop_bits(StopBits::One)
-            .flow_control(FlowControl::None)
-            .timeout(if cfg!(windows) {
-                Duration::from_millis(50)
-            } else {
-                Duration::from_secs(60)
-            })
-            .open()
-        {
-            return port;
-        }
-    }
-    panic!("unable to get port!");
+fn get_port() -> Result<(Box<dyn Transport>, String), String> {
+    let (port, name) = serial::find_grbl(port_timeout())?;
+    Ok((Box::new(port), name))
 }
 
-// used by the new() thread to send to grbl and parse response
-pub fn send(port: &mut Box<dyn SerialPort>, command: &mut Command) {
-    let buf = format!("{}\n", command.command).as_bytes().to_owned();
-    port.write_all(&buf[..]).unwrap();
-    loop {
-        match read_until(0xA, port) {
-            Ok(line) => {
-                command.response_time = Some(Local::now());
-                match &command.command[..] {
-                    "$$" => command.result = Some(line),
-                    "$N" => command.result = Some(line),
-                    _ => command.result = Some(line.replace("\n", "").replace("\r", "")),
-                }
-                break;
+// $ commands other than jogs, grbl may write to eeprom for these and stops reading serial
+fn is_system(command: &Command) -> bool {
+    command.command.starts_with('$') && !command.command.starts_with("$J=")
+}
+
+// sent jogs grbl threw away, oldest first
+fn drop_jogs(in_flight: &mut VecDeque<(Command, usize)>) -> Vec<Command> {
+    let (jogs, rest): (Vec<(Command, usize)>, Vec<(Command, usize)>) = in_flight
+        .drain(..)
+        .partition(|(cmd, _)| cmd.command.starts_with("$J="));
+    in_flight.extend(rest);
+    jogs.into_iter().map(|(cmd, _)| cmd).collect()
+}
+
+// answer for a command grbl will never answer, so whoever counts responses is not left waiting
+fn cancelled(mut cmd: Command) -> Command {
+    cmd.response_time = Some(Local::now());
+    cmd.result = Some("cancelled".to_string());
+    cmd
+}
+
+// requests are answered to whoever is waiting, everything else goes in the response buffer
+fn deliver(rb: &mut Vec<Command>, mut cmd: Command) {
+    // whoever asked may have given up waiting
+    match cmd.reply.take() {
+        Some((_, Reply::Async(tx))) => {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                tx.send(cmd).unwrap_or(());
             }
-            Err(err) => panic!("{}", err),
         }
+        Some((_, Reply::Blocking(tx))) => tx.send(cmd).unwrap_or(()),
+        None => rb.push(cmd),
     }
 }
-cfg_if::cfg_if! {
-if #[cfg(windows)] {
-fn read_until(_c: u8, port: &mut Box<dyn SerialPort>) -> Result<String, std::io::Error> {
-    //let mut reader = BufReader::new(port);
-    let mut buf: Vec<u8> = vec![0;32];
-    let mut result_buf: Vec<u8> = Vec::new();
-    let mut cont = true;
-    while cont {
-        println!("{}", buf.len());
-        match port.read(&mut buf) {
-            Ok(_num) => {
-                result_buf.extend_from_slice(&buf[..]);
-                if result_buf[0] != 0x24 {
-                    result_buf = result_buf.into_iter().take_while(|u| *u != 0xA).collect();
-                } else {
-                    let len1 = result_buf.len();
-                    result_buf = result_buf.into_iter().take_while(|b| *b != 0x6F).collect();
-                    let len2 = result_buf.len();
-                    if len1 != len2 {result_buf.pop();}
-                }
-            }
-            Err(_err) => {
-                result_buf = result_buf.into_iter().filter(|b| *b != 0x0).collect::<Vec<u8>>();
-                if **result_buf.last().as_ref().unwrap_or(&&0x1) == 0xD ||
-                    **result_buf.last().as_ref().unwrap_or(&&0x1) == 0x47 ||
-                    **result_buf.first().as_ref().unwrap_or(&&0x1) == 0x0 {
-                        cont = false;
-                    }
-            },
-        }
+
+// the thread is ending, dropping the requests still queued answers them as Disconnected. Those
+// in flight go with the thread. ok_rx goes first so anything queued after this sees is_ok() fail
+fn hang_up(ok_rx: mpsc::Receiver<()>, command_buffer: &Mutex<Vec<Command>>) {
+    drop(ok_rx);
+    command_buffer.lock().unwrap().clear();
+}
+
+fn response(cmd: Command) -> Result<Response, GrblError> {
+    let result = cmd.result.unwrap_or(String::new());
+    if result == "cancelled" {
+        return Err(GrblError::Cancelled);
     }
-    Ok(str::from_utf8(&result_buf[..]).unwrap_or("**Parsing reponse failed**").to_string())
+    if let Some(code) = faults::parse_error(&result) {
+        return Err(GrblError::Refused(code));
+    }
+    let mut lines: Vec<String> = result.lines().map(|line| line.to_string()).collect();
+    lines.pop();
+    Ok(Response {
+        lines,
+        time: cmd.response_time.unwrap_or(Local::now()),
+    })
 }
-} else {
-fn read_until(c: u8, port: &mut Box<dyn SerialPort>) -> Result<String, std::io::Error> {
-    use std::io::BufRead;
-use std::io::BufReader;
-    let mut reader = BufReader::new(port);
-    let mut buf: Vec<u8> = Vec::new();
-    let mut len: usize;
-    loop {
-        match reader.fill_buf() {
-            Ok(data) => {
-                len = data.len();
-                if data.len() > 0 {
-                    buf.extend_from_slice(&data[..]);
-                    if buf.last().unwrap_or(&0) == &c {
-                        return Ok(str::from_utf8(&buf[..]).unwrap().to_string());
-                    }
-                } else {
-                    return Ok(str::from_utf8(&buf[..]).unwrap().to_string());
-                }
-            }
-            Err(err) => {
-                return Err(err);
-            }
+
+// character counting: send queued lines while everything unanswered still fits in grbl's
+// receive buffer, so the planner always has the next move
+fn stream(
+    port: &mut Box<dyn Transport>,
+    cb: &mut Vec<Command>,
+    in_flight: &mut VecDeque<(Command, usize)>,
+) -> std::io::Result<()> {
+    while let Some(cmd) = cb.pop() {
+        let len = cmd.command.len() + 1;
+        let used: usize = in_flight.iter().map(|(_, len)| len).sum();
+        let waiting = match in_flight.front() {
+            Some((front, _)) => is_system(front) || is_system(&cmd) || used + len >= RX_BUFFER_SIZE,
+            None => false,
+        };
+        if waiting {
+            cb.push(cmd);
+            break;
         }
-        reader.consume(len);
+        port.write_all(format!("{}\n", cmd.command).as_bytes())?;
+        in_flight.push_back((cmd, len));
     }
+    Ok(())
 }
+
+// whole lines received since the last call without line endings, a timeout is no lines
+fn read_lines(port: &mut Box<dyn Transport>, pending: &mut Vec<u8>) -> std::io::Result<Vec<String>> {
+    let mut buf = [0u8; 256];
+    match port.read(&mut buf) {
+        Ok(n) => pending.extend_from_slice(&buf[..n]),
+        Err(err)
+            if err.kind() == ErrorKind::TimedOut
+                || err.kind() == ErrorKind::WouldBlock
+                || err.kind() == ErrorKind::Interrupted =>
+        {
+            return Ok(Vec::new())
+        }
+        Err(err) => return Err(err),
+    }
+    let mut lines = Vec::new();
+    while let Some(i) = pending.iter().position(|b| *b == b'\n') {
+        let line: Vec<u8> = pending.drain(..=i).collect();
+        lines.push(String::from_utf8_lossy(&line).trim().to_string());
+    }
+    Ok(lines)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulator() -> Grbl {
+        with_transport(|| {
+            Ok((
+                Box::new(Simulator::new(port_timeout())),
+                "simulator".to_string(),
+            ))
+        })
+    }
+
+    fn executor() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn request_is_answered_on_an_executor() {
+        let grbl = simulator();
+        let response = executor()
+            .block_on(grbl.request(Command::new("$$".to_string())))
+            .unwrap();
+        assert!(response.lines.iter().any(|line| line == "$130=200.000"));
+    }
+
+    #[test]
+    fn request_is_refused() {
+        let grbl = simulator();
+        assert_eq!(
+            executor()
+                .block_on(grbl.request(Command::new("$Q".to_string())))
+                .err(),
+            Some(GrblError::Refused(3))
+        );
+        assert_eq!(
+            grbl.request_blocking(Command::new("$Q".to_string())).err(),
+            Some(GrblError::Refused(3))
+        );
+    }
+
+    #[test]
+    fn requests_without_grbl_are_disconnected() {
+        let grbl = with_transport(|| Err("no grbl".to_string()));
+        grbl.wait_for_link();
+        assert_eq!(
+            executor()
+                .block_on(grbl.request(Command::new("$$".to_string())))
+                .err(),
+            Some(GrblError::Disconnected)
+        );
+        assert_eq!(
+            grbl.request_blocking(Command::new("$$".to_string())).err(),
+            Some(GrblError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn drop_queued_keeps_requests() {
+        // never connects, so nothing queued is sent
+        let grbl = with_transport(|| {
+            thread::sleep(Duration::from_secs(60));
+            Err("no grbl".to_string())
+        });
+        let (tx, _rx) = mpsc::channel();
+        let mut request = Command::new("$$".to_string());
+        request.reply = Some((0, Reply::Blocking(tx)));
+        grbl.push_command(Command::new("G1 X-1 F60".to_string()));
+        grbl.push_command(request);
+        grbl.push_command(Command::new("$J=X-2 F250".to_string()));
+        grbl.drop_queued();
+        let queued = grbl.command_buffer.lock().unwrap().clone();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].command, "$$");
+        let cancelled: Vec<String> = grbl
+            .clear_responses()
+            .into_iter()
+            .map(|cmd| format!("{} {}", cmd.command, cmd.result.unwrap()))
+            .collect();
+        assert_eq!(cancelled, vec!["G1X-1F60 cancelled", "$J=X-2F250 cancelled"]);
+    }
+
+    #[test]
+    fn halt_stops_planned_moves_and_keeps_the_position() {
+        let grbl = simulator();
+        grbl.request_blocking(Command::new("$X".to_string())).unwrap();
+        // 50mm at 60mm/min keeps the gantry busy for the whole test
+        grbl.push_command(Command::new("G1 X-50 F60".to_string()));
+        let start = Instant::now();
+        while grbl.get_status().map_or(true, |s| s.status != "Run") {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+        grbl.halt_blocking();
+        thread::sleep(Duration::from_millis(200));
+        let status = grbl.get_status().unwrap();
+        assert_eq!(status.status, "Idle");
+        assert!(status.x < 0.0);
+    }
 }
@@ -0,0 +1,366 @@
+use std::io::{self, BufRead, Write};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use chrono::prelude::*;
+
+use super::actions;
+use super::dryrun::{dry_run, format_secs};
+use super::estimate::{step_estimates, Eta};
+use super::events::{Event, InputWhen};
+use super::faults::{Fault, Remedy};
+use super::grbl::{self, Link};
+use super::limits::read_max_travel;
+use super::logger::{get_username, Logger};
+use super::nodes;
+use super::paths::DEFAULT_FEED;
+use super::recovery::{self, recovery_reason, retract_and_home, LastState};
+use super::run::{read_recipe, revision_line};
+use super::validate::validate;
+use super::{NodeTracker, RecipeState, RunEnd, State};
+
+// `bathtub run <recipe> [--dry-run]` runs ./recipes/<recipe>.toml without the ui, returns the exit code
+pub fn run(recipe_name: &str) -> i32 {
+    let mut nodes = match nodes::gen_nodes() {
+        Ok(nodes) => nodes,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    let report = validate(&nodes::get_baths_config().unwrap_or(nodes::Nodes::new()));
+    for error in &report.errors {
+        eprintln!("config/baths.toml: {}", error);
+    }
+    for warning in &report.warnings {
+        eprintln!("config/baths.toml: warning, {}", warning);
+    }
+    if report.errors.len() > 0 {
+        return 1;
+    }
+    let node_map = nodes::get_nodemap(&nodes);
+    let actions = match actions::gen_actions() {
+        Ok(actions) => actions,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    let recipe = match read_recipe(recipe_name) {
+        Ok(recipe) => recipe,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
+    let mut preview = dry_run(&recipe.steps, &nodes, &node_map, &actions);
+    if std::env::args().any(|arg| arg == "--dry-run") {
+        println!("{}", preview);
+        return if preview.errors.len() > 0 { 1 } else { 0 };
+    }
+    if preview.errors.len() > 0 {
+        for error in &preview.errors {
+            eprintln!("{}", error);
+        }
+        eprintln!("Run with --dry-run for more information");
+        return 1;
+    }
+    let mut eta = Eta::new(step_estimates(
+        recipe_name,
+        recipe.revision,
+        &recipe.steps,
+        &preview,
+    ));
+    let home = match node_map.get(&"HOME".to_string()) {
+        Some(i) => nodes.node[*i].clone(),
+        None => {
+            eprintln!("'HOME' required by Bathtub, not found in config/baths.toml");
+            return 1;
+        }
+    };
+
+    let grbl = grbl::new();
+    if let Link::Failed(problem) = grbl.wait_for_link() {
+        eprintln!("Unable to connect to GRBL");
+        eprintln!("{}", problem);
+        return 1;
+    }
+    // same check as the ui makes on start, nothing moves sideways until the head is raised
+    let mut status = grbl.get_status();
+    for _ in 0..50 {
+        if status.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        status = grbl.get_status();
+    }
+    let status = match status {
+        Some(status) => status,
+        None => {
+            eprintln!("No status report from GRBL, unable to check the gantry position");
+            return 1;
+        }
+    };
+    if let Some(reason) = recovery_reason(&status, &LastState::load(), &nodes) {
+        println!("{}", reason);
+        println!("Retracting to hover height and homing");
+        if let Err(err) = retract_and_home(&grbl, &nodes, nodes.feed.unwrap_or(DEFAULT_FEED)) {
+            eprintln!("Homing failed, {}", err);
+            return 1;
+        }
+    }
+    // check again now grbl's max travel is known
+    nodes.limits.max_travel = read_max_travel(&grbl);
+    preview = dry_run(&recipe.steps, &nodes, &node_map, &actions);
+    if preview.violations.len() > 0 {
+        for violation in &preview.violations {
+            eprintln!("{}", violation);
+        }
+        return 1;
+    }
+
+    let before_inputs = match prompt_inputs(&recipe.required_inputs.before) {
+        Ok(inputs) => inputs,
+        Err(_) => {
+            eprintln!("stdin closed before all required inputs were entered");
+            return 1;
+        }
+    };
+    let mut logger = Logger::new();
+    logger.set_log_file(format!(
+        "{}; Run - {}",
+        Local::now().to_rfc2822(),
+        recipe_name
+    ));
+    logger
+        .send_event(Event::RunStarted {
+            recipe: recipe_name.to_string(),
+            operator: get_username(),
+            revision: recipe.revision,
+        })
+        .unwrap();
+    logger
+        .send_line("--------------------".to_string())
+        .unwrap();
+    logger.send_line(revision_line(recipe.revision)).unwrap();
+    log_inputs(&logger, &before_inputs, InputWhen::Before);
+    logger
+        .send_line("--------------------".to_string())
+        .unwrap();
+
+    let recipe_state = Arc::new((Mutex::new(RecipeState::RecipeRunning), Condvar::new()));
+    let node_tracker = Arc::new(Mutex::new(NodeTracker {
+        prev: None,
+        current: home,
+        next: Vec::new(),
+    }));
+    let (step_tx, step_rx) = mpsc::channel();
+    let (done_tx, done_rx) = mpsc::channel();
+    let steps = recipe.steps.clone();
+    let state_nodes = nodes.clone();
+    let mut last_state = LastState::load();
+    {
+        let grbl = grbl.clone();
+        let logger = logger.clone();
+        let recipe_state = Arc::clone(&recipe_state);
+        thread::spawn(move || {
+            done_tx
+                .send(State::run_recipe_blocking(
+                    grbl,
+                    logger,
+                    recipe_state,
+                    node_tracker,
+                    steps,
+                    node_map,
+                    nodes,
+                    actions,
+                    step_tx,
+                    None,
+                ))
+                .unwrap();
+        });
+    }
+
+    let mut fault = None;
+    let result = loop {
+        if let Ok(result) = done_rx.try_recv() {
+            break result;
+        }
+        if !grbl.is_ok() {
+            eprintln!("GRBL connection lost");
+            break Err(());
+        }
+        if let Some(f) = grbl.take_fault() {
+            log_fault(&logger, &f);
+            let (rs, cvar) = &*recipe_state;
+            *rs.lock().unwrap() = RecipeState::Fault;
+            cvar.notify_all();
+            fault = Some(f);
+        }
+        if let Some(s) = grbl.get_status() {
+            let rs = *recipe_state.0.lock().unwrap();
+            recovery::record(&mut last_state, &s, &state_nodes, rs);
+        }
+        if let Ok(Some(num)) = step_rx.try_recv() {
+            eta.set_step(Some(num));
+            let step = &recipe.steps[num];
+            println!(
+                "Step {} of {}) {} @ {}, about {} left",
+                num + 1,
+                recipe.steps.len(),
+                step.selected_action,
+                step.selected_destination,
+                format_secs(eta.total_remaining())
+            );
+        }
+        let (rs, cvar) = &*recipe_state;
+        let require_input = match *rs.lock().unwrap() {
+            RecipeState::RequireInput => true,
+            _ => false,
+        };
+        if require_input {
+            eta.set_paused(true);
+            let answered = prompt("Waiting for user input, press Enter to continue").is_some();
+            eta.set_paused(false);
+            // nobody is there to continue the run
+            if !answered {
+                eprintln!("stdin closed while waiting for user input");
+                logger
+                    .send_line(format!(
+                        "{} => Stopped, stdin closed while waiting for user input",
+                        Local::now().to_rfc2822()
+                    ))
+                    .unwrap();
+            }
+            *rs.lock().unwrap() = if answered {
+                RecipeState::RecipeRunning
+            } else {
+                RecipeState::Stopped
+            };
+            cvar.notify_all();
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    // the recipe thread sees a fault as a stop and finishes normally, maybe before it was seen here
+    if fault.is_none() {
+        fault = grbl.take_fault();
+        if let Some(f) = &fault {
+            log_fault(&logger, f);
+        }
+    }
+    let result = match fault {
+        Some(f) => {
+            eprintln!("{}", remedy_hint(f.remedy()));
+            Err(())
+        }
+        None => result,
+    };
+    {
+        let (rs, cvar) = &*recipe_state;
+        *rs.lock().unwrap() = RecipeState::Stopped;
+        cvar.notify_all();
+    }
+    if let Some(s) = grbl.get_status() {
+        recovery::record(&mut last_state, &s, &state_nodes, RecipeState::Stopped);
+    }
+    match result {
+        Ok(RunEnd::Completed) => {
+            logger
+                .send_line(format!("{} => Done", Local::now().to_rfc2822()))
+                .unwrap();
+            logger.send_event(Event::Done).unwrap();
+            println!("Done");
+        }
+        _ => {
+            grbl.halt_blocking();
+            logger
+                .send_line(format!("{} => Stopped", Local::now().to_rfc2822()))
+                .unwrap();
+            logger.send_event(Event::Stopped).unwrap();
+        }
+    }
+    let after_inputs = prompt_inputs(&recipe.required_inputs.after).unwrap_or_else(|_| {
+        eprintln!("stdin closed before all required inputs were entered");
+        Vec::new()
+    });
+    logger
+        .send_line("--------------------".to_string())
+        .unwrap();
+    log_inputs(&logger, &after_inputs, InputWhen::After);
+    logger.set_log_file(String::new());
+    logger.flush();
+    match result {
+        Ok(RunEnd::Completed) => 0,
+        _ => 1,
+    }
+}
+
+fn log_fault(logger: &Logger, fault: &Fault) {
+    eprintln!("{}: {}", fault.title(), fault.description());
+    logger
+        .send_line(format!(
+            "{} => {}: {}",
+            Local::now().to_rfc2822(),
+            fault.title(),
+            fault.description()
+        ))
+        .unwrap();
+    logger
+        .send_event(Event::Fault {
+            fault: fault.title(),
+            description: fault.description(),
+        })
+        .unwrap();
+}
+
+// what to do about a fault without the recovery dialog
+fn remedy_hint(remedy: Remedy) -> &'static str {
+    match remedy {
+        Remedy::Unlock => "GRBL kept its position, unlock it with $X before running again",
+        Remedy::Home => "GRBL lost its position, home it with $H before running again",
+        Remedy::CheckLimits => {
+            "Check the limit switches are clear and wired correctly, then home GRBL with $H"
+        }
+        Remedy::Acknowledge => "Check the recipe's actions and the limits before running again",
+    }
+}
+
+// None once stdin is closed
+fn prompt(msg: &str) -> Option<String> {
+    print!("{}", msg);
+    io::stdout().flush().unwrap();
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+// ask for each required input until it is not empty
+fn prompt_inputs(titles: &Vec<String>) -> Result<Vec<(String, String)>, ()> {
+    let mut inputs = Vec::with_capacity(titles.len());
+    for title in titles {
+        let mut value = String::new();
+        while value.is_empty() {
+            value = prompt(&format!("{}: ", title)).ok_or(())?;
+        }
+        inputs.push((title.clone(), value));
+    }
+    Ok(inputs)
+}
+
+fn log_inputs(logger: &Logger, inputs: &Vec<(String, String)>, when: InputWhen) {
+    for (title, value) in inputs {
+        logger
+            .send_line(format!("{} => {}: {}", Local::now().to_rfc2822(), title, value))
+            .unwrap();
+        logger
+            .send_event(Event::Input {
+                when,
+                title: title.clone(),
+                value: value.clone(),
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+use super::build::SaveRecipe;
+use super::logger::get_username;
+use super::run::Step;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// every saved revision of ./recipes/<name>.toml is kept in RECIPE_HISTORY/<name>/<revision>.toml
+pub const RECIPE_HISTORY: &str = "./recipe_history";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Revision {
+    pub revision: u32,
+    pub author: String,
+    pub time: String,
+    pub recipe: SaveRecipe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Same,
+    Added,
+    Removed,
+    Changed,
+    Moved,
+}
+
+// one line of a step level diff, old is None for added steps and new is None for removed ones
+#[derive(Debug, Clone)]
+pub struct StepDiff {
+    pub kind: DiffKind,
+    pub old: Option<Step>,
+    pub new: Option<Step>,
+}
+
+// newest first
+pub fn revisions(name: &str) -> Vec<Revision> {
+    let mut revisions: Vec<Revision> =
+        match fs::read_dir(Path::new(&format!("{}/{}", RECIPE_HISTORY, name))) {
+            Ok(files) => files
+                .filter_map(|f| f.ok())
+                .filter_map(|f| fs::read_to_string(f.path()).ok())
+                .filter_map(|toml_str| toml::from_str(&toml_str).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+    revisions.sort_by(|a, b| b.revision.cmp(&a.revision));
+    revisions
+}
+
+pub fn next_revision(name: &str) -> u32 {
+    revisions(name).first().map(|r| r.revision).unwrap_or(0) + 1
+}
+
+// store recipe as the next revision of name, the recipe's revision field is set to match
+// numbers the recipe as the next revision and keeps it in the history
+pub fn save_revision(name: &str, recipe: &mut SaveRecipe) -> Result<u32, ()> {
+    recipe.revision = Some(next_revision(name));
+    record_revision(name, recipe)
+}
+
+// keeps a recipe that already has its revision number in the history, call once it is written
+pub fn record_revision(name: &str, recipe: &SaveRecipe) -> Result<u32, ()> {
+    let revision = recipe.revision.ok_or(())?;
+    let dir = format!("{}/{}", RECIPE_HISTORY, name);
+    fs::create_dir_all(&dir).map_err(|_| ())?;
+    let toml_str = toml::to_string_pretty(&Revision {
+        revision,
+        author: get_username().unwrap_or("**Unavailable**".to_string()),
+        time: Local::now().to_rfc2822(),
+        recipe: recipe.clone(),
+    })
+    .map_err(|_| ())?;
+    fs::write(format!("{}/{}.toml", dir, revision), toml_str).map_err(|_| ())?;
+    Ok(revision)
+}
+
+// steps are compared by what they do, not by their number, so inserting a step only shows the
+// new step as added
+fn same_step(a: &Step, b: &Step) -> bool {
+    a.selected_destination == b.selected_destination
+        && a.selected_action == b.selected_action
+        && a.hover == b.hover
+        && a.wait == b.wait
+        && secs(a) == secs(b)
+}
+
+// a step replaced by one going to the same bath or doing the same action was edited, anything
+// else is a different step in its place
+fn related(old: &Step, new: &Step) -> bool {
+    old.selected_destination == new.selected_destination
+        || old.selected_action == new.selected_action
+}
+
+pub fn secs(step: &Step) -> u64 {
+    step.hours_value.parse::<u64>().unwrap_or(0) * 3600
+        + step.mins_value.parse::<u64>().unwrap_or(0) * 60
+        + step.secs_value.parse::<u64>().unwrap_or(0)
+}
+
+// longest common subsequence of the two step lists. A removed step that shows up again elsewhere
+// is shown as moved, a removed step directly followed by a related added one as a single changed
+// step
+pub fn diff_steps(old: &Vec<Step>, new: &Vec<Step>) -> Vec<StepDiff> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if same_step(&old[i], &new[j]) {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut diff: Vec<StepDiff> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && same_step(&old[i], &new[j]) {
+            diff.push(StepDiff {
+                kind: DiffKind::Same,
+                old: Some(old[i].clone()),
+                new: Some(new[j].clone()),
+            });
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            diff.push(StepDiff {
+                kind: DiffKind::Removed,
+                old: Some(old[i].clone()),
+                new: None,
+            });
+            i += 1;
+        } else {
+            diff.push(StepDiff {
+                kind: DiffKind::Added,
+                old: None,
+                new: Some(new[j].clone()),
+            });
+            j += 1;
+        }
+    }
+
+    // moved steps are listed where they now are
+    let mut i = 0;
+    while i < diff.len() {
+        if diff[i].kind == DiffKind::Added {
+            let new_step = diff[i].new.clone().unwrap();
+            if let Some(r) = diff.iter().position(|d| {
+                d.kind == DiffKind::Removed && same_step(d.old.as_ref().unwrap(), &new_step)
+            }) {
+                diff[i].kind = DiffKind::Moved;
+                diff[i].old = diff[r].old.take();
+                diff.remove(r);
+                if r < i {
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let mut paired: Vec<StepDiff> = Vec::with_capacity(diff.len());
+    for d in diff {
+        match paired.last_mut() {
+            Some(last)
+                if last.kind == DiffKind::Removed
+                    && d.kind == DiffKind::Added
+                    && related(last.old.as_ref().unwrap(), d.new.as_ref().unwrap()) =>
+            {
+                last.kind = DiffKind::Changed;
+                last.new = d.new;
+            }
+            _ => paired.push(d),
+        }
+    }
+    paired
+}
+
+// which parts of a changed step differ, in the order they are shown in Build
+pub fn changed_fields(old: &Step, new: &Step) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if old.selected_destination != new.selected_destination {
+        fields.push("destination");
+    }
+    if old.hover != new.hover {
+        fields.push("hover");
+    }
+    if old.selected_action != new.selected_action {
+        fields.push("action");
+    }
+    if secs(old) != secs(new) {
+        fields.push("time");
+    }
+    if old.wait != new.wait {
+        fields.push("wait");
+    }
+    fields
+}
+
+pub fn describe_step(step: &Step) -> String {
+    let secs = secs(step);
+    format!(
+        "{}) {} @ {}{} for {}h {}m {}s{}",
+        step.step_num,
+        step.selected_action,
+        step.selected_destination,
+        if step.hover { " (hover)" } else { "" },
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+        if step.wait { ", wait for input" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(step_num: usize, destination: &str, secs: u64) -> Step {
+        Step {
+            step_num: step_num.to_string(),
+            selected_destination: destination.to_string(),
+            selected_action: "Rest".to_string(),
+            secs_value: secs.to_string(),
+            mins_value: "0".to_string(),
+            hours_value: "0".to_string(),
+            hover: false,
+            wait: false,
+        }
+    }
+
+    fn kinds(diff: &Vec<StepDiff>) -> Vec<DiffKind> {
+        diff.iter().map(|d| d.kind).collect()
+    }
+
+    #[test]
+    fn inserted_step_is_the_only_change() {
+        let old = vec![step(1, "A", 5), step(2, "B", 5)];
+        let new = vec![step(1, "A", 5), step(2, "C", 5), step(3, "B", 5)];
+        let diff = diff_steps(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![DiffKind::Same, DiffKind::Added, DiffKind::Same]
+        );
+        assert_eq!(diff[1].new.as_ref().unwrap().selected_destination, "C");
+    }
+
+    #[test]
+    fn changed_and_removed_steps() {
+        let old = vec![step(1, "A", 5), step(2, "B", 5), step(3, "C", 5)];
+        let new = vec![step(1, "A", 10), step(2, "B", 5)];
+        let diff = diff_steps(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![DiffKind::Changed, DiffKind::Same, DiffKind::Removed]
+        );
+        assert_eq!(
+            changed_fields(diff[0].old.as_ref().unwrap(), diff[0].new.as_ref().unwrap()),
+            vec!["time"]
+        );
+    }
+
+    #[test]
+    fn unrelated_replacement_is_removed_and_added() {
+        let old = vec![step(1, "A", 5), step(2, "B", 5)];
+        let mut other = step(2, "C", 10);
+        other.selected_action = "Swish".to_string();
+        let new = vec![step(1, "A", 5), other];
+        let diff = diff_steps(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![DiffKind::Same, DiffKind::Removed, DiffKind::Added]
+        );
+        // same bath, different action
+        let mut swish = step(2, "B", 5);
+        swish.selected_action = "Swish".to_string();
+        let new = vec![step(1, "A", 5), swish];
+        assert_eq!(
+            kinds(&diff_steps(&old, &new)),
+            vec![DiffKind::Same, DiffKind::Changed]
+        );
+    }
+
+    #[test]
+    fn moved_step() {
+        let old = vec![step(1, "A", 5), step(2, "B", 5), step(3, "C", 5)];
+        let new = vec![step(1, "B", 5), step(2, "C", 5), step(3, "A", 5)];
+        let diff = diff_steps(&old, &new);
+        assert_eq!(
+            kinds(&diff),
+            vec![DiffKind::Same, DiffKind::Same, DiffKind::Moved]
+        );
+        assert_eq!(diff[2].old.as_ref().unwrap().step_num, "1");
+        assert_eq!(diff[2].new.as_ref().unwrap().step_num, "3");
+    }
+}
@@ -0,0 +1,367 @@
+use super::config::{self, ConfigError, LIMITS};
+use super::grbl::{Command as Cmd, Grbl, Response};
+use super::nodes::{Node, Nodes};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// where the gantry may go, in machine coordinates. Everything is optional, a missing
+// config/limits.toml only checks GRBL's own max travel
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Limits {
+    // every move must end inside
+    pub bounds: Option<Zone>,
+    // boxes no move may pass through, tank walls, fixtures, etc.
+    pub keep_out: Option<Vec<Zone>>,
+    // $130-$132 read from grbl, homed machines can reach -max_travel to 0 on each axis
+    #[serde(skip)]
+    pub max_travel: Option<[f32; 3]>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Zone {
+    pub name: Option<String>,
+    // [x, y, z]
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+impl Zone {
+    fn contains(&self, p: [f32; 3]) -> bool {
+        (0..3).all(|i| p[i] >= self.min[i] && p[i] <= self.max[i])
+    }
+
+    // slab test, true if any point of the straight line from a to b is inside
+    fn crosses(&self, a: [f32; 3], b: [f32; 3]) -> bool {
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for i in 0..3 {
+            let d = b[i] - a[i];
+            if d.abs() < f32::EPSILON {
+                if a[i] < self.min[i] || a[i] > self.max[i] {
+                    return false;
+                }
+            } else {
+                let t1 = (self.min[i] - a[i]) / d;
+                let t2 = (self.max[i] - a[i]) / d;
+                enter = enter.max(t1.min(t2));
+                exit = exit.min(t1.max(t2));
+                if enter > exit {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn describe(&self) -> String {
+        match &self.name {
+            Some(name) => format!("keep-out zone '{}'", name),
+            None => format!(
+                "keep-out zone ({}, {}, {}) to ({}, {}, {})",
+                self.min[0], self.min[1], self.min[2], self.max[0], self.max[1], self.max[2]
+            ),
+        }
+    }
+}
+
+impl Limits {
+    // a missing file means no limits, a broken one must not be ignored
+    pub fn load() -> Result<Limits, ConfigError> {
+        if !Path::new(LIMITS).exists() {
+            return Ok(Limits::default());
+        }
+        config::read(LIMITS)
+    }
+
+    // a straight move from a to b
+    pub fn check_move(&self, a: [f32; 3], b: [f32; 3]) -> Result<(), String> {
+        if let Some(travel) = self.max_travel {
+            if (0..3).any(|i| b[i] > 0.001 || b[i] < -travel[i] - 0.001) {
+                return Err(format!(
+                    "{} is outside GRBL's max travel ($130-$132)",
+                    point(b)
+                ));
+            }
+        }
+        if let Some(bounds) = &self.bounds {
+            if !bounds.contains(b) {
+                return Err(format!("{} is outside the work area", point(b)));
+            }
+        }
+        for zone in self.keep_out.as_ref().unwrap_or(&Vec::new()) {
+            if zone.crosses(a, b) {
+                return Err(format!(
+                    "moving from {} to {} passes through the {}",
+                    point(a),
+                    point(b),
+                    zone.describe()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // every leg of a path from gen_node_paths, starting at from
+    pub fn check_path(&self, from: &Node, path: &Vec<Node>) -> Result<(), String> {
+        let mut a = position(from);
+        for node in path {
+            self.check_move(a, position(node))
+                .map_err(|err| format!("path to '{}': {}", node.name, err))?;
+            a = position(node);
+        }
+        Ok(())
+    }
+
+    // one pass of an action's commands starting at start. Repeating actions must end where they
+    // started or they would creep further every time they repeat
+    pub fn check_action(
+        &self,
+        start: [f32; 3],
+        commands: &Vec<String>,
+        repeats: bool,
+    ) -> Result<(), String> {
+        let mut a = start;
+        for command in commands {
+            if let Some(b) = gcode_target(a, command) {
+                self.check_move(a, b)
+                    .map_err(|err| format!("'{}': {}", command, err))?;
+                a = b;
+            }
+        }
+        if repeats && (0..3).any(|i| (a[i] - start[i]).abs() > 0.001) {
+            return Err(format!(
+                "commands end at {} instead of {}, so every repeat moves further away",
+                point(a),
+                point(start)
+            ));
+        }
+        Ok(())
+    }
+
+    // hidden nodes to go through instead of the straight move from a to b when it passes through a
+    // keep-out zone, lifting straight up, crossing over and lowering straight down. Tries the
+    // higher of a's and b's hover heights first then the top of travel. Err if the move is not
+    // allowed either way
+    pub fn waypoints(&self, nodes: &Nodes, a: &Node, b: &Node) -> Result<Vec<Node>, String> {
+        let (pa, pb) = (position(a), position(b));
+        let err = match self.check_move(pa, pb) {
+            Ok(_) => return Ok(Vec::new()),
+            Err(err) => err,
+        };
+        let hover = nodes.hover_z(a.x, a.y).max(nodes.hover_z(b.x, b.y));
+        for z in [hover.max(a.z).max(b.z), 0.0].iter() {
+            let lift = [a.x, a.y, *z];
+            let over = [b.x, b.y, *z];
+            let mut points = vec![pa];
+            for p in [lift, over].iter() {
+                if points.last() != Some(p) && *p != pb {
+                    points.push(*p);
+                }
+            }
+            points.push(pb);
+            if points.len() > 2
+                && points
+                    .windows(2)
+                    .all(|leg| self.check_move(leg[0], leg[1]).is_ok())
+            {
+                // the edge's feed is kept for the lift and cross, lowering uses b's own feed
+                let feed = a
+                    .edges
+                    .as_ref()
+                    .and_then(|edges| edges.iter().find(|edge| edge.to == b.name))
+                    .and_then(|edge| edge.feed);
+                return Ok(points[1..points.len() - 1]
+                    .iter()
+                    .map(|p| Node {
+                        name: if p[0] == a.x && p[1] == a.y {
+                            format!("{}_lift", a.name)
+                        } else {
+                            format!("{}_lift", b.name)
+                        },
+                        x: p[0],
+                        y: p[1],
+                        z: p[2],
+                        hide: true,
+                        neighbors: Vec::new(),
+                        feed,
+                        edges: None,
+                        hover: None,
+                        approach: None,
+                    })
+                    .collect());
+            }
+        }
+        Err(err)
+    }
+
+    // every neighbor of every node that cannot be reached in a straight line or with waypoints
+    pub fn blocked_edges(&self, nodes: &Nodes) -> Vec<String> {
+        let mut blocked = Vec::new();
+        for a in &nodes.node {
+            for neighbor in &a.neighbors {
+                if let Some(b) = nodes.node.iter().find(|n| &n.name == neighbor) {
+                    if let Err(err) = self.waypoints(nodes, a, b) {
+                        blocked.push(format!("'{}' to '{}': {}", a.name, b.name, err));
+                    }
+                }
+            }
+        }
+        blocked
+    }
+}
+
+pub fn position(node: &Node) -> [f32; 3] {
+    [node.x, node.y, node.z]
+}
+
+fn point(p: [f32; 3]) -> String {
+    format!("({:.3}, {:.3}, {:.3})", p[0], p[1], p[2])
+}
+
+// where a jog or G0/G1 line moves to from a, None for lines that do not move. Lines with G91 are
+// relative, everything else is taken as absolute
+pub fn gcode_target(a: [f32; 3], gcode: &str) -> Option<[f32; 3]> {
+    let line = gcode.trim_start_matches("$J=").to_uppercase();
+    let relative = line.contains("G91");
+    let mut b = a;
+    let mut moves = false;
+    for word in line.split_whitespace() {
+        let axis = match word.chars().next() {
+            Some('X') => 0,
+            Some('Y') => 1,
+            Some('Z') => 2,
+            _ => continue,
+        };
+        if let Ok(value) = word[1..].parse::<f32>() {
+            b[axis] = if relative { a[axis] + value } else { value };
+            moves = true;
+        }
+    }
+    if moves {
+        Some(b)
+    } else {
+        None
+    }
+}
+
+// $130-$132 from grbl's settings, None if grbl does not answer in time
+pub async fn max_travel(grbl: Grbl) -> Option<[f32; 3]> {
+    parse_max_travel(grbl.request(Cmd::new("$$".to_string())).await.ok()?)
+}
+
+// same as max_travel() for callers without an executor, like the headless runner
+pub fn read_max_travel(grbl: &Grbl) -> Option<[f32; 3]> {
+    parse_max_travel(grbl.request_blocking(Cmd::new("$$".to_string())).ok()?)
+}
+
+fn parse_max_travel(response: Response) -> Option<[f32; 3]> {
+    let mut travel = [None; 3];
+    for line in response.lines {
+        let setting: Vec<&str> = line.trim().split('=').collect();
+        if setting.len() == 2 {
+            if let Some(axis) = ["$130", "$131", "$132"]
+                .iter()
+                .position(|s| *s == setting[0])
+            {
+                travel[axis] = setting[1].parse::<f32>().ok();
+            }
+        }
+    }
+    match travel {
+        [Some(x), Some(y), Some(z)] => Some([x, y, z]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone() -> Zone {
+        Zone {
+            name: Some("wall".to_string()),
+            min: [-20.0, -10.0, -30.0],
+            max: [-10.0, 0.0, -5.0],
+        }
+    }
+
+    #[test]
+    fn gcode_target_absolute_and_relative() {
+        let a = [-1.0, -2.0, -3.0];
+        assert_eq!(
+            gcode_target(a, "$J=X-5 Y-6 Z-7 F250"),
+            Some([-5.0, -6.0, -7.0])
+        );
+        assert_eq!(gcode_target(a, "G0 Z-1"), Some([-1.0, -2.0, -1.0]));
+        assert_eq!(
+            gcode_target(a, "$J=G91 Y0.5 F250"),
+            Some([-1.0, -1.5, -3.0])
+        );
+        assert_eq!(gcode_target(a, "g91 g1 x-1 f100"), Some([-2.0, -2.0, -3.0]));
+    }
+
+    #[test]
+    fn gcode_target_none_without_axes() {
+        assert_eq!(gcode_target([0.0; 3], "G4 P1"), None);
+        assert_eq!(gcode_target([0.0; 3], "$H"), None);
+    }
+
+    #[test]
+    fn crosses_zone() {
+        let zone = zone();
+        // straight through the middle
+        assert!(zone.crosses([-25.0, -5.0, -10.0], [-5.0, -5.0, -10.0]));
+        // starting inside
+        assert!(zone.crosses([-15.0, -5.0, -10.0], [-15.0, -5.0, -1.0]));
+        // along an edge counts as touching the wall
+        assert!(zone.crosses([-25.0, 0.0, -10.0], [-5.0, 0.0, -10.0]));
+    }
+
+    #[test]
+    fn misses_zone() {
+        let zone = zone();
+        // over the top
+        assert!(!zone.crosses([-25.0, -5.0, -1.0], [-5.0, -5.0, -1.0]));
+        // stops short
+        assert!(!zone.crosses([-30.0, -5.0, -10.0], [-21.0, -5.0, -10.0]));
+        // diagonal passing by a corner
+        assert!(!zone.crosses([-25.0, -4.0, -10.0], [-15.0, 6.0, -10.0]));
+    }
+
+    #[test]
+    fn check_move_limits() {
+        let limits = Limits {
+            bounds: Some(Zone {
+                name: None,
+                min: [-100.0, -100.0, -50.0],
+                max: [0.0, 0.0, 0.0],
+            }),
+            keep_out: Some(vec![zone()]),
+            max_travel: Some([200.0, 200.0, 40.0]),
+        };
+        assert!(limits
+            .check_move([-1.0, -1.0, -1.0], [-50.0, -50.0, -1.0])
+            .is_ok());
+        assert!(limits
+            .check_move([-1.0, -1.0, -1.0], [-1.0, -1.0, -45.0])
+            .is_err());
+        assert!(limits
+            .check_move([-1.0, -1.0, -1.0], [-150.0, -1.0, -1.0])
+            .is_err());
+        assert!(limits
+            .check_move([-25.0, -5.0, -10.0], [-5.0, -5.0, -10.0])
+            .is_err());
+    }
+
+    #[test]
+    fn repeating_action_must_return() {
+        let limits = Limits::default();
+        let swish = vec![
+            "$J=G91 Y0.3 F250".to_string(),
+            "$J=G91 Y-0.3 F250".to_string(),
+        ];
+        assert!(limits.check_action([-1.0; 3], &swish, true).is_ok());
+        let creep = vec!["$J=G91 Y0.3 F250".to_string()];
+        assert!(limits.check_action([-1.0; 3], &creep, true).is_err());
+        assert!(limits.check_action([-1.0; 3], &creep, false).is_ok());
+    }
+}
@@ -2,25 +2,51 @@ use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::Path;
 use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
 use std::{fs, thread};
 
 use super::advanced::{Log, LOGS};
+use super::events::{Event, Record};
+
+// json lines copies of the logs in LOGS, same file name with .jsonl added
+pub const RUN_LOGS: &str = "./run_logs";
 
 const WIN_CHARS: [&str; 9] = ["<", ">", ":", "\"", "/", "\\", "|", "?", "*"];
 
 #[derive(Debug, Clone)]
 pub struct Logger {
     sender: Sender<String>,
+    flushed: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl Logger {
     pub fn new() -> Logger {
         let (tx, rx): (Sender<String>, Receiver<String>) = channel();
         let mut file_name = String::new();
+        let flushed = Arc::new((Mutex::new(true), Condvar::new()));
+        let flushed2 = Arc::clone(&flushed);
         thread::spawn(move || loop {
             if let Ok(mut line) = rx.recv() {
+                // json must be written as is, skip replace_os_char
+                if line.ends_with("\n\rjson") {
+                    if !file_name.is_empty() {
+                        fs::create_dir_all(RUN_LOGS).unwrap_or(());
+                        if let Ok(mut log) = OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(Path::new(&format!("{}/{}.jsonl", RUN_LOGS, file_name)))
+                        {
+                            writeln!(log, "{}", line.replace("\n\rjson", "")).unwrap();
+                        }
+                    }
+                    continue;
+                }
                 line = replace_os_char(line);
-                if line.ends_with("\n\rset_log_file") {
+                if line.ends_with("\n\rflush") {
+                    let (done, cvar) = &*flushed2;
+                    *done.lock().unwrap() = true;
+                    cvar.notify_all();
+                } else if line.ends_with("\n\rset_log_file") {
                     file_name = line.replace("\n\rset_log_file", "");
                 } else {
                     match OpenOptions::new()
@@ -52,7 +78,23 @@ impl Logger {
                 break;
             }
         });
-        Logger { sender: tx }
+        Logger {
+            sender: tx,
+            flushed,
+        }
+    }
+
+    // lines and events go to the receiver instead of a file, for tests to read back
+    #[cfg(test)]
+    pub fn capture() -> (Logger, Receiver<String>) {
+        let (tx, rx) = channel();
+        (
+            Logger {
+                sender: tx,
+                flushed: Arc::new((Mutex::new(true), Condvar::new())),
+            },
+            rx,
+        )
     }
 
     pub fn set_log_file(&mut self, mut file_name: String) {
@@ -67,6 +109,27 @@ impl Logger {
     pub fn send_line(&self, line: String) -> Result<(), SendError<String>> {
         self.sender.send(line)
     }
+
+    pub fn send_event(&self, event: Event) -> Result<(), SendError<String>> {
+        self.send_record(Record::new(event))
+    }
+
+    pub fn send_record(&self, record: Record) -> Result<(), SendError<String>> {
+        let mut json = serde_json::to_string(&record).unwrap();
+        json.push_str("\n\rjson");
+        self.sender.send(json)
+    }
+
+    // block until every line sent so far has been written
+    pub fn flush(&self) {
+        let (done, cvar) = &*self.flushed;
+        let mut done = done.lock().unwrap();
+        *done = false;
+        self.sender.send("\n\rflush".to_string()).unwrap();
+        while !*done {
+            done = cvar.wait(done).unwrap();
+        }
+    }
     pub async fn search_files<'a>(
         vals: Vec<String>,
         file_name: String,
@@ -86,11 +149,11 @@ impl Logger {
 // function
 cfg_if::cfg_if! {
     if #[cfg(windows)] {
-fn get_username() -> Option<String> {
+pub fn get_username() -> Option<String> {
     Some(env!("USERNAME").to_string())
 }
 } else {
-    fn get_username() -> Option<String> {
+    pub fn get_username() -> Option<String> {
         use users::{get_current_uid, get_user_by_uid};
         match get_user_by_uid(get_current_uid())
         .unwrap()
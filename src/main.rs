@@ -3,59 +3,183 @@
 mod actions;
 mod advanced;
 mod build;
+mod config;
+mod connect;
+mod dryrun;
+mod estimate;
+mod events;
+mod faults;
+mod graph;
 mod grbl;
+mod headless;
+mod history;
+mod limits;
 mod logger;
 mod manual;
 mod nodes;
+mod overrides;
 mod paths;
+mod profile;
+mod progress;
+mod recovery;
 mod run;
+mod serial;
+mod simulator;
+mod status;
 mod style;
+mod traveler;
+mod validate;
 use actions::Actions;
-use advanced::{Advanced, AdvancedMessage, NodeTabMessage};
+use advanced::{
+    ActionTabMessage, Advanced, AdvancedMessage, GrblMessage, NodeTabMessage, SaveBarMessage,
+};
 use build::{Build, BuildMessage};
+use config::{ConfigError, ACTIONS, BATHS, LIMITS};
+use connect::{Connect, ConnectMessage};
+use limits::Limits;
 use chrono::prelude::*;
-use grbl::{Command as Cmd, Grbl};
+use events::{Event, Record};
+use faults::{Fault, Remedy};
+use grbl::{Command as Cmd, Grbl, GrblError, Link, Realtime};
 use image::io::Reader as ImageReader;
 use logger::Logger;
 use manual::{Manual, ManualMessage};
 use nodes::{Node, Nodes};
+use progress::Progress;
+use recovery::{nearest_node, position_lost, recovery_reason, LastState};
 use run::Step;
 use run::{Run, RunMessage, RunState};
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::{mpsc, Arc, Condvar, Mutex};
 use std::time::{Duration, Instant};
 use std::{mem::discriminant, thread};
 use style::style::Theme;
+use validate::{validate, Report};
 
 use iced::{
-    button, time, window, Align, Application, Button, Clipboard, Column, Command, Container,
-    Element, Font, HorizontalAlignment, Length, Row, Settings, Space, Subscription, Text,
+    button, text_input, time, window, Align, Application, Button, Clipboard, Column, Command,
+    Container, Element, Font, HorizontalAlignment, Length, Row, Settings, Space, Subscription,
+    Text, TextInput,
 };
 
 pub fn main() -> iced::Result {
-    let icon = ImageReader::open(Path::new("./icon.ico"))
-        .unwrap()
-        .decode()
-        .unwrap()
-        .to_rgba8();
+    let args = profile::positional_args();
+    let root = profile::data_root();
+    if args.len() > 2 && args[1] == "run" {
+        let entered = profile::chosen(&root).and_then(|chosen| match chosen {
+            Some(name) => profile::enter(&root, Some(&name)),
+            None if profile::list(&root).is_empty() => profile::enter(&root, None),
+            None => Err(format!(
+                "Choose a profile with --profile: {}",
+                profile::list(&root).join(", ")
+            )),
+        });
+        if let Err(err) = entered {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        std::process::exit(headless::run(&args[2]));
+    }
     Bathtub::run(Settings {
         window: window::Settings {
-            icon: Some(
-                window::Icon::from_rgba(icon.to_vec(), icon.width(), icon.height()).unwrap(),
-            ),
+            icon: load_icon(&root),
             ..window::Settings::default()
         },
+        flags: root,
         ..Settings::default()
     })
 }
 
+// icon.ico from the data root, or next to bathtub itself
+fn load_icon(root: &Path) -> Option<window::Icon> {
+    let beside_exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("icon.ico")));
+    let path = std::iter::once(root.join("icon.ico"))
+        .chain(beside_exe)
+        .find(|path| path.exists())?;
+    let icon = ImageReader::open(path).ok()?.decode().ok()?.to_rgba8();
+    window::Icon::from_rgba(icon.to_vec(), icon.width(), icon.height()).ok()
+}
+
 //#[derive(Debug)]
 enum Bathtub {
+    // which line to open, when there is more than one profile
+    Choosing(ProfilePicker),
     Loading,
     Loaded(State),
+    // config files that could not be loaded
+    Failed(Failure),
+}
+
+struct ProfilePicker {
+    root: PathBuf,
+    profiles: Vec<String>,
+    profile_btns: Vec<button::State>,
+    new_name: String,
+    new_name_state: text_input::State,
+    create_btn: button::State,
+    message: Option<String>,
+}
+
+impl ProfilePicker {
+    fn new(root: PathBuf, message: Option<String>) -> ProfilePicker {
+        let profiles = profile::list(&root);
+        ProfilePicker {
+            profile_btns: vec![button::State::new(); profiles.len()],
+            profiles,
+            root,
+            new_name: String::new(),
+            new_name_state: text_input::State::new(),
+            create_btn: button::State::new(),
+            message,
+        }
+    }
+}
+
+// config files that stopped Bathtub from starting, with a restore and default button for each
+struct Failure {
+    errors: Vec<ConfigError>,
+    files: Vec<String>,
+    restore_btns: Vec<button::State>,
+    default_btns: Vec<button::State>,
+    read_only_btn: button::State,
+}
+
+// shown over everything after GRBL raised an alarm or refused a command
+struct FaultDialog {
+    fault: Fault,
+    unlock_btn: button::State,
+    home_btn: button::State,
+    continue_btn: button::State,
+}
+
+impl FaultDialog {
+    fn new(fault: Fault) -> FaultDialog {
+        FaultDialog {
+            fault,
+            unlock_btn: button::State::new(),
+            home_btn: button::State::new(),
+            continue_btn: button::State::new(),
+        }
+    }
+}
+
+impl Failure {
+    fn new(errors: Vec<ConfigError>) -> Failure {
+        let mut files: Vec<String> = errors.iter().map(|e| e.file.clone()).collect();
+        files.dedup();
+        Failure {
+            restore_btns: vec![button::State::new(); files.len()],
+            default_btns: vec![button::State::new(); files.len()],
+            read_only_btn: button::State::new(),
+            errors,
+            files,
+        }
+    }
 }
 
 pub struct NodeTracker {
@@ -75,9 +199,25 @@ struct State {
     homing_required: Rc<RefCell<bool>>,
     grbl: Grbl,
     connected: bool,
+    // port picker shown while not connected
+    connect: Connect,
+    // still probing ports, checked again on the next tick
+    reconnecting: Option<Grbl>,
     logger: Logger,
     recipe_state: Arc<(Mutex<RecipeState>, Condvar)>,
     current_step: Option<mpsc::Receiver<Option<usize>>>,
+    // last written to LAST_STATE, loaded from it on start
+    last_state: Option<LastState>,
+    // nothing may move until GRBL's position has been checked against last_state
+    recovery_checked: bool,
+    // why a retract and home is needed, nothing may move until it is done
+    recovery: Option<String>,
+    recover_btn: button::State,
+    fault: Option<FaultDialog>,
+    // files that could not be loaded, nothing can be moved or saved while not empty
+    read_only: Vec<ConfigError>,
+    // errors in config/baths.toml, nothing is moved along the nodes until they are fixed
+    node_errors: Vec<String>,
 }
 
 impl State {
@@ -91,7 +231,42 @@ impl State {
         nodes: Nodes,
         actions: Actions,
         current_step_sender: mpsc::Sender<Option<usize>>,
-    ) -> Result<(), ()> {
+        progress: Option<Progress>,
+    ) -> Result<RunEnd, ()> {
+        // a run waits on grbl for as long as the recipe takes, kept off the executor's threads
+        tokio::task::spawn_blocking(move || {
+            State::run_recipe_blocking(
+                grbl,
+                logger,
+                recipe_state,
+                node_tracker,
+                recipe,
+                node_map,
+                nodes,
+                actions,
+                current_step_sender,
+                progress,
+            )
+        })
+        .await
+        .unwrap()
+    }
+
+    // same as run_recipe() for callers without an executor, like the headless runner.
+    // With progress the recipe starts at progress.step with progress.elapsed_ms of that step's
+    // action already done, and progress is kept on disk until the recipe finishes or is stopped
+    fn run_recipe_blocking(
+        grbl: Grbl,
+        logger: Logger,
+        recipe_state: Arc<(Mutex<RecipeState>, Condvar)>,
+        node_tracker: Arc<Mutex<NodeTracker>>,
+        recipe: Vec<Step>,
+        node_map: HashMap<String, usize>,
+        nodes: Nodes,
+        actions: Actions,
+        current_step_sender: mpsc::Sender<Option<usize>>,
+        mut progress: Option<Progress>,
+    ) -> Result<RunEnd, ()> {
         if (*node_tracker.lock().unwrap()).current.name[..] == *"HOME" {
             let state: RecipeState;
             {
@@ -104,28 +279,17 @@ impl State {
                 };
             }
             grbl.clear_responses();
-            grbl.push_command(Cmd::new("$H".to_string()));
-            grbl.push_command(Cmd::new("HomingWait".to_string()));
-            // wait for homing to finish
-            loop {
-                if grbl.is_ok() {
-                    // lock just to keep this idle while we wait for the homing to finish.
-                    let _wait_for_thread = grbl.command_buffer.lock().unwrap();
-                    if let Some(cmd) = grbl.pop_command() {
-                        if cmd.command[..] == *"HomingWait" {
-                            break;
-                        } else {
-                            thread::sleep(Duration::from_millis(100))
-                        }
-                    }
-                } else {
-                    break;
-                }
+            // grbl answers $H once homing is done
+            if let Err(err) = grbl.request_blocking(Cmd::new("$H".to_string())) {
+                logger
+                    .send_line(format!("{} => Homing failed, {}", Local::now().to_rfc2822(), err))
+                    .unwrap();
+                return Err(());
             }
             {
                 let (recipe_state, _) = &*recipe_state;
                 let mut recipe_state = recipe_state.lock().unwrap();
-                if discriminant(&*recipe_state) != discriminant(&RecipeState::Stopped) {
+                if !halted(*recipe_state) {
                     *recipe_state = state;
                 }
             }
@@ -138,12 +302,11 @@ impl State {
         let nodes2 = nodes.clone();
         thread::spawn(move || {
             while !break_and_hold(Arc::clone(&recipe_state2)) {
-                if let Some(grbl_stat) = gx.get_status() {
-                    if let Some(index) = nodes2.node.iter().position(|n| {
-                        (grbl_stat.x - n.x).abs() < 0.5
-                            && (grbl_stat.y - n.y).abs() < 0.5
-                            && (grbl_stat.z - n.z).abs() < 0.5
-                    }) {
+                // an alarm position can not be trusted, grbl may have lost steps
+                if let Some(grbl_stat) = gx.get_status().filter(|s| !position_lost(s)) {
+                    if let Some(index) = nearest_node(&nodes2, grbl_stat.x, grbl_stat.y, grbl_stat.z)
+                        .and_then(|n| nodes2.node.iter().position(|m| m.name == n.name))
+                    {
                         let mut nt2 = node_tracker2.lock().unwrap();
                         if nt2.next.len() == 1 && nt2.current.name == nt2.next[0].name {
                             nt2.next.clear()
@@ -164,20 +327,31 @@ impl State {
                                     &nt2.current
                                 ))
                                 .unwrap();
+                            logger2
+                                .send_event(Event::Arrived {
+                                    node: nt2.current.name.clone(),
+                                    x: nt2.current.x,
+                                    y: nt2.current.y,
+                                    z: nt2.current.z,
+                                })
+                                .unwrap();
                         }
                     }
                 }
                 thread::sleep(Duration::from_millis(1))
             }
         });
-        let mut current_step_num: Option<usize> = None;
-        for step in recipe {
-            if let Some(num) = &mut current_step_num {
-                *num += 1;
-            } else {
-                current_step_num = Some(0);
+        let start = progress.as_ref().map(|p| p.step).unwrap_or(0);
+        for (step_index, step) in recipe.into_iter().enumerate().skip(start) {
+            current_step_sender.send(Some(step_index)).unwrap();
+            if let Some(progress) = &mut progress {
+                if step_index != start {
+                    progress.elapsed_ms = 0;
+                }
+                progress.step = step_index;
+                progress.step_num = step.step_num.clone();
+                progress.save();
             }
-            current_step_sender.send(current_step_num).unwrap();
             let notify_user_input_recv = if step.wait {
                 logger
                     .send_line(format!(
@@ -186,16 +360,24 @@ impl State {
                         step.step_num
                     ))
                     .unwrap();
+                logger
+                    .send_event(Event::WaitingForInput {
+                        step: step.step_num.clone(),
+                    })
+                    .unwrap();
                 let (recipe_state, _) = &*recipe_state;
                 let mut recipe_state = recipe_state.lock().unwrap();
-                *recipe_state = RecipeState::RequireInput;
+                // keep a stop or fault that came in during the last step
+                if !halted(*recipe_state) {
+                    *recipe_state = RecipeState::RequireInput;
+                }
                 true
             } else {
                 false
             };
             if break_and_hold(Arc::clone(&recipe_state)) {
                 logger
-                    .send_line(format!("{} => Stopped By User", Local::now().to_rfc2822()))
+                    .send_line(stopped_line(&recipe_state))
                     .unwrap();
                 break;
             }
@@ -207,6 +389,11 @@ impl State {
                         step.step_num
                     ))
                     .unwrap();
+                logger
+                    .send_event(Event::InputReceived {
+                        step: step.step_num.clone(),
+                    })
+                    .unwrap();
             }
             logger
                 .send_line(format!(
@@ -216,6 +403,16 @@ impl State {
                     step.selected_destination
                 ))
                 .unwrap();
+            logger
+                .send_event(Event::StepStarted {
+                    step: step.step_num.clone(),
+                    destination: step.selected_destination.clone(),
+                    action: step.selected_action.clone(),
+                    planned_secs: step.hours_value.parse::<u64>().unwrap_or(0) * 3600
+                        + step.mins_value.parse::<u64>().unwrap_or(0) * 60
+                        + step.secs_value.parse::<u64>().unwrap_or(0),
+                })
+                .unwrap();
             // gen paths and send
             let hover = match step.hover {
                 true => "_hover",
@@ -229,7 +426,19 @@ impl State {
                         .get(&format!("{}{}", step.selected_destination, hover))
                     {
                         Some(n) => n,
-                        _ => break,
+                        None => {
+                            refuse_move(
+                                &logger,
+                                &grbl,
+                                &progress,
+                                &step.step_num,
+                                format!(
+                                    "'{}{}' is not in config/baths.toml",
+                                    step.selected_destination, hover
+                                ),
+                            );
+                            return Err(());
+                        }
                     }
                     .clone()];
                     logger
@@ -247,8 +456,18 @@ impl State {
                             future_node.z,
                         ))
                         .unwrap();
-                    let node_paths =
-                        paths::gen_node_paths(&nodes, &nt.current, future_node).unwrap();
+                    let node_paths = match paths::gen_node_paths(&nodes, &nt.current, future_node)
+                    {
+                        Ok(node_paths) => node_paths,
+                        Err(err) => {
+                            refuse_move(&logger, &grbl, &progress, &step.step_num, err.to_string());
+                            return Err(());
+                        }
+                    };
+                    if let Err(err) = nodes.limits.check_path(&nt.current, &node_paths.node) {
+                        refuse_move(&logger, &grbl, &progress, &step.step_num, err);
+                        return Err(());
+                    }
                     logger
                         .send_line(format!(
                             "{} => Step {}) on path {}",
@@ -266,30 +485,48 @@ impl State {
                             ),
                         ))
                         .unwrap();
+                    logger
+                        .send_event(Event::PathChosen {
+                            step: step.step_num.clone(),
+                            from: nt.current.name.clone(),
+                            to: future_node.name.clone(),
+                            path: node_paths.node.iter().map(|n| n.name.clone()).collect(),
+                        })
+                        .unwrap();
+                    let mut from = nt.current.clone();
                     for node in node_paths.node {
                         nt.next.push(node.clone());
+                        let gcode = paths::jog_gcode(&node, paths::feed_rate(&nodes, &from, &node));
                         logger
                             .send_line(format!(
                                 "{} => Step {}) Sending pathing G-code '{}'",
                                 Local::now().to_rfc2822(),
                                 step.step_num,
-                                format!("$J=X{} Y{} Z{} F250", node.x, node.y, node.x),
+                                gcode,
                             ))
                             .unwrap();
-                        grbl.push_command(Cmd::new(format!(
-                            "$J=X{} Y{} Z{} F250",
-                            node.x, node.y, node.z
-                        )));
+                        logger
+                            .send_event(Event::GcodeSent {
+                                step: step.step_num.clone(),
+                                gcode: gcode.clone(),
+                            })
+                            .unwrap();
+                        grbl.push_command(Cmd::new(gcode));
+                        from = node;
                     }
                 }
                 send_path_required = false;
                 while (node_tracker.lock().unwrap()).next.len() != 0 {
+                    // a refused jog never arrives, look for errors while waiting
+                    for response in grbl.clear_responses() {
+                        log_response(&logger, &grbl, &recipe_state, &step.step_num, response);
+                    }
                     let (recipe_state, _) = &*recipe_state;
                     match *recipe_state.lock().unwrap() {
-                        RecipeState::Stopped => break,
+                        RecipeState::Stopped | RecipeState::Fault => break,
                         RecipeState::RecipePaused => {
-                            grbl.push_command(Cmd::new("\u{85}".to_string()));
-                            set_pause_node(Arc::clone(&node_tracker), grbl.clone());
+                            grbl.realtime(Realtime::JogCancel);
+                            set_pause_node(Arc::clone(&node_tracker), grbl.get_status());
                             send_path_required = true;
                             break;
                         }
@@ -306,7 +543,7 @@ impl State {
                 + step.secs_value.parse::<u128>().unwrap_or(0) * 1000;
             if break_and_hold(Arc::clone(&recipe_state)) {
                 logger
-                    .send_line(format!("{} => Stopped By User", Local::now().to_rfc2822()))
+                    .send_line(stopped_line(&recipe_state))
                     .unwrap();
                 break;
             }
@@ -318,16 +555,14 @@ impl State {
                     step.selected_action
                 ))
                 .unwrap();
+            logger
+                .send_event(Event::ActionStarted {
+                    step: step.step_num.clone(),
+                    action: step.selected_action.clone(),
+                })
+                .unwrap();
             for response in grbl.clear_responses() {
-                logger
-                    .send_line(format!(
-                        "{} => Step {}) G-code '{}' responded '{}'",
-                        response.response_time.as_ref().unwrap().to_rfc2822(),
-                        step.step_num,
-                        response.command,
-                        response.result.unwrap(),
-                    ))
-                    .unwrap();
+                log_response(&logger, &grbl, &recipe_state, &step.step_num, response);
             }
             // send action steps
             // TODO: Hash map creation should be moved into state, not in loop
@@ -338,32 +573,74 @@ impl State {
             }
             if break_and_hold(Arc::clone(&recipe_state)) {
                 logger
-                    .send_line(format!("{} => Stopped By User", Local::now().to_rfc2822()))
+                    .send_line(stopped_line(&recipe_state))
                     .unwrap();
                 break;
             }
-            let action_commands = action_map.get(&step.selected_action).unwrap();
+            let action_commands = match action_map.get(&step.selected_action) {
+                Some(commands) => commands,
+                None => {
+                    refuse_move(
+                        &logger,
+                        &grbl,
+                        &progress,
+                        &step.step_num,
+                        format!("'{}' is not in config/actions.toml", step.selected_action),
+                    );
+                    return Err(());
+                }
+            };
+            let action_start = limits::position(&node_tracker.lock().unwrap().current);
+            if let Err(err) = nodes.limits.check_action(
+                action_start,
+                action_commands,
+                !action_commands.iter().any(|c| c == "WAIT"),
+            ) {
+                refuse_move(&logger, &grbl, &progress, &step.step_num, err);
+                return Err(());
+            }
             let mut queue_len = 0;
             for command in action_commands {
                 if break_and_hold(Arc::clone(&recipe_state)) {
                     logger
-                        .send_line(format!("{} => Stopped By User", Local::now().to_rfc2822()))
+                        .send_line(stopped_line(&recipe_state))
                         .unwrap();
                     break;
                 }
                 if command != &"WAIT".to_string() {
                     queue_len += 1;
+                    logger
+                        .send_event(Event::GcodeSent {
+                            step: step.step_num.clone(),
+                            gcode: command.clone(),
+                        })
+                        .unwrap();
                     grbl.push_command(Cmd::new(command.clone()));
                 } else {
                     contains_wait = true
                 }
             }
-            let mut timer = Instant::now();
+            // a resumed step only runs what was left of its action
+            let done_ms = progress
+                .as_ref()
+                .map(|p| p.elapsed_ms)
+                .unwrap_or(0);
+            let mut timer = Instant::now()
+                .checked_sub(Duration::from_millis(done_ms))
+                .unwrap_or(Instant::now());
+            let mut progress_saved = Instant::now();
             loop {
+                if let Some(progress) = &mut progress {
+                    if progress_saved.elapsed().as_millis() >= 1000 {
+                        progress.elapsed_ms = timer.elapsed().as_millis() as u64;
+                        progress.save();
+                        progress_saved = Instant::now();
+                    }
+                }
                 let baht = break_and_hold_timer(Arc::clone(&recipe_state));
                 if baht.0 {
                     logger
-                        .send_line(format!("{} => Stopped By User", Local::now().to_rfc2822()))
+                        .send_line(stopped_line(&recipe_state))
                         .unwrap();
                     break;
                 }
@@ -371,7 +648,8 @@ impl State {
                     timer += Duration::from_millis(ms_paused as u64);
                 }
                 if timer.elapsed().as_millis() >= mseconds {
-                    grbl.push_command(Cmd::new("\u{85}".to_string()));
+                    // nothing of the action may run on into the path to the next bath
+                    grbl.halt_blocking();
                     logger
                         .send_line(format!(
                             "{} => Step {}) finished {}",
@@ -380,31 +658,26 @@ impl State {
                             step.selected_action
                         ))
                         .unwrap();
+                    logger
+                        .send_event(Event::StepFinished {
+                            step: step.step_num.clone(),
+                            action: step.selected_action.clone(),
+                        })
+                        .unwrap();
                     break;
                 } else if !contains_wait {
                     for response in grbl.clear_responses() {
                         if queue_len != 0 {
                             queue_len -= 1
                         }
-                        logger
-                            .send_line(format!(
-                                "{} => Step {}) G-code '{}' responded '{}'",
-                                response.response_time.as_ref().unwrap().to_rfc2822(),
-                                step.step_num,
-                                response.command,
-                                response.result.unwrap(),
-                            ))
-                            .unwrap();
+                        log_response(&logger, &grbl, &recipe_state, &step.step_num, response);
                     }
                     if queue_len == 0 {
                         for command in action_commands {
                             let baht2 = break_and_hold_timer(Arc::clone(&recipe_state));
                             if baht2.0 {
                                 logger
-                                    .send_line(format!(
-                                        "{} => Stopped By User",
-                                        Local::now().to_rfc2822()
-                                    ))
+                                    .send_line(stopped_line(&recipe_state))
                                     .unwrap();
                                 break;
                             }
@@ -419,6 +692,12 @@ impl State {
                                     command.clone()
                                 ))
                                 .unwrap();
+                            logger
+                                .send_event(Event::GcodeSent {
+                                    step: step.step_num.clone(),
+                                    gcode: command.clone(),
+                                })
+                                .unwrap();
                             queue_len += 1;
                             grbl.push_command(Cmd::new(command.clone()));
                         }
@@ -426,10 +705,32 @@ impl State {
                 }
             }
         }
-        Ok(())
+        if progress.is_some() {
+            let (recipe_state, _) = &*recipe_state;
+            let rs = *recipe_state.lock().unwrap();
+            let stopped = discriminant(&rs) == discriminant(&RecipeState::Stopped);
+            // stopped without the user asking means GRBL was lost, keep where we were. After a
+            // fault the run can be picked up again once the gantry is recovered
+            if discriminant(&rs) != discriminant(&RecipeState::Fault) && (!stopped || grbl.is_ok()) {
+                Progress::clear();
+            }
+        }
+        let (recipe_state, _) = &*recipe_state;
+        let rs = *recipe_state.lock().unwrap();
+        if halted(rs) {
+            grbl.halt_blocking();
+        }
+        Ok(if halted(rs) { RunEnd::Stopped } else { RunEnd::Completed })
     }
 }
 
+// how a run that was not refused ended, only a completed run went through its last step
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunEnd {
+    Completed,
+    Stopped,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum RecipeState {
     Stopped,
@@ -439,6 +740,8 @@ pub enum RecipeState {
     RequireInput,
     HomingManual,
     HomingRun,
+    // GRBL raised an alarm or refused a command, nothing moves until the fault dialog is done
+    Fault,
 }
 
 struct Tabs {
@@ -594,43 +897,80 @@ struct LoadState {
     nodes: Nodes,
     node_map: HashMap<String, usize>,
     actions: Actions,
+    report: Report,
+    read_only: Vec<ConfigError>,
 }
 
 #[derive(Debug, Clone)]
 enum LoadError {
-    Nodes,
+    Config(Vec<ConfigError>),
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     TabBar(TabBarMessage),
-    RecipeDone(Result<(), ()>),
+    RecipeDone(Result<RunEnd, ()>),
     Manual(ManualMessage),
     Build(BuildMessage),
     Run(RunMessage),
     Advanced(AdvancedMessage),
     Loaded(Result<LoadState, LoadError>),
     Tick,
+    Recover,
+    Recovered(Result<(), GrblError>),
+    FaultUnlock,
+    FaultHome,
+    FaultContinue,
+    FaultCleared(Result<(), GrblError>),
+    MaxTravel(Option<[f32; 3]>),
+    Connect(ConnectMessage),
+    RestoreBackup(usize),
+    CreateDefault(usize),
+    OpenReadOnly,
+    ProfileChosen(usize),
+    ProfileNameChanged(String),
+    CreateProfile,
 }
 
 impl<'a> Application for Bathtub {
     type Executor = iced::executor::Default;
     type Message = Message;
-    type Flags = ();
+    // data root, see profile::data_root
+    type Flags = PathBuf;
 
-    fn new(_flags: ()) -> (Bathtub, Command<Message>) {
-        (
-            Bathtub::Loading,
-            Command::perform(LoadState::load(), Message::Loaded),
-        )
+    fn new(root: PathBuf) -> (Bathtub, Command<Message>) {
+        let entered = profile::chosen(&root).and_then(|chosen| match chosen {
+            Some(name) => profile::enter(&root, Some(&name)).map(|_| true),
+            None if profile::list(&root).is_empty() => profile::enter(&root, None).map(|_| true),
+            None => Ok(false),
+        });
+        match entered {
+            Ok(true) => (
+                Bathtub::Loading,
+                Command::perform(LoadState::load(false), Message::Loaded),
+            ),
+            Ok(false) => (
+                Bathtub::Choosing(ProfilePicker::new(root, None)),
+                Command::none(),
+            ),
+            Err(err) => (
+                Bathtub::Choosing(ProfilePicker::new(root, Some(err))),
+                Command::none(),
+            ),
+        }
     }
 
     fn title(&self) -> String {
-        String::from("Bathtub")
+        match profile::name() {
+            Some(name) => format!("Bathtub - {}", name),
+            None => String::from("Bathtub"),
+        }
     }
 
     fn subscription(&self) -> Subscription<Message> {
         match self {
+            // grbl is left alone in read-only
+            Bathtub::Loaded(state) if !state.read_only.is_empty() => Subscription::none(),
             Bathtub::Loaded(state) => {
                 if state.grbl.is_ok() {
                     return time::every(Duration::from_millis(50)).map(|_| Message::Tick);
@@ -645,6 +985,32 @@ impl<'a> Application for Bathtub {
     fn update(&mut self, message: Message, _clipboard: &mut Clipboard) -> Command<Message> {
         let mut command = Command::none(); // setup to allow nested match statements to return different command
         match self {
+            Bathtub::Choosing(picker) => match message {
+                Message::ProfileChosen(i) => {
+                    match profile::enter(&picker.root, Some(&picker.profiles[i])) {
+                        Ok(_) => {
+                            *self = Bathtub::Loading;
+                            Command::perform(LoadState::load(false), Message::Loaded)
+                        }
+                        Err(err) => {
+                            picker.message = Some(err);
+                            Command::none()
+                        }
+                    }
+                }
+                Message::ProfileNameChanged(name) => {
+                    picker.new_name = name;
+                    Command::none()
+                }
+                Message::CreateProfile => {
+                    match profile::create(&picker.root, &picker.new_name) {
+                        Ok(_) => *picker = ProfilePicker::new(picker.root.clone(), None),
+                        Err(err) => picker.message = Some(err),
+                    }
+                    Command::none()
+                }
+                _ => Command::none(),
+            },
             Bathtub::Loading => {
                 match message {
                     Message::Loaded(Ok(state)) => {
@@ -660,7 +1026,7 @@ impl<'a> Application for Bathtub {
                         let recipe_state =
                             Arc::new((Mutex::new(RecipeState::Stopped), Condvar::new()));
                         let grbl = grbl::new();
-                        let logger = Logger::new();
+                        let mut logger = Logger::new();
                         let homing_required = Rc::new(RefCell::new(true));
                         let mut unsaved_tabs_local = HashMap::with_capacity(2);
                         unsaved_tabs_local.insert(TabState::Build, false);
@@ -712,21 +1078,77 @@ impl<'a> Application for Bathtub {
                             homing_required,
                             grbl: grbl.clone(),
                             connected: true,
+                            connect: Connect::new(),
+                            reconnecting: None,
                             logger: logger.clone(),
                             recipe_state: Arc::clone(&recipe_state),
                             current_step: None,
+                            last_state: LastState::load(),
+                            recovery_checked: false,
+                            recovery: None,
+                            recover_btn: button::State::new(),
+                            fault: None,
+                            read_only: state.read_only.clone(),
+                            node_errors: state.report.errors.clone(),
                         });
+                        if !state.read_only.is_empty() {
+                            logger.set_log_file(format!(
+                                "{}; Config - Opened read-only",
+                                Local::now().to_rfc2822()
+                            ));
+                            logger.send_line(String::new()).unwrap();
+                            for error in &state.read_only {
+                                logger.send_line(error.to_string()).unwrap();
+                            }
+                        }
+                        // the same problems are listed in Advanced > Nodes until they are fixed
+                        if !state.report.errors.is_empty() || !state.report.warnings.is_empty() {
+                            logger.set_log_file(format!(
+                                "{}; Nodes - Problems in config/baths.toml",
+                                Local::now().to_rfc2822()
+                            ));
+                            logger.send_line(String::new()).unwrap();
+                            for error in &state.report.errors {
+                                logger.send_line(format!("Error: {}", error)).unwrap();
+                            }
+                            for warning in &state.report.warnings {
+                                logger.send_line(format!("Warning: {}", warning)).unwrap();
+                            }
+                        }
                     }
-                    Message::Loaded(Err(_)) => {
-                        panic!("somehow loaded had an error")
-                        // need to figure out how to notify user of errors
-                        // need to add correct fail state, following is from the Todos example
-                        //*self = Bathtub::Loaded(State::default());
+                    Message::Loaded(Err(LoadError::Config(errors))) => {
+                        *self = Bathtub::Failed(Failure::new(errors));
                     }
                     _ => {}
                 }
                 Command::none()
             }
+            Bathtub::Failed(failure) => {
+                // the broken file is kept as <file>.broken by both
+                let fixed = match message {
+                    Message::RestoreBackup(i) => config::restore_backup(&failure.files[i]),
+                    Message::CreateDefault(i) => config::create_default(&failure.files[i]),
+                    Message::OpenReadOnly => {
+                        *self = Bathtub::Loading;
+                        return Command::perform(LoadState::load(true), Message::Loaded);
+                    }
+                    _ => Err(()),
+                };
+                match fixed {
+                    Ok(_) => {
+                        *self = Bathtub::Loading;
+                        Command::perform(LoadState::load(false), Message::Loaded)
+                    }
+                    Err(_) => Command::none(),
+                }
+            }
+            // nothing may move and nothing may be written over the files that did not load
+            Bathtub::Loaded(state) if !state.read_only.is_empty() && writes(&message) => {
+                Command::none()
+            }
+            Bathtub::Loaded(state) if !state.node_errors.is_empty() && moves(&message) => {
+                Command::none()
+            }
             Bathtub::Loaded(state) => {
                 match message {
                     Message::TabBar(TabBarMessage::Manual) => {
@@ -756,14 +1178,14 @@ impl<'a> Application for Bathtub {
                             _ => {
                                 *recipe_state = RecipeState::Stopped;
                                 cvar.notify_all();
-                                state.grbl.push_command(Cmd::new("\u{85}".to_string()));
-                                set_pause_node(Arc::clone(&state.node_tracker), state.grbl.clone());
+                                state.grbl.realtime(Realtime::JogCancel);
+                                set_pause_node(Arc::clone(&state.node_tracker), state.grbl.get_status());
                             }
                         }
                     }
-                    Message::Manual(ManualMessage::ThankYou(cmd)) => {
+                    Message::Manual(ManualMessage::ThankYou(command, answer)) => {
                         state.tabs.advanced.update_logs();
-                        state.tabs.manual.update(ManualMessage::ThankYou(cmd));
+                        state.tabs.manual.update(ManualMessage::ThankYou(command, answer));
                     }
                     Message::Manual(ManualMessage::ButtonPressed(node)) => {
                         let (recipe_state, _) = &*state.recipe_state;
@@ -799,6 +1221,7 @@ impl<'a> Application for Bathtub {
                                     state.nodes.borrow().clone(),
                                     state.actions.borrow().clone(),
                                     tx,
+                                    None,
                                 ),
                                 Message::RecipeDone,
                             );
@@ -841,6 +1264,8 @@ impl<'a> Application for Bathtub {
                             state.tabs.advanced.update_logs();
                             let (tx, rx) = mpsc::channel();
                             state.current_step = Some(rx);
+                            state.tabs.run.running =
+                                state.tabs.run.starting.as_ref().map(|p| p.log.clone());
                             command = Command::perform(
                                 State::run_recipe(
                                     state.grbl.clone(),
@@ -852,6 +1277,7 @@ impl<'a> Application for Bathtub {
                                     state.nodes.borrow().clone(),
                                     state.actions.borrow().clone(),
                                     tx,
+                                    state.tabs.run.starting.take(),
                                 ),
                                 Message::RecipeDone,
                             );
@@ -863,51 +1289,101 @@ impl<'a> Application for Bathtub {
                             .logger
                             .send_line(format!("{} => Paused by user", Local::now().to_rfc2822()))
                             .unwrap();
+                        state.logger.send_event(Event::Paused).unwrap();
                         let (recipe_state, cvar) = &*state.recipe_state;
                         let mut recipe_state = recipe_state.lock().unwrap();
                         *recipe_state = RecipeState::RecipePaused;
                         cvar.notify_all();
-                        state.grbl.push_command(Cmd::new("\u{85}".to_string()));
+                        // unlike a jog cancel this also holds G0/G1 action commands
+                        state.grbl.realtime(Realtime::FeedHold);
                     }
                     Message::Run(RunMessage::Resume) => {
                         state
                             .logger
                             .send_line(format!("{} => Resumed by user", Local::now().to_rfc2822()))
                             .unwrap();
+                        state.logger.send_event(Event::Resumed).unwrap();
                         let (recipe_state, cvar) = &*state.recipe_state;
                         let mut recipe_state = recipe_state.lock().unwrap();
                         *recipe_state = RecipeState::RecipeRunning;
                         cvar.notify_all();
+                        state.grbl.realtime(Realtime::CycleStart);
                     }
+                    Message::Run(RunMessage::Override(command)) => state.grbl.realtime(command),
                     Message::Run(RunMessage::Stop) => {
+                        state.logger.send_event(Event::Stopped).unwrap();
                         {
                             let (recipe_state, cvar) = &*state.recipe_state;
                             let mut recipe_state = recipe_state.lock().unwrap();
                             *recipe_state = RecipeState::Stopped;
                             cvar.notify_all();
                         }
-                        state.grbl.push_command(Cmd::new("\u{85}".to_string()));
-                        set_pause_node(Arc::clone(&state.node_tracker), state.grbl.clone());
+                        // a reset while held throws away the held moves and keeps the position
+                        let status = state.grbl.get_status();
+                        match &status {
+                            Some(s) if s.status == "Hold" => state.grbl.realtime(Realtime::SoftReset),
+                            // the recipe thread stops the moves grbl has planned once it sees this
+                            _ => state.grbl.drop_queued(),
+                        }
+                        set_pause_node(Arc::clone(&state.node_tracker), status);
                         state.tabs.run.state = if state.tabs.run.required_after_inputs.len() > 0 {
                             RunState::AfterRequiredInput
                         } else {
                             RunState::Standard
                         };
                     }
-                    Message::RecipeDone(Ok(_)) => {
+                    Message::RecipeDone(Ok(end)) => {
                         state.current_step = None;
                         state.tabs.run.current_step = None;
-                        state
-                            .logger
-                            .send_line(format!("{} => Done", Local::now().to_rfc2822()))
-                            .unwrap();
+                        // progress is only left behind when the run was cut short, manual moves
+                        // write none and must not pick up what an older run left
+                        let interrupted = match state.tabs.run.running.take() {
+                            Some(log) => {
+                                let progress = Progress::load().filter(|p| p.log == log);
+                                state.tabs.run.set_interrupted(progress.clone());
+                                progress
+                            }
+                            None => None,
+                        };
+                        match &interrupted {
+                            // a stop or fault was logged when it happened
+                            None if end == RunEnd::Stopped => {}
+                            Some(progress) => {
+                                state
+                                    .logger
+                                    .send_line(format!(
+                                        "{} => Interrupted at step {}",
+                                        Local::now().to_rfc2822(),
+                                        progress.step_num
+                                    ))
+                                    .unwrap();
+                                state
+                                    .logger
+                                    .send_event(Event::Interrupted {
+                                        step: progress.step_num.clone(),
+                                    })
+                                    .unwrap();
+                            }
+                            None => {
+                                state
+                                    .logger
+                                    .send_line(format!("{} => Done", Local::now().to_rfc2822()))
+                                    .unwrap();
+                                state.logger.send_event(Event::Done).unwrap();
+                            }
+                        }
                         {
+                            // a fault stays until the dialog is done with it
                             let (recipe_state, cvar) = &*state.recipe_state;
                             let mut recipe_state = recipe_state.lock().unwrap();
-                            *recipe_state = RecipeState::Stopped;
+                            if !halted(*recipe_state) {
+                                *recipe_state = RecipeState::Stopped;
+                            }
                             cvar.notify_all();
                         }
-                        state.tabs.run.state = if state.tabs.run.required_after_inputs.len() > 0 {
+                        state.tabs.run.state = if interrupted.is_none()
+                            && state.tabs.run.required_after_inputs.len() > 0
+                        {
                             RunState::AfterRequiredInput
                         } else {
                             RunState::Standard
@@ -917,9 +1393,12 @@ impl<'a> Application for Bathtub {
                     Message::RecipeDone(Err(_err)) => {
                         state.current_step = None;
                         state.tabs.run.current_step = None;
+                        state.tabs.run.running = None;
                         let (recipe_state, cvar) = &*state.recipe_state;
                         let mut recipe_state = recipe_state.lock().unwrap();
-                        *recipe_state = RecipeState::Stopped;
+                        if !halted(*recipe_state) {
+                            *recipe_state = RecipeState::Stopped;
+                        }
                         cvar.notify_all();
                         state.tabs.advanced.update_logs();
                     }
@@ -929,13 +1408,118 @@ impl<'a> Application for Bathtub {
                                 state.tabs.run.current_step = num;
                             }
                         }
+                        state.tabs.run.update_eta();
                         if state.grbl.is_ok() /* to run without check for connected GRBL, replace state.grbl.is_ok() with true */ {
+                            if let Some(fault) = state.grbl.take_fault() {
+                                let (recipe_state, cvar) = &*state.recipe_state;
+                                let mut recipe_state = recipe_state.lock().unwrap();
+                                // nothing was running, the fault gets a log of its own
+                                if halted(*recipe_state) {
+                                    state.logger.set_log_file(format!(
+                                        "{}; {}",
+                                        Local::now().to_rfc2822(),
+                                        fault.title()
+                                    ));
+                                    state.logger.send_line(String::new()).unwrap();
+                                }
+                                state
+                                    .logger
+                                    .send_line(format!(
+                                        "{} => {}: {}",
+                                        Local::now().to_rfc2822(),
+                                        fault.title(),
+                                        fault.description()
+                                    ))
+                                    .unwrap();
+                                state
+                                    .logger
+                                    .send_event(Event::Fault {
+                                        fault: fault.title(),
+                                        description: fault.description(),
+                                    })
+                                    .unwrap();
+                                *recipe_state = RecipeState::Fault;
+                                cvar.notify_all();
+                                set_pause_node(
+                                    Arc::clone(&state.node_tracker),
+                                    state.grbl.get_status(),
+                                );
+                                // commands refused after an alarm do not hide it
+                                let alarmed =
+                                    state.fault.as_ref().map_or(false, |d| d.fault.is_alarm());
+                                if !alarmed || fault.is_alarm() {
+                                    state.fault = Some(FaultDialog::new(fault));
+                                }
+                                state.tabs.advanced.update_logs();
+                            }
                             let stat = state.grbl.get_status();
                             if let Some(s) = stat {
                                 state.tabs.manual.status = format!(
-                                    "{} state at\n({:.3}, {:.3}, {:.3})",
-                                    &s.status, &s.x, &s.y, &s.z
-                                )
+                                    "{} state at\n({:.3}, {:.3}, {:.3}){}",
+                                    s.state_text(),
+                                    &s.x,
+                                    &s.y,
+                                    &s.z,
+                                    match s.pins.triggered() {
+                                        pins if pins.is_empty() => String::new(),
+                                        pins => format!("\nTriggered: {}", pins.join(", ")),
+                                    }
+                                );
+                                state.tabs.advanced.grbl_tab.status = Some(s.clone());
+                                let overrides = Some((s.feed_override, s.rapid_override));
+                                state.tabs.manual.overrides_percent = overrides;
+                                state.tabs.run.overrides_percent = overrides;
+                                if !state.recovery_checked {
+                                    state.recovery_checked = true;
+                                    command = Command::perform(
+                                        limits::max_travel(state.grbl.clone()),
+                                        Message::MaxTravel,
+                                    );
+                                    state.recovery = recovery_reason(
+                                        &s,
+                                        &state.last_state,
+                                        &*state.nodes.borrow(),
+                                    );
+                                    // GRBL still knows where it is, start from there instead of HOME
+                                    if !position_lost(&s) {
+                                        if let Some(node) =
+                                            nearest_node(&*state.nodes.borrow(), s.x, s.y, s.z)
+                                        {
+                                            state.node_tracker.lock().unwrap().current =
+                                                node.clone();
+                                        }
+                                    }
+                                    if let Some(reason) = &state.recovery {
+                                        state.logger.set_log_file(format!(
+                                            "{}; Recovery - Retract and home required",
+                                            Local::now().to_rfc2822()
+                                        ));
+                                        state.logger.send_line(String::new()).unwrap();
+                                        state
+                                            .logger
+                                            .send_line(format!(
+                                                "{} => {} GRBL reports {} at ({:.3}, {:.3}, {:.3})",
+                                                Local::now().to_rfc2822(),
+                                                reason,
+                                                s.status,
+                                                s.x,
+                                                s.y,
+                                                s.z
+                                            ))
+                                            .unwrap();
+                                        state.tabs.advanced.update_logs();
+                                    }
+                                }
+                                // keep the record of a crash until the gantry is recovered
+                                if state.recovery.is_none() {
+                                    let rs = *state.recipe_state.0.lock().unwrap();
+                                    recovery::record(
+                                        &mut state.last_state,
+                                        &s,
+                                        &*state.nodes.borrow(),
+                                        rs,
+                                    );
+                                }
                             }
                         } else {
                             if state.connected {
@@ -943,25 +1527,51 @@ impl<'a> Application for Bathtub {
                                 let (recipe_state, _) = &*state.recipe_state;
                                 let mut recipe_state = recipe_state.lock().unwrap();
                                 *recipe_state = RecipeState::Stopped;
-                                state.logger.set_log_file(format!(
-                                    "{}; GRBL Critical error! - Connection Lost",
-                                    Local::now().to_rfc2822()
-                                ));
-                                state.logger.send_line(String::new()).unwrap();
-                                state.logger.send_line(format!("{}; More detailed information not currently logged by Bathtub.", Local::now().to_rfc2822())).unwrap();
+                                if let Link::Failed(problem) = state.grbl.link() {
+                                    // never connected, no port answered like grbl
+                                    state.logger.set_log_file(format!(
+                                        "{}; GRBL not found",
+                                        Local::now().to_rfc2822()
+                                    ));
+                                    state.logger.send_line(String::new()).unwrap();
+                                    state.logger.send_line(problem.clone()).unwrap();
+                                    state.connect.problem = Some(problem);
+                                } else {
+                                    state.logger.set_log_file(format!(
+                                        "{}; GRBL Critical error! - Connection Lost",
+                                        Local::now().to_rfc2822()
+                                    ));
+                                    state.logger.send_line(String::new()).unwrap();
+                                    state.logger.send_line(format!("{}; More detailed information not currently logged by Bathtub.", Local::now().to_rfc2822())).unwrap();
+                                }
                             }
                             state.connected = false;
-                            let grbl = grbl::new();
-                            thread::sleep(Duration::from_millis(100));
-                            if grbl.is_ok() {
+                            // a new connection starts from a reset grbl
+                            state.fault = None;
+                            let grbl = state.reconnecting.take().unwrap_or_else(grbl::new);
+                            let port = match grbl.link() {
+                                Link::Probing => {
+                                    state.reconnecting = Some(grbl.clone());
+                                    None
+                                }
+                                Link::Failed(problem) => {
+                                    state.connect.problem = Some(problem);
+                                    None
+                                }
+                                Link::Connected(port) => Some(port),
+                            };
+                            if port.is_some() && grbl.is_ok() {
                                 state.logger.set_log_file(format!(
                                     "{}; GRBL Connection reestablished!",
                                     Local::now().to_rfc2822()
                                 ));
                                 state.logger.send_line(String::new()).unwrap();
-                                state.logger.send_line(format!("{}; More detailed information not currently logged by Bathtub.", Local::now().to_rfc2822())).unwrap();
+                                state.logger.send_line(format!("{}; Connected on {}.", Local::now().to_rfc2822(), port.unwrap())).unwrap();
+                                state.connect.problem = None;
                                 *state.homing_required.borrow_mut() = true;
                                 state.connected = true;
+                                // GRBL may have been reset with the head lowered
+                                state.recovery_checked = false;
                                 state.grbl = grbl.clone();
                                 state.tabs.manual.grbl = grbl.clone();
                                 state.tabs.advanced.grbl_tab.grbl = grbl.clone();
@@ -980,6 +1590,147 @@ impl<'a> Application for Bathtub {
                             state.tabs.advanced.update_logs();
                         }
                     }
+                    Message::Connect(msg) => state.connect.update(msg),
+                    Message::MaxTravel(max_travel) => {
+                        state.nodes.borrow_mut().limits.max_travel = max_travel;
+                        state.tabs.run.update_dry_run();
+                    }
+                    Message::Recover => {
+                        {
+                            let (recipe_state, _) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = RecipeState::HomingManual;
+                        }
+                        state
+                            .logger
+                            .send_line(format!(
+                                "{} => Retracting to hover height and homing",
+                                Local::now().to_rfc2822()
+                            ))
+                            .unwrap();
+                        command = Command::perform(
+                            recovery::recover(
+                                state.grbl.clone(),
+                                state.nodes.borrow().clone(),
+                                state.nodes.borrow().feed.unwrap_or(paths::DEFAULT_FEED),
+                                false,
+                            ),
+                            Message::Recovered,
+                        );
+                    }
+                    // homing failed with an alarm, the fault dialog takes over
+                    Message::Recovered(_)
+                        if discriminant(&*state.recipe_state.0.lock().unwrap())
+                            == discriminant(&RecipeState::Fault) => {}
+                    // the dialog that asked stays open to try again
+                    Message::Recovered(Err(ref err)) | Message::FaultCleared(Err(ref err)) => {
+                        let what = match message {
+                            Message::Recovered(_) => "Homing",
+                            _ => "Unlocking",
+                        };
+                        {
+                            let (recipe_state, cvar) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = match state.fault {
+                                Some(_) => RecipeState::Fault,
+                                None => RecipeState::Stopped,
+                            };
+                            cvar.notify_all();
+                        }
+                        state
+                            .logger
+                            .send_line(format!(
+                                "{} => {} failed, {}",
+                                Local::now().to_rfc2822(),
+                                what,
+                                err
+                            ))
+                            .unwrap();
+                        state.tabs.advanced.update_logs();
+                    }
+                    Message::Recovered(Ok(_)) => {
+                        {
+                            let (recipe_state, cvar) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = RecipeState::Stopped;
+                            cvar.notify_all();
+                        }
+                        state
+                            .logger
+                            .send_line(format!("{} => Homed", Local::now().to_rfc2822()))
+                            .unwrap();
+                        state.recovery = None;
+                        state.fault = None;
+                        state.node_tracker.lock().unwrap().current = state.nodes.borrow().node
+                            [state
+                                .node_map
+                                .borrow()
+                                .get(&"HOME".to_string())
+                                .unwrap()
+                                .clone()]
+                        .clone();
+                        state.tabs.advanced.update_logs();
+                    }
+                    Message::FaultUnlock => {
+                        let reset = state.fault.as_ref().map_or(false, |f| f.fault.needs_reset());
+                        {
+                            let (recipe_state, _) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = RecipeState::HomingManual;
+                        }
+                        state
+                            .logger
+                            .send_line(format!("{} => Unlocking GRBL", Local::now().to_rfc2822()))
+                            .unwrap();
+                        command = Command::perform(
+                            recovery::unlock(state.grbl.clone(), reset),
+                            Message::FaultCleared,
+                        );
+                    }
+                    Message::FaultHome => {
+                        let reset = state.fault.as_ref().map_or(false, |f| f.fault.needs_reset());
+                        {
+                            let (recipe_state, _) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = RecipeState::HomingManual;
+                        }
+                        state
+                            .logger
+                            .send_line(format!(
+                                "{} => Retracting to hover height and homing",
+                                Local::now().to_rfc2822()
+                            ))
+                            .unwrap();
+                        command = Command::perform(
+                            recovery::recover(
+                                state.grbl.clone(),
+                                state.nodes.borrow().clone(),
+                                state.nodes.borrow().feed.unwrap_or(paths::DEFAULT_FEED),
+                                reset,
+                            ),
+                            Message::Recovered,
+                        );
+                    }
+                    // an alarm came while unlocking, the fault dialog takes over again
+                    Message::FaultCleared(_)
+                        if discriminant(&*state.recipe_state.0.lock().unwrap())
+                            == discriminant(&RecipeState::Fault) => {}
+                    Message::FaultContinue | Message::FaultCleared(Ok(_)) => {
+                        // a reset while held throws away the held moves and keeps the position
+                        if let Message::FaultContinue = message {
+                            if let Some(s) = state.grbl.get_status() {
+                                if s.status == "Hold" {
+                                    state.grbl.realtime(Realtime::SoftReset);
+                                }
+                            }
+                        }
+                        {
+                            let (recipe_state, cvar) = &*state.recipe_state;
+                            *recipe_state.lock().unwrap() = RecipeState::Stopped;
+                            cvar.notify_all();
+                        }
+                        state
+                            .logger
+                            .send_line(format!("{} => Fault cleared", Local::now().to_rfc2822()))
+                            .unwrap();
+                        state.fault = None;
+                        state.tabs.advanced.update_logs();
+                    }
                     Message::Manual(msg) => {
                         command = state
                             .tabs
@@ -1000,6 +1751,8 @@ impl<'a> Application for Bathtub {
                     }
                     Message::Advanced(AdvancedMessage::NodesTab(NodeTabMessage::Saved(_))) => {
                         state.tabs.manual.update_grid();
+                        state.node_errors =
+                            validate(&nodes::get_baths_config().unwrap_or(Nodes::new())).errors;
                         command = state
                             .tabs
                             .advanced
@@ -1022,41 +1775,113 @@ impl<'a> Application for Bathtub {
 
     fn view(&mut self) -> Element<Message> {
         match self {
+            Bathtub::Choosing(picker) => picker_view(picker),
             Bathtub::Loading => loading_message("Loading . . ."),
+            Bathtub::Failed(failure) => failure_view(failure),
             Bathtub::Loaded(State {
                 state,
                 tabs,
                 tab_bar,
                 recipe_state,
                 connected,
+                recovery_checked,
+                recovery,
+                recover_btn,
+                fault,
+                grbl,
+                read_only,
+                node_errors,
+                connect,
                 ..
             }) => match state {
-                _ if !*connected => Row::with_children(vec![
-                    Space::with_width(Length::Fill).into(),
-                    Column::with_children(vec![
-                        Text::new("Unable to connect to GRBL.")
-                            .font(CQ_MONO)
+                TabState::Manual | TabState::Run if !read_only.is_empty() => Column::new()
+                    .push(tab_bar.view().map(move |msg| Message::TabBar(msg)))
+                    .push(read_only_banner(read_only))
+                    .push(Space::with_height(Length::Units(100)))
+                    .push(
+                        Text::new("Unavailable in read-only mode")
                             .size(50)
-                            .into(),
-                        Text::new("Here are some things to check:").size(25).into(),
-                        Text::new("1) GRBL is powered on.").size(25).into(),
-                        Text::new("2) The USB cable is connected between this computer and GRBL.")
-                            .size(25)
-                            .into(),
-                        Text::new(
-                            "3) There are no other GRBL realted applications open on this PC.",
-                        )
-                        .size(25)
-                        .into(),
-                        Text::new("4) You have asked GRBL to please work.")
-                            .size(25)
-                            .into(),
-                    ])
+                            .font(CQ_MONO),
+                    )
+                    .align_items(Align::Center)
+                    .into(),
+                TabState::Build if !read_only.is_empty() => Column::new()
+                    .push(tab_bar.view().map(move |msg| Message::TabBar(msg)))
+                    .push(read_only_banner(read_only))
+                    .push(tabs.build.view().map(move |msg| Message::Build(msg)))
+                    .into(),
+                TabState::Advanced if !read_only.is_empty() => Column::new()
+                    .push(tab_bar.view().map(move |msg| Message::TabBar(msg)))
+                    .push(read_only_banner(read_only))
+                    .push(tabs.advanced.view().map(move |msg| Message::Advanced(msg)))
                     .into(),
+                TabState::Manual | TabState::Run if !node_errors.is_empty() => Column::new()
+                    .push(tab_bar.view().map(move |msg| Message::TabBar(msg)))
+                    .push(node_errors_banner(node_errors))
+                    .push(Space::with_height(Length::Units(100)))
+                    .push(
+                        Text::new("Unavailable until config/baths.toml is fixed")
+                            .size(50)
+                            .font(CQ_MONO),
+                    )
+                    .align_items(Align::Center)
+                    .into(),
+                _ if !*connected => Row::with_children(vec![
+                    Space::with_width(Length::Fill).into(),
+                    connect.view().map(move |msg| Message::Connect(msg)),
                     Space::with_width(Length::Fill).into(),
                 ])
                 .padding(30)
                 .into(),
+                _ if !*recovery_checked => loading_message("Checking where the gantry is . . ."),
+                _ if fault.is_some() => {
+                    let busy = discriminant(&*recipe_state.0.lock().unwrap())
+                        == discriminant(&RecipeState::HomingManual);
+                    fault_view(fault.as_mut().unwrap(), grbl.get_status(), busy)
+                }
+                _ if recovery.is_some() => {
+                    let homing = discriminant(&*recipe_state.0.lock().unwrap())
+                        == discriminant(&RecipeState::HomingManual);
+                    Row::with_children(vec![
+                        Space::with_width(Length::Fill).into(),
+                        Column::with_children(vec![
+                            Text::new("Bathtub did not shut down safely.")
+                                .font(CQ_MONO)
+                                .size(50)
+                                .into(),
+                            Text::new(recovery.as_ref().unwrap()).size(25).into(),
+                            Text::new(
+                                "The head will be raised straight up to hover height, then homed.\n\
+                                 Make sure nothing is in the way of the gantry before continuing.",
+                            )
+                            .size(25)
+                            .into(),
+                            match homing {
+                                true => Text::new("Retracting and homing . . .")
+                                    .font(CQ_MONO)
+                                    .size(30)
+                                    .into(),
+                                false => Button::new(
+                                    recover_btn,
+                                    Text::new("Retract and Home")
+                                        .size(30)
+                                        .horizontal_alignment(HorizontalAlignment::Center)
+                                        .font(CQ_MONO),
+                                )
+                                .style(Theme::Yellow)
+                                .on_press(Message::Recover)
+                                .padding(10)
+                                .width(Length::Units(400))
+                                .into(),
+                            },
+                        ])
+                        .spacing(20)
+                        .into(),
+                        Space::with_width(Length::Fill).into(),
+                    ])
+                    .padding(30)
+                    .into()
+                }
                 TabState::Manual => {
                     let content =
                         Column::new().push(tab_bar.view().map(move |msg| Message::TabBar(msg)));
@@ -1144,36 +1969,393 @@ impl<'a> Application for Bathtub {
 }
 
 impl LoadState {
-    fn new(nodes: Nodes, node_map: HashMap<String, usize>, actions: Actions) -> LoadState {
+    fn new(
+        nodes: Nodes,
+        node_map: HashMap<String, usize>,
+        actions: Actions,
+        report: Report,
+        read_only: Vec<ConfigError>,
+    ) -> LoadState {
         LoadState {
             nodes,
             node_map,
             actions,
+            report,
+            read_only,
         }
     }
 
     // This is just a placeholder. Will eventually read data from server
-    async fn load() -> Result<LoadState, LoadError> {
+    // read_only opens with whatever could be loaded instead of returning the errors
+    async fn load(read_only: bool) -> Result<LoadState, LoadError> {
+        let mut errors = Vec::new();
         // try to read file 3 times before returning error
-        let mut nodes = Nodes { node: Vec::new() };
+        let mut nodes = Nodes::new();
         for i in 0..3 {
-            match nodes::gen_nodes() {
+            match nodes::get_baths_config() {
                 Ok(n) => {
                     nodes = n;
                     break;
                 }
-                Err(_err) if i == 2 => return Err(LoadError::Nodes),
+                Err(err) if i == 2 => errors.push(err),
                 Err(_) => thread::sleep(Duration::from_millis(50)),
             };
         }
+        let report = validate(&nodes);
+        // only a missing HOME stops bathtub from starting, everything else is shown in the ui
+        if errors.is_empty() && !nodes.node.iter().any(|n| n.name == "HOME") {
+            errors.extend(report.errors.iter().map(|e| ConfigError::new(BATHS, e)));
+        }
+        nodes.limits = match Limits::load() {
+            Ok(limits) => limits,
+            Err(err) => {
+                errors.push(err);
+                Limits::default()
+            }
+        };
+        let actions = match actions::gen_actions() {
+            Ok(actions) => actions,
+            Err(err) => {
+                errors.push(err);
+                Actions { action: Vec::new() }
+            }
+        };
+        if !errors.is_empty() && !read_only {
+            return Err(LoadError::Config(errors));
+        }
+        if errors.is_empty() {
+            for file in &[BATHS, ACTIONS, LIMITS] {
+                config::save_backup(file);
+            }
+        }
+        if !nodes.node.iter().any(|n| n.name == "HOME") {
+            nodes
+                .node
+                .extend(toml::from_str::<Nodes>(config::DEFAULT_BATHS).unwrap().node);
+        }
+        nodes.add_height_nodes();
         Ok(LoadState::new(
             nodes.clone(),
             nodes::get_nodemap(&nodes),
-            actions::gen_actions(),
+            actions,
+            report,
+            errors,
         ))
     }
 }
 
+fn picker_view(picker: &mut ProfilePicker) -> Element<'_, Message> {
+    let mut content = Column::new()
+        .spacing(10)
+        .push(Text::new("Choose a line.").font(CQ_MONO).size(50));
+    if let Some(message) = &picker.message {
+        content = content.push(
+            Container::new(Text::new(message))
+                .style(Theme::Red)
+                .padding(10)
+                .width(Length::Fill),
+        );
+    }
+    for (i, (name, btn)) in picker
+        .profiles
+        .iter()
+        .zip(picker.profile_btns.iter_mut())
+        .enumerate()
+    {
+        content = content.push(
+            Button::new(
+                btn,
+                Text::new(name)
+                    .size(30)
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .font(CQ_MONO),
+            )
+            .style(Theme::Blue)
+            .padding(10)
+            .width(Length::Fill)
+            .on_press(Message::ProfileChosen(i)),
+        );
+    }
+    content = content.push(Space::with_height(Length::Units(30))).push(
+        Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                TextInput::new(
+                    &mut picker.new_name_state,
+                    "New profile name",
+                    &picker.new_name,
+                    Message::ProfileNameChanged,
+                )
+                .style(Theme::Blue)
+                .font(CQ_MONO)
+                .padding(10),
+            )
+            .push(
+                Button::new(
+                    &mut picker.create_btn,
+                    Text::new("Create")
+                        .horizontal_alignment(HorizontalAlignment::Center)
+                        .font(CQ_MONO),
+                )
+                .style(Theme::Yellow)
+                .padding(10)
+                .on_press(Message::CreateProfile),
+            ),
+    );
+    Row::with_children(vec![
+        Space::with_width(Length::Fill).into(),
+        content.width(Length::Units(600)).into(),
+        Space::with_width(Length::Fill).into(),
+    ])
+    .padding(30)
+    .into()
+}
+
+fn failure_view(failure: &mut Failure) -> Element<'_, Message> {
+    let mut content = Column::new().spacing(10).push(
+        Text::new("Bathtub cannot start.")
+            .font(CQ_MONO)
+            .size(50),
+    );
+    for error in &failure.errors {
+        content = content.push(Text::new(error.to_string()).font(CQ_MONO).size(25));
+    }
+    for ((i, file), (restore_btn, default_btn)) in failure.files.iter().enumerate().zip(
+        failure
+            .restore_btns
+            .iter_mut()
+            .zip(failure.default_btns.iter_mut()),
+    ) {
+        let restore = Button::new(
+            restore_btn,
+            Text::new("Restore Last Good Backup")
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .font(CQ_MONO),
+        )
+        .style(Theme::Blue)
+        .padding(10);
+        content = content.push(
+            Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(Text::new(file).size(25).width(Length::Units(300)))
+                .push(match config::has_backup(file) {
+                    true => restore.on_press(Message::RestoreBackup(i)),
+                    false => restore,
+                })
+                .push(
+                    Button::new(
+                        default_btn,
+                        Text::new("Create Default")
+                            .horizontal_alignment(HorizontalAlignment::Center)
+                            .font(CQ_MONO),
+                    )
+                    .style(Theme::Yellow)
+                    .padding(10)
+                    .on_press(Message::CreateDefault(i)),
+                ),
+        );
+    }
+    content = content
+        .push(
+            Text::new(
+                "Either keeps the broken file as <file>.broken. Or fix it and restart Bathtub.",
+            )
+            .size(20),
+        )
+        .push(
+            Button::new(
+                &mut failure.read_only_btn,
+                Text::new("Open Read-Only")
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .font(CQ_MONO),
+            )
+            .style(Theme::Blue)
+            .padding(10)
+            .on_press(Message::OpenReadOnly),
+        );
+    Row::with_children(vec![
+        Space::with_width(Length::Fill).into(),
+        content.into(),
+        Space::with_width(Length::Fill).into(),
+    ])
+    .padding(30)
+    .into()
+}
+
+fn fault_view(
+    dialog: &mut FaultDialog,
+    status: Option<status::Status>,
+    busy: bool,
+) -> Element<'_, Message> {
+    let remedy = dialog.fault.remedy();
+    let advice = match remedy {
+        Remedy::Unlock => "GRBL kept its position. Unlock it to carry on from where the gantry is.",
+        Remedy::Home => "GRBL lost its position. The head will be raised to hover height, then homed.",
+        Remedy::CheckLimits => {
+            "Check the limit switches below are clear and wired correctly, then home. Homing is \
+             unavailable while a limit switch is triggered."
+        }
+        Remedy::Acknowledge => {
+            "The recipe was stopped and what was queued after the command was dropped. Check the \
+             action or the limits before running again."
+        }
+    };
+    let pins = status.as_ref().map(|s| s.pins.clone()).unwrap_or_default();
+    let limit_triggered = pins.x_limit || pins.y_limit || pins.z_limit;
+    let mut content = Column::new()
+        .spacing(20)
+        .push(Text::new(dialog.fault.title()).font(CQ_MONO).size(50))
+        .push(Text::new(dialog.fault.description()).size(25))
+        .push(Text::new(advice).size(25))
+        .push(
+            Text::new(match &status {
+                Some(s) => format!(
+                    "{} at ({:.3}, {:.3}, {:.3})\nTriggered: {}",
+                    s.state_text(),
+                    s.x,
+                    s.y,
+                    s.z,
+                    match pins.triggered() {
+                        triggered if triggered.is_empty() => "nothing".to_string(),
+                        triggered => triggered.join(", "),
+                    }
+                ),
+                None => "Waiting for GRBL's status . . .".to_string(),
+            })
+            .font(CQ_MONO)
+            .size(25),
+        );
+    if busy {
+        return Row::with_children(vec![
+            Space::with_width(Length::Fill).into(),
+            content
+                .push(Text::new("Working . . .").font(CQ_MONO).size(30))
+                .into(),
+            Space::with_width(Length::Fill).into(),
+        ])
+        .padding(30)
+        .into();
+    }
+    // the suggested next step stands out
+    let style = |suggested: bool| match suggested {
+        true => Theme::Yellow,
+        false => Theme::Blue,
+    };
+    let mut buttons = Row::new().spacing(20);
+    if remedy == Remedy::Acknowledge {
+        buttons = buttons.push(
+            Button::new(
+                &mut dialog.continue_btn,
+                Text::new("Continue")
+                    .size(30)
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .font(CQ_MONO),
+            )
+            .style(Theme::Yellow)
+            .on_press(Message::FaultContinue)
+            .padding(10)
+            .width(Length::Units(300)),
+        );
+    } else {
+        buttons = buttons.push(
+            Button::new(
+                &mut dialog.unlock_btn,
+                Text::new("Unlock ($X)")
+                    .size(30)
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .font(CQ_MONO),
+            )
+            .style(style(remedy == Remedy::Unlock))
+            .on_press(Message::FaultUnlock)
+            .padding(10)
+            .width(Length::Units(300)),
+        );
+        let home = Button::new(
+            &mut dialog.home_btn,
+            Text::new("Home ($H)")
+                .size(30)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .font(CQ_MONO),
+        )
+        .style(style(remedy == Remedy::Home || remedy == Remedy::CheckLimits))
+        .padding(10)
+        .width(Length::Units(300));
+        buttons = buttons.push(match limit_triggered {
+            true => home,
+            false => home.on_press(Message::FaultHome),
+        });
+    }
+    content = content.push(buttons);
+    Row::with_children(vec![
+        Space::with_width(Length::Fill).into(),
+        content.into(),
+        Space::with_width(Length::Fill).into(),
+    ])
+    .padding(30)
+    .into()
+}
+
+fn read_only_banner<'a>(errors: &Vec<ConfigError>) -> Element<'a, Message> {
+    let mut files: Vec<&str> = errors.iter().map(|e| &e.file[..]).collect();
+    files.dedup();
+    Container::new(Text::new(format!(
+        "Read-only: {} could not be loaded. Nothing can be moved or saved until it is fixed and Bathtub is restarted.",
+        files.join(", ")
+    )))
+    .style(Theme::Red)
+    .padding(10)
+    .width(Length::Fill)
+    .into()
+}
+
+fn node_errors_banner<'a>(errors: &Vec<String>) -> Element<'a, Message> {
+    Container::new(Text::new(format!(
+        "config/baths.toml has errors, fix them in Advanced > Nodes before moving anything.\n{}",
+        errors.join("\n")
+    )))
+    .style(Theme::Red)
+    .padding(10)
+    .width(Length::Fill)
+    .into()
+}
+
+// messages that move the gantry along the nodes, ignored while baths.toml has errors
+fn moves(message: &Message) -> bool {
+    match message {
+        Message::Manual(ManualMessage::ButtonPressed(_)) | Message::Run(RunMessage::Run(_)) => {
+            true
+        }
+        _ => false,
+    }
+}
+
+// messages that move the gantry or write a file, ignored in read-only
+fn writes(message: &Message) -> bool {
+    match message {
+        Message::Manual(_)
+        | Message::Run(_)
+        | Message::Recover
+        | Message::FaultUnlock
+        | Message::FaultHome
+        | Message::FaultContinue => true,
+        Message::Build(BuildMessage::SaveMessage(SaveBarMessage::Save))
+        | Message::Build(BuildMessage::DeleteConfirmed)
+        | Message::Build(BuildMessage::Rollback(_)) => true,
+        Message::Advanced(AdvancedMessage::GrblTab(GrblMessage::SaveMessage(
+            SaveBarMessage::Save,
+        )))
+        | Message::Advanced(AdvancedMessage::NodesTab(NodeTabMessage::SaveMessage(
+            SaveBarMessage::Save,
+        )))
+        | Message::Advanced(AdvancedMessage::ActionsTab(ActionTabMessage::SaveMessage(
+            SaveBarMessage::Save,
+        ))) => true,
+        _ => false,
+    }
+}
+
 fn loading_message<'a>(msg: &str) -> Element<'a, Message> {
     Container::new(
         Text::new(msg)
@@ -1195,7 +2377,7 @@ fn break_and_hold(recipe_state: Arc<(Mutex<RecipeState>, Condvar)>) -> bool {
     let mut rs = recipe_state.lock().unwrap();
     while !stop {
         match *rs {
-            RecipeState::Stopped => stop = true,
+            RecipeState::Stopped | RecipeState::Fault => stop = true,
             RecipeState::RecipePaused => rs = cvar.wait(rs).unwrap(),
             RecipeState::RequireInput => rs = cvar.wait(rs).unwrap(),
             _ => break,
@@ -1212,7 +2394,7 @@ fn break_and_hold_timer(recipe_state: Arc<(Mutex<RecipeState>, Condvar)>) -> (bo
     let timer = Instant::now();
     while !stop {
         match *rs {
-            RecipeState::Stopped => stop = true,
+            RecipeState::Stopped | RecipeState::Fault => stop = true,
             RecipeState::RecipePaused => {
                 did_pause = true;
                 rs = cvar.wait(rs).unwrap()
@@ -1231,12 +2413,92 @@ fn break_and_hold_timer(recipe_state: Arc<(Mutex<RecipeState>, Condvar)>) -> (bo
     )
 }
 
-fn set_pause_node(node_tracker: Arc<Mutex<NodeTracker>>, grbl: Grbl) {
+// a refused command halts the recipe, what was still queued behind it is dropped
+fn log_response(
+    logger: &Logger,
+    grbl: &Grbl,
+    recipe_state: &Arc<(Mutex<RecipeState>, Condvar)>,
+    step_num: &String,
+    response: Cmd,
+) {
+    let time = response.response_time.unwrap();
+    let result = response.result.unwrap();
+    logger
+        .send_line(format!(
+            "{} => Step {}) G-code '{}' responded '{}'",
+            time.to_rfc2822(),
+            step_num,
+            response.command,
+            faults::explain(&result),
+        ))
+        .unwrap();
+    if let Some(code) = faults::parse_error(&result) {
+        grbl.drop_queued();
+        grbl.set_fault(Fault::Error {
+            code,
+            command: response.command.clone(),
+        });
+        let (recipe_state, cvar) = &**recipe_state;
+        *recipe_state.lock().unwrap() = RecipeState::Fault;
+        cvar.notify_all();
+    }
+    logger
+        .send_record(Record::at(
+            time,
+            Event::GrblResponse {
+                step: step_num.clone(),
+                gcode: response.command,
+                response: result,
+            },
+        ))
+        .unwrap();
+}
+
+fn halted(rs: RecipeState) -> bool {
+    match rs {
+        RecipeState::Stopped | RecipeState::Fault => true,
+        _ => false,
+    }
+}
+
+fn stopped_line(recipe_state: &Arc<(Mutex<RecipeState>, Condvar)>) -> String {
+    let by = match *recipe_state.0.lock().unwrap() {
+        RecipeState::Fault => "GRBL Fault",
+        _ => "User",
+    };
+    format!("{} => Stopped By {}", Local::now().to_rfc2822(), by)
+}
+
+// a move that cannot be made safely stops the recipe before anything of it reaches grbl
+fn refuse_move(
+    logger: &Logger,
+    grbl: &Grbl,
+    progress: &Option<Progress>,
+    step_num: &String,
+    err: String,
+) {
+    grbl.halt_blocking();
+    logger
+        .send_line(format!(
+            "{} => Step {}) Not sent, {}",
+            Local::now().to_rfc2822(),
+            step_num,
+            err
+        ))
+        .unwrap();
+    // manual moves have no progress, and must not clear a recipe's that is waiting to resume
+    if progress.is_some() {
+        Progress::clear();
+    }
+}
+
+// without a status report the gantry is taken to still be at the node it last reached
+fn set_pause_node(node_tracker: Arc<Mutex<NodeTracker>>, status: Option<status::Status>) {
     let mut nt = node_tracker.lock().unwrap();
-    if nt.current.name != "paused_node" {
+    if nt.current.name != "paused_node" && status.is_some() {
         let cn_pos = nt.next.iter().position(|n| nt.current.name == n.name);
         let neighbor2 = if let Some(num) = cn_pos {
-            if nt.next.len() > num
+            if num + 1 < nt.next.len()
                 && nt
                     .current
                     .neighbors
@@ -1258,7 +2520,7 @@ fn set_pause_node(node_tracker: Arc<Mutex<NodeTracker>>, grbl: Grbl) {
         } else {
             None
         };
-        let s = grbl.get_status().unwrap();
+        let s = status.unwrap();
 
         nt.current = Node {
             name: "paused_node".to_string(),
@@ -1271,6 +2533,10 @@ fn set_pause_node(node_tracker: Arc<Mutex<NodeTracker>>, grbl: Grbl) {
             } else {
                 vec![nt.current.name.clone()]
             },
+            feed: None,
+            edges: None,
+            hover: None,
+            approach: None,
         };
         nt.next.clear();
     } else {
@@ -1282,3 +2548,123 @@ const CQ_MONO: Font = Font::External {
     name: "CQ_MONO",
     bytes: include_bytes!("../fonts/CQ_MONO.otf"),
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actions::Action;
+    use simulator::Simulator;
+
+    const NODES: &str = "
+        [[node]]
+        name = 'HOME'
+        x = -1.0
+        y = -1.0
+        z = -1.0
+        hide = true
+        neighbors = ['A']
+
+        [[node]]
+        name = 'A'
+        x = -4.0
+        y = -1.0
+        z = -3.0
+        hide = false
+        neighbors = ['HOME']";
+
+    fn step(step_num: usize, destination: &str, action: &str, secs: u64, hover: bool) -> Step {
+        Step {
+            step_num: step_num.to_string(),
+            selected_destination: destination.to_string(),
+            selected_action: action.to_string(),
+            secs_value: secs.to_string(),
+            mins_value: "0".to_string(),
+            hours_value: "0".to_string(),
+            hover,
+            wait: false,
+        }
+    }
+
+    // runs steps on the simulator starting from HOME, with what was logged and the steps reported
+    fn run(steps: Vec<Step>) -> (Result<RunEnd, ()>, Vec<String>, Vec<Option<usize>>) {
+        let grbl = grbl::with_transport(|| {
+            Ok((Box::new(Simulator::new(Duration::from_millis(20))), "simulator".to_string()))
+        });
+        let (logger, lines) = Logger::capture();
+        let mut nodes: Nodes = toml::from_str(NODES).unwrap();
+        nodes.add_height_nodes();
+        let node_map = nodes::get_nodemap(&nodes);
+        let actions = Actions {
+            action: vec![Action {
+                name: "Rest".to_string(),
+                commands: vec!["WAIT".to_string()],
+            }],
+        };
+        let recipe_state = Arc::new((Mutex::new(RecipeState::RecipeRunning), Condvar::new()));
+        let node_tracker = Arc::new(Mutex::new(NodeTracker {
+            prev: None,
+            current: nodes.node[node_map["HOME"]].clone(),
+            next: Vec::new(),
+        }));
+        let (step_tx, step_rx) = mpsc::channel();
+        let result = State::run_recipe_blocking(
+            grbl,
+            logger,
+            Arc::clone(&recipe_state),
+            node_tracker,
+            steps,
+            node_map,
+            nodes,
+            actions,
+            step_tx,
+            None,
+        );
+        // ends the thread tracking which node the gantry is at
+        *recipe_state.0.lock().unwrap() = RecipeState::Stopped;
+        let lines = lines
+            .try_iter()
+            .filter(|line| !line.ends_with("\n\rjson"))
+            .collect();
+        (result, lines, step_rx.try_iter().collect())
+    }
+
+    fn logged(lines: &Vec<String>, text: &str) -> bool {
+        lines.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn runs_recipe_to_the_end() {
+        let (result, lines, steps) = run(vec![
+            step(1, "A", "Rest", 1, false),
+            step(2, "A", "Rest", 0, true),
+        ]);
+        assert_eq!(result, Ok(RunEnd::Completed));
+        assert_eq!(steps, vec![Some(0), Some(1)]);
+        assert!(logged(&lines, "Step 1) on path A_hover > A"));
+        assert!(logged(&lines, "Arrived @A"));
+        assert!(logged(&lines, "Step 1) finished Rest"));
+        assert!(logged(&lines, "Arrived @A_hover"));
+        assert!(logged(&lines, "Step 2) finished Rest"));
+    }
+
+    #[test]
+    fn refuses_missing_destination() {
+        let (result, lines, _) = run(vec![step(1, "Missing", "Rest", 0, false)]);
+        assert_eq!(result, Err(()));
+        assert!(logged(
+            &lines,
+            "Step 1) Not sent, 'Missing' is not in config/baths.toml"
+        ));
+    }
+
+    #[test]
+    fn refuses_missing_action() {
+        let (result, lines, _) = run(vec![step(1, "A", "Missing", 0, false)]);
+        assert_eq!(result, Err(()));
+        assert!(logged(
+            &lines,
+            "Step 1) Not sent, 'Missing' is not in config/actions.toml"
+        ));
+        assert!(!logged(&lines, "finished Missing"));
+    }
+}
@@ -1,20 +1,18 @@
-use super::grbl::{Command as Cmd, Grbl};
+use super::grbl::{Command as Cmd, Grbl, GrblError, Realtime, Response};
 use super::logger::Logger;
-use super::nodes::{Node, Nodes};
+use super::nodes::{is_generated, Node, Nodes};
+use super::overrides::Overrides;
 use super::paths::gen_node_paths;
 use super::style::style::Theme;
 use crate::{NodeTracker, RecipeState, CQ_MONO};
 use chrono::prelude::*;
 use iced::{
-    button, scrollable, text_input, tooltip, Button, Checkbox, Column, Command, Container, Element,
+    button, scrollable, text_input, tooltip, Align, Button, Checkbox, Column, Command, Container, Element,
     HorizontalAlignment, Length, Row, Scrollable, Space, Text, TextInput, Tooltip,
 };
-use regex::Regex;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::{Arc, Condvar, Mutex};
-use std::thread;
-use std::time::Duration;
 
 pub struct Manual {
     pub scroll: scrollable::State,
@@ -22,7 +20,6 @@ pub struct Manual {
     pub stop_btn: button::State,
     pub status: String,
     pub hover: bool,
-    pub status_regex: Regex,
     grid_btn: button::State,
     terminal_btn: button::State,
     state: ManualState,
@@ -36,6 +33,10 @@ pub struct Manual {
     recipe_state: Arc<(Mutex<RecipeState>, Condvar)>,
     node_tracker: Arc<Mutex<NodeTracker>>,
     pub unsaved_in_bathtub: bool,
+    overrides: Overrides,
+    // (feed, rapid) overrides grbl last reported, set every tick in main.rs
+    pub overrides_percent: Option<(u16, u16)>,
+    reset_btn: button::State,
 }
 
 #[derive(Debug, Clone)]
@@ -53,7 +54,10 @@ pub enum ManualMessage {
     GridTab,
     TerminalInputChanged(String),
     TerminalInputSubmitted,
-    ThankYou(Option<Cmd>),
+    // command typed in the terminal and grbl's answer to it
+    ThankYou(String, Result<Response, GrblError>),
+    Override(Realtime),
+    SoftReset,
 }
 
 impl Manual {
@@ -73,10 +77,6 @@ impl Manual {
             hover: true,
             ref_nodes,
             homing_required,
-            status_regex: Regex::new(
-                r"(?P<status>[A-Za-z]+).{6}(?P<X>[-\d.]+),(?P<Y>[-\d.]+),(?P<Z>[-\d.]+)",
-            )
-            .unwrap(),
             grid_btn: button::State::new(),
             terminal_btn: button::State::new(),
             state: ManualState::Grid,
@@ -88,6 +88,9 @@ impl Manual {
             recipe_state,
             node_tracker,
             unsaved_in_bathtub: false,
+            overrides: Overrides::new(),
+            overrides_percent: None,
+            reset_btn: button::State::new(),
         }
     }
 
@@ -97,18 +100,35 @@ impl Manual {
             ManualMessage::TerminalTab => self.state = ManualState::Terminal,
             ManualMessage::GridTab => self.state = ManualState::Grid,
             ManualMessage::TerminalInputChanged(val) => self.terminal_input_value = val,
-            ManualMessage::ThankYou(cmd) => match cmd {
-                Some(cmd) => self.terminal_responses.insert(
+            ManualMessage::ThankYou(command, answer) => self.terminal_responses.insert(
+                0,
+                match answer {
+                    Ok(mut response) => {
+                        response.lines.push("ok".to_string());
+                        format!(
+                            "{}; '{}' => {}",
+                            response.time.to_rfc2822(),
+                            command,
+                            response.lines.join("\n")
+                        )
+                    }
+                    Err(err) => format!("{}; '{}' => {}", Local::now().to_rfc2822(), command, err),
+                },
+            ),
+            ManualMessage::Override(command) => self.grbl.realtime(command),
+            ManualMessage::SoftReset => {
+                *self.homing_required.borrow_mut() = true;
+                self.logger.set_log_file(format!(
+                    "{}; Manual (Terminal) - Soft Reset",
+                    Local::now().to_rfc2822()
+                ));
+                self.logger.send_line(String::new()).unwrap();
+                self.grbl.realtime(Realtime::SoftReset);
+                self.terminal_responses.insert(
                     0,
-                    format!(
-                        "{}; '{}' => {}",
-                        cmd.response_time.unwrap().to_rfc2822(),
-                        cmd.command,
-                        cmd.result.unwrap()
-                    ),
-                ),
-                None => {}
-            },
+                    format!("{}; => Soft reset sent to GRBL", Local::now().to_rfc2822()),
+                );
+            }
             ManualMessage::TerminalInputSubmitted => {
                 *self.homing_required.borrow_mut() = true;
                 let val = self.terminal_input_value.replace("\n", "").to_uppercase();
@@ -151,10 +171,9 @@ impl Manual {
                         ))
                         .unwrap();
 
-                    self.grbl.push_command(Cmd::new(val));
                     return Command::perform(
-                        command_please(self.grbl.clone()),
-                        ManualMessage::ThankYou,
+                        terminal_request(self.grbl.clone(), val.clone()),
+                        move |answer| ManualMessage::ThankYou(val.clone(), answer),
                     );
                 }
             }
@@ -267,6 +286,11 @@ impl Manual {
                                                                     "{}_hover",
                                                                     ref_nodes.node[nt.0].name
                                                                 )
+                                                            || node_tracker.current.name
+                                                                == format!(
+                                                                    "{}_approach",
+                                                                    ref_nodes.node[nt.0].name
+                                                                )
                                                         {
                                                             Theme::BlueDisabledBright
                                                         } else {
@@ -348,6 +372,11 @@ impl Manual {
                         }),
                     );
 
+                let overrides = self
+                    .overrides
+                    .view(self.overrides_percent)
+                    .map(ManualMessage::Override);
+
                 let content = if paths_r_safe {
                     Column::new()
                         .max_width(800)
@@ -356,6 +385,7 @@ impl Manual {
                         .push(tab_btns)
                         .push(button_grid)
                         .push(modifiers)
+                        .push(overrides)
                 } else {
                     Column::new()
                         .max_width(800)
@@ -364,6 +394,7 @@ impl Manual {
                         .push(tab_btns)
                         .push(paths_warning)
                         .push(modifiers)
+                        .push(overrides)
                 };
 
                 Scrollable::new(&mut self.scroll)
@@ -384,13 +415,29 @@ impl Manual {
                 .on_submit(ManualMessage::TerminalInputSubmitted)
                 .padding(10);
 
+                let reset = Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(terminal_input)
+                    .push(
+                        Button::new(
+                            &mut self.reset_btn,
+                            Text::new("Soft Reset")
+                                .horizontal_alignment(HorizontalAlignment::Center)
+                                .font(CQ_MONO),
+                        )
+                        .style(Theme::Red)
+                        .padding(10)
+                        .on_press(ManualMessage::SoftReset),
+                    );
+
                 let content = Column::new()
                     .max_width(800)
                     .spacing(20)
                     .push(title)
                     .push(tab_btns)
                     .push(warning)
-                    .push(terminal_input)
+                    .push(reset)
                     .push(
                         self.terminal_responses
                             .iter()
@@ -411,9 +458,8 @@ impl Manual {
     }
 }
 
-async fn command_please(grbl: Grbl) -> Option<Cmd> {
-    thread::sleep(Duration::from_secs(1));
-    grbl.pop_command()
+async fn terminal_request(grbl: Grbl, command: String) -> Result<Response, GrblError> {
+    grbl.request(Cmd::new(command)).await
 }
 
 // given (x, y) coord get name or none
@@ -423,7 +469,7 @@ fn grid(rn: &Nodes) -> Vec<Vec<Option<usize>>> {
         v.push(n);
         v
     });
-    nodes.retain(|n| !n.1.name.contains("_hover") && !n.1.hide);
+    nodes.retain(|n| !is_generated(&n.1.name) && !n.1.hide);
     nodes.sort_by(|a, b| (b.1.y).total_cmp(&a.1.y));
     let mut test_value = nodes[0].1.y;
     let mut push_vec: usize = 0;
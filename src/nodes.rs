@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use toml;
+
+use super::config::{self, ConfigError, BATHS};
+use super::limits::Limits;
+use super::recovery::NODE_TOLERANCE;
+
+// height of the generated <node>_hover nodes when the node does not set its own
+pub const HOVER_Z: f32 = -1.0;
 
 #[derive(Clone, Debug)]
 pub struct Actions {
@@ -18,6 +23,22 @@ pub struct Node {
     pub z: f32,
     pub hide: bool,
     pub neighbors: Vec<String>,
+    // feed rate when approaching this node
+    pub feed: Option<f32>,
+    pub edges: Option<Vec<Edge>>,
+    // height of <node>_hover, the head is clear of the bath at or above it
+    pub hover: Option<f32>,
+    // optional level between hover and the bath, a <node>_approach node is generated at it so
+    // the head drops quickly to just above the liquid then enters at the node's feed
+    pub approach: Option<f32>,
+}
+
+// optional settings for travelling from a node to one of its neighbors
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Edge {
+    pub to: String,
+    pub cost: Option<f32>,
+    pub feed: Option<f32>,
 }
 
 impl std::fmt::Display for Node {
@@ -28,12 +49,21 @@ impl std::fmt::Display for Node {
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Nodes {
+    // default feed rate for jogging between nodes
+    pub feed: Option<f32>,
     pub node: Vec<Node>,
+    // soft limits and keep-out zones from config/limits.toml
+    #[serde(skip)]
+    pub limits: Limits,
 }
 
 impl Nodes {
     pub fn new() -> Nodes {
-        Nodes { node: vec![] }
+        Nodes {
+            feed: None,
+            node: vec![],
+            limits: Limits::default(),
+        }
     }
     pub fn add_height_nodes(&mut self) {
         let hidden_nodes = self.node.iter().fold(Vec::new(), |mut v, n| {
@@ -45,6 +75,11 @@ impl Nodes {
         let mut new_nodes: Vec<Node> = vec![];
         //let bath_iter = baths.bath.into_iter();
         for node in &mut self.node {
+            // the hover node goes down to the approach node if there is one, then the bath
+            let below = match node.approach {
+                Some(_) if !node.hide => format!("{}_approach", node.name),
+                _ => node.name.clone(),
+            };
             // create node for hovering
             if !node.hide {
                 // if hidden then we do not want to auto-generate realted nodes
@@ -52,12 +87,12 @@ impl Nodes {
                     name: format!("{}_hover", node.name),
                     x: node.x,
                     y: node.y,
-                    z: -1.0,
+                    z: node.hover.unwrap_or(HOVER_Z),
                     hide: false,
                     neighbors: node
                         .neighbors
                         .iter()
-                        .fold(vec![node.name.clone()], |mut v, n| {
+                        .fold(vec![below.clone()], |mut v, n| {
                             if hidden_nodes.iter().any(|hn| hn == n) {
                                 v.push(n.clone());
                             } else {
@@ -65,7 +100,39 @@ impl Nodes {
                             }
                             v
                         }),
+                    feed: None,
+                    // edges are set between the 2d nodes, so they apply when hovering
+                    edges: node.edges.as_ref().map(|edges| {
+                        edges
+                            .iter()
+                            .map(|edge| Edge {
+                                to: if hidden_nodes.iter().any(|hn| *hn == edge.to) {
+                                    edge.to.clone()
+                                } else {
+                                    format!("{}_hover", edge.to)
+                                },
+                                ..edge.clone()
+                            })
+                            .collect()
+                    }),
+                    hover: None,
+                    approach: None,
                 });
+                if let Some(approach) = node.approach {
+                    new_nodes.push(Node {
+                        name: below.clone(),
+                        x: node.x,
+                        y: node.y,
+                        z: approach,
+                        hide: false,
+                        neighbors: vec![format!("{}_hover", node.name), node.name.clone()],
+                        // down to here at the default feed, the node's feed is for entering the bath
+                        feed: None,
+                        edges: None,
+                        hover: None,
+                        approach: None,
+                    });
+                }
             }
 
             // create node for head in bath
@@ -86,18 +153,58 @@ impl Nodes {
                             n
                         })
                         .collect()
+                } else if node.approach.is_some() {
+                    vec![below]
                 } else {
                     vec![format!("{}_hover", node.name)]
                 },
+                feed: node.feed,
+                edges: if node.hide {
+                    node.edges.as_ref().map(|edges| {
+                        edges
+                            .iter()
+                            .map(|edge| {
+                                let mut edge = edge.clone();
+                                if !hidden_nodes.iter().any(|hn| *hn == edge.to) {
+                                    edge.to.push_str("_hover");
+                                }
+                                edge
+                            })
+                            .collect()
+                    })
+                } else {
+                    None
+                },
+                // kept so the nodes tab can show and save them
+                hover: node.hover,
+                approach: node.approach,
             })
         }
         self.node = new_nodes;
     }
+
+    // height of the hover node above (x, y), or the highest hover node when no bath is there
+    pub fn hover_z(&self, x: f32, y: f32) -> f32 {
+        let hovers = self.node.iter().filter(|n| n.name.ends_with("_hover"));
+        match hovers
+            .clone()
+            .find(|n| (n.x - x).abs() < NODE_TOLERANCE && (n.y - y).abs() < NODE_TOLERANCE)
+        {
+            Some(n) => n.z,
+            None => hovers.map(|n| n.z).fold(HOVER_Z, f32::max),
+        }
+    }
 }
 
-pub fn gen_nodes() -> Result<Nodes, ()> {
+// nodes made by add_height_nodes rather than read from baths.toml
+pub fn is_generated(name: &str) -> bool {
+    name.ends_with("_hover") || name.ends_with("_approach")
+}
+
+pub fn gen_nodes() -> Result<Nodes, ConfigError> {
     let mut nodes = get_baths_config()?;
     nodes.add_height_nodes();
+    nodes.limits = Limits::load()?;
     Ok(nodes)
 }
 
@@ -112,9 +219,6 @@ pub fn get_nodemap(nodes: &Nodes) -> HashMap<String, usize> {
         })
 }
 
-fn get_baths_config() -> Result<Nodes, ()> {
-    match &fs::read_to_string("config/baths.toml") {
-        Ok(file) => Ok(toml::from_str::<Nodes>(file).unwrap()),
-        Err(_) => Err(()),
-    }
+pub fn get_baths_config() -> Result<Nodes, ConfigError> {
+    config::read(BATHS)
 }
@@ -0,0 +1,80 @@
+use super::grbl::Realtime;
+use super::style::style::Theme;
+use crate::CQ_MONO;
+use iced::{button, Align, Button, Column, Element, HorizontalAlignment, Length, Row, Text};
+
+// override buttons, feed for G1 moves between 10% and 200% and rapids for G0 moves.
+// Jogs and homing always run at their own feed
+pub struct Overrides {
+    minus10_btn: button::State,
+    minus1_btn: button::State,
+    reset_btn: button::State,
+    plus1_btn: button::State,
+    plus10_btn: button::State,
+    rapid_quarter_btn: button::State,
+    rapid_half_btn: button::State,
+    rapid_full_btn: button::State,
+}
+
+impl Overrides {
+    pub fn new() -> Self {
+        Overrides {
+            minus10_btn: button::State::new(),
+            minus1_btn: button::State::new(),
+            reset_btn: button::State::new(),
+            plus1_btn: button::State::new(),
+            plus10_btn: button::State::new(),
+            rapid_quarter_btn: button::State::new(),
+            rapid_half_btn: button::State::new(),
+            rapid_full_btn: button::State::new(),
+        }
+    }
+
+    // (feed, rapid) percent grbl last reported, None before the first status
+    pub fn view(&mut self, percent: Option<(u16, u16)>) -> Element<'_, Realtime> {
+        let (feed, rapid) = match percent {
+            Some((feed, rapid)) => (format!("{}%", feed), format!("{}%", rapid)),
+            None => ("--".to_string(), "--".to_string()),
+        };
+        Column::new()
+            .spacing(10)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(label(format!("Feed {}", feed)))
+                    .push(override_btn(&mut self.minus10_btn, "-10", Realtime::FeedMinus10))
+                    .push(override_btn(&mut self.minus1_btn, "-1", Realtime::FeedMinus1))
+                    .push(override_btn(&mut self.reset_btn, "100%", Realtime::FeedReset))
+                    .push(override_btn(&mut self.plus1_btn, "+1", Realtime::FeedPlus1))
+                    .push(override_btn(&mut self.plus10_btn, "+10", Realtime::FeedPlus10)),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(label(format!("Rapid {}", rapid)))
+                    .push(override_btn(&mut self.rapid_quarter_btn, "25%", Realtime::RapidQuarter))
+                    .push(override_btn(&mut self.rapid_half_btn, "50%", Realtime::RapidHalf))
+                    .push(override_btn(&mut self.rapid_full_btn, "100%", Realtime::RapidReset)),
+            )
+            .into()
+    }
+}
+
+fn label(text: String) -> Text {
+    Text::new(text).font(CQ_MONO).size(25).width(Length::Units(180))
+}
+
+fn override_btn<'a>(state: &'a mut button::State, label: &str, command: Realtime) -> Button<'a, Realtime> {
+    Button::new(
+        state,
+        Text::new(label)
+            .horizontal_alignment(HorizontalAlignment::Center)
+            .font(CQ_MONO),
+    )
+    .style(Theme::Blue)
+    .padding(10)
+    .width(Length::Units(70))
+    .on_press(command)
+}
@@ -1,172 +1,351 @@
 use super::nodes::{Node, Nodes};
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
 
-#[derive(Clone, Debug)]
-struct NodeDistance {
-    node: Node,
-    distance: Option<u16>,
-    visited: bool,
+// used when baths.toml does not set a feed rate
+pub const DEFAULT_FEED: f32 = 250.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathError {
+    UnknownNode(String),
+    NoPath { from: String, to: String },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::UnknownNode(name) => write!(f, "'{}' is not a node", name),
+            PathError::NoPath { from, to } => {
+                write!(f, "No safe path from '{}' to '{}'", from, to)
+            }
+        }
+    }
+}
+
+// entry in the priority queue, ordered so the BinaryHeap pops the lowest cost first
+#[derive(Clone, Copy, Debug)]
+struct Visit {
+    cost: f32,
+    index: usize,
+}
+
+impl PartialEq for Visit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Visit {}
+
+impl PartialOrd for Visit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Visit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.index.cmp(&self.index))
+    }
 }
 
-pub fn gen_node_paths(nodes: &Nodes, start: &Node, stop: &Node) -> Result<Nodes, ()> {
+// cost of travelling from one node to its neighbor, the straight line distance unless
+// overridden by the edge in baths.toml
+pub fn edge_cost(from: &Node, to: &Node) -> f32 {
+    match from
+        .edges
+        .as_ref()
+        .and_then(|edges| edges.iter().find(|edge| edge.to == to.name))
+        .and_then(|edge| edge.cost)
+    {
+        Some(cost) => cost.max(0.0),
+        None => distance(from, to),
+    }
+}
+
+// feed rate for jogging from a node to its neighbor, the edge's feed, then the approach feed of
+// the node being moved to, then the default in baths.toml
+pub fn feed_rate(nodes: &Nodes, from: &Node, to: &Node) -> f32 {
+    from.edges
+        .as_ref()
+        .and_then(|edges| edges.iter().find(|edge| edge.to == to.name))
+        .and_then(|edge| edge.feed)
+        .or(to.feed)
+        .or(nodes.feed)
+        .unwrap_or(DEFAULT_FEED)
+}
+
+// node positions are machine coordinates, G90 G53 keeps the jog from depending on grbl's
+// distance mode or work offset
+pub fn jog_gcode(node: &Node, feed: f32) -> String {
+    format!("$J=G90 G53 X{} Y{} Z{} F{}", node.x, node.y, node.z, feed)
+}
+
+// how much further going through lift is than the straight move from one node to the next
+fn detour(from: &Node, lift: &Vec<Node>, to: &Node) -> f32 {
+    let mut travelled = 0.0;
+    let mut at = from;
+    for node in lift.iter().chain(std::iter::once(to)) {
+        travelled += distance(at, node);
+        at = node;
+    }
+    (travelled - distance(from, to)).max(0.0)
+}
+
+pub fn distance(from: &Node, to: &Node) -> f32 {
+    ((to.x - from.x).powi(2) + (to.y - from.y).powi(2) + (to.z - from.z).powi(2)).sqrt()
+}
+
+// Dijkstra's algorithm over the neighbor graph, returns every node after start up to and
+// including stop
+pub fn gen_node_paths(nodes: &Nodes, start: &Node, stop: &Node) -> Result<Nodes, PathError> {
     // return start node if start == stop
     if start.name == stop.name {
         return Ok(Nodes {
+            feed: nodes.feed,
             node: vec![start.clone()],
+            limits: nodes.limits.clone(),
         });
     }
+    // start may not be in nodes (ie. paused_node), so it is added and takes the place of any
+    // node with the same name
+    let mut graph = nodes.node.clone();
+    graph.push(start.clone());
+    let mut node_map = HashMap::new();
+    for (i, n) in graph.iter().enumerate() {
+        node_map.insert(n.name.clone(), i);
+    }
+    let start_index = node_map[&start.name];
+    let stop_index = match node_map.get(&stop.name) {
+        Some(i) => *i,
+        None => return Err(PathError::UnknownNode(stop.name.clone())),
+    };
 
-    // Assign relevant nodes a distance from the start node
-    //println!("from {} to {}", &start.name, &stop.name);
-    //println!("starts nei are {:?}", &start.neighbors);
-    let mut distance_nodes: Vec<NodeDistance> = Vec::new();
-    for node in &nodes.node {
-        distance_nodes.push(NodeDistance {
-            node: node.clone(),
-            distance: None,
-            visited: false,
-        })
-    }
-    distance_nodes.push(NodeDistance {
-        node: start.clone(),
-        distance: Some(0),
-        visited: false,
+    let mut costs: Vec<Option<f32>> = vec![None; graph.len()];
+    let mut previous: Vec<Option<usize>> = vec![None; graph.len()];
+    // waypoints lifting over keep-out zones on the way from previous
+    let mut lifts: Vec<Vec<Node>> = vec![Vec::new(); graph.len()];
+    let mut queue = BinaryHeap::new();
+    costs[start_index] = Some(0.0);
+    queue.push(Visit {
+        cost: 0.0,
+        index: start_index,
     });
-    // Create map for faster node traversal
-    let mut distance_map = HashMap::new();
-    for (i, n) in distance_nodes.iter().enumerate() {
-        //println!("{}", distance_nodes[i].node.name);
-        distance_map.insert(n.node.name.clone(), i);
-    }
-    // Dijkstra's algorithm setup
-    let mut current_index = distance_map[&start.name];
-    let mut neighbor_index: usize;
-    let mut mut_dn: &mut NodeDistance;
-    let mut new_distance: u16;
-    let mut discovered_index: Vec<usize> = vec![];
-    // Dijkstra's algorithm assign distance values
-    loop {
-        //while current node is not end point
-        new_distance = distance_nodes[current_index].distance.unwrap() + 1;
-        for neighbor in distance_nodes[current_index].node.neighbors.clone() {
-            // for each neighbor in current node
-            if !distance_nodes[distance_map[&neighbor]].visited {
-                //only add unvisited nodes
-                discovered_index.push(distance_map[&neighbor]);
-            }
-            neighbor_index = distance_map[&neighbor];
-            mut_dn = &mut distance_nodes[neighbor_index];
-            match mut_dn.distance {
-                // update distance if no current distance, or new distance less than existing
-                Some(d) if d < new_distance => mut_dn.distance = Some(d),
-                _ => mut_dn.distance = Some(new_distance),
-            }
-        }
-        // Set current node as visited
-        mut_dn = &mut distance_nodes[current_index];
-        mut_dn.visited = true;
-        // Change current node to a discovered unvisited node
-        match discovered_index.pop() {
-            Some(i) => current_index = i,
-            _ => break,
-        }
-        if distance_nodes[current_index].node.name == stop.name {
+    while let Some(Visit { cost, index }) = queue.pop() {
+        if index == stop_index {
             break;
-        };
-    }
-    // Filter useless nodes
-    distance_nodes.retain(|n| {
-        if let Some(_) = n.distance {
-            true
-        } else {
-            false
         }
-    });
-    distance_nodes.sort_by(|a, b| b.distance.unwrap().cmp(&a.distance.unwrap()));
-    /*
-    for node in &distance_nodes {
-        println!("{} ({})", node.node.name, node.distance.unwrap());
-    }
-    */
-    let mut remove_to: usize = 0;
-    // removes nodes untill final node is found
-    for i in 0..distance_nodes.len() {
-        if distance_nodes[i].node.name != stop.name {
-            remove_to = i + 1;
-        } else {
-            break;
-        }
-    }
-    for _i in 0..remove_to {
-        distance_nodes.remove(0);
-    }
-    // Remove sequntial nodes that are not neighbors
-    // This is because in the forward direction 2+ nodes can have the same
-    // distance from the same distance from the origin, but the both are not
-    // neighbors to the following node, thus removing all incorrect nodes
-    // that are not filtered from Dijkstra's algorithm
-    let mut i = 0;
-    /*
-    println!("----- distance nodes -----");
-    for node in &distance_nodes {
-        println!("{}", node.node.name);
-    }
-    */
-    if distance_nodes.len() == 0 {
-        return Err(());
-    }
-    loop {
-        let mut no_match: bool = true;
-        while no_match {
-            for neighbor in &distance_nodes[i].node.neighbors {
-                if neighbor == &distance_nodes[i + 1].node.name {
-                    no_match = false;
-                    break;
-                } else {
-                    if i == distance_nodes.len() - 2 {
-                        no_match = false;
-                        break;
-                    }
-                }
-            }
-            if no_match {
-                distance_nodes.remove(i + 1);
+        // skip entries that were already beaten by a cheaper route
+        if let Some(best) = costs[index] {
+            if cost > best {
+                continue;
             }
         }
-        if i == distance_nodes.len() - 2 {
-            break;
-        } else {
-            i += 1;
+        for neighbor in &graph[index].neighbors {
+            let next = match node_map.get(neighbor) {
+                Some(i) => *i,
+                None => continue,
+            };
+            // legs that cannot be lifted over are not travelled at all
+            let lift = match nodes.limits.waypoints(nodes, &graph[index], &graph[next]) {
+                Ok(lift) => lift,
+                Err(_) => continue,
+            };
+            let next_cost = cost
+                + edge_cost(&graph[index], &graph[next])
+                + detour(&graph[index], &lift, &graph[next]);
+            if costs[next].map_or(true, |c| next_cost < c) {
+                costs[next] = Some(next_cost);
+                previous[next] = Some(index);
+                lifts[next] = lift;
+                queue.push(Visit {
+                    cost: next_cost,
+                    index: next,
+                });
+            }
         }
     }
-    // filter nodes that are neighbors to start and finish, but not shortest path
-    for i in 1..distance_nodes.len() - 1 {
-        if distance_nodes[i].distance == distance_nodes[i - 1].distance {
-            distance_nodes.remove(i);
-        }
+
+    if costs[stop_index].is_none() {
+        return Err(PathError::NoPath {
+            from: start.name.clone(),
+            to: stop.name.clone(),
+        });
     }
-    // main.rs does not import nor need NodeDistance, convert back to Node
-    let mut path_nodes: Nodes = Nodes::new();
-    for node in distance_nodes {
-        path_nodes.node.push(node.node);
+    let mut path_nodes = Nodes::new();
+    path_nodes.feed = nodes.feed;
+    let mut current = stop_index;
+    while current != start_index {
+        path_nodes.node.push(graph[current].clone());
+        path_nodes
+            .node
+            .extend(std::mem::take(&mut lifts[current]).into_iter().rev());
+        current = previous[current].unwrap();
     }
     path_nodes.node.reverse();
-    path_nodes.node.remove(0);
-    /*
-    println!("----- final -----");
-    for node in &path_nodes.node {
-        println!("{}", &node.name);
-    }
-    */
     Ok(path_nodes)
 }
 
-/*
-pub fn gen_gcode_paths(path_nodes: &Nodes) -> Vec<String> {
-    let mut gcode_path: Vec<String> = vec![];
-    for node in &path_nodes.node {
-        gcode_path.push(format!("$J=X{} Y{} Z{} F250\n", node.x, node.y, node.z));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{Limits, Zone};
+
+    // A and C are next to each other, B is out of the way. hover is the height of every bath's
+    // hover node, edges are added to A
+    fn nodes(hover: f32, edges: &str) -> Nodes {
+        let mut nodes: Nodes = toml::from_str(&format!(
+            "[[node]]
+            name = 'HOME'
+            x = -1.0
+            y = -1.0
+            z = -1.0
+            hide = true
+            neighbors = ['A']
+
+            [[node]]
+            name = 'A'
+            x = -10.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            hover = {hover}
+            neighbors = ['HOME', 'B', 'C']
+            {edges}
+
+            [[node]]
+            name = 'B'
+            x = -20.0
+            y = -20.0
+            z = -5.0
+            hide = false
+            hover = {hover}
+            neighbors = ['A', 'C']
+
+            [[node]]
+            name = 'C'
+            x = -30.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            hover = {hover}
+            neighbors = ['A', 'B']",
+            hover = hover,
+            edges = edges
+        ))
+        .unwrap();
+        nodes.add_height_nodes();
+        nodes
+    }
+
+    // stands between A and C, clear of B
+    fn wall() -> Limits {
+        Limits {
+            keep_out: Some(vec![Zone {
+                name: None,
+                min: [-22.0, -5.0, -50.0],
+                max: [-18.0, 3.0, -0.5],
+            }]),
+            ..Limits::default()
+        }
+    }
+
+    fn node<'a>(nodes: &'a Nodes, name: &str) -> &'a Node {
+        nodes.node.iter().find(|n| n.name == name).unwrap()
+    }
+
+    fn path(nodes: &Nodes, from: &str, to: &str) -> Result<Vec<String>, PathError> {
+        gen_node_paths(nodes, node(nodes, from), node(nodes, to))
+            .map(|path| path.node.into_iter().map(|n| n.name).collect())
+    }
+
+    #[test]
+    fn shortest_path() {
+        let nodes = nodes(-1.0, "");
+        assert_eq!(
+            path(&nodes, "A", "C").unwrap(),
+            vec!["A_hover", "C_hover", "C"]
+        );
+        assert_eq!(path(&nodes, "HOME", "A").unwrap(), vec!["A_hover", "A"]);
+        assert_eq!(path(&nodes, "C", "C").unwrap(), vec!["C"]);
+    }
+
+    #[test]
+    fn edge_cost_overrides_distance() {
+        let nodes = nodes(-1.0, "edges = [{ to = 'C', cost = 100.0 }]");
+        assert_eq!(
+            path(&nodes, "A", "C").unwrap(),
+            vec!["A_hover", "B_hover", "C_hover", "C"]
+        );
+    }
+
+    #[test]
+    fn no_path() {
+        let mut nodes = nodes(-1.0, "");
+        // A can go down into its bath and nowhere else
+        let a_hover = nodes.node.iter_mut().find(|n| n.name == "A_hover").unwrap();
+        a_hover.neighbors.retain(|n| n == "A");
+        let a = node(&nodes, "A").clone();
+        let c = node(&nodes, "C").clone();
+        assert_eq!(
+            gen_node_paths(&nodes, &a, &c).err(),
+            Some(PathError::NoPath {
+                from: "A".to_string(),
+                to: "C".to_string()
+            })
+        );
+        let mut missing = c.clone();
+        missing.name = "D".to_string();
+        assert_eq!(
+            gen_node_paths(&nodes, &a, &missing).err(),
+            Some(PathError::UnknownNode("D".to_string()))
+        );
+    }
+
+    #[test]
+    fn lifts_over_keep_out() {
+        let mut nodes = nodes(-1.0, "");
+        nodes.limits = wall();
+        assert_eq!(
+            path(&nodes, "A", "C").unwrap(),
+            vec!["A_hover", "A_hover_lift", "C_hover_lift", "C_hover", "C"]
+        );
+    }
+
+    #[test]
+    fn long_lift_costs_more_than_going_around() {
+        let mut nodes = nodes(-40.0, "");
+        nodes.limits = wall();
+        assert_eq!(
+            path(&nodes, "A", "C").unwrap(),
+            vec!["A_hover", "B_hover", "C_hover", "C"]
+        );
+    }
+
+    #[test]
+    fn goes_around_legs_that_cannot_be_lifted_over() {
+        let mut nodes = nodes(-1.0, "");
+        nodes.limits = wall();
+        nodes.limits.keep_out.as_mut().unwrap()[0].max[2] = 1.0;
+        assert_eq!(
+            path(&nodes, "A", "C").unwrap(),
+            vec!["A_hover", "B_hover", "C_hover", "C"]
+        );
+    }
+
+    #[test]
+    fn jog_is_absolute_in_machine_coordinates() {
+        let nodes = nodes(-1.0, "");
+        let a = &nodes.node[nodes.node.iter().position(|n| n.name == "A").unwrap()];
+        let gcode = jog_gcode(a, 250.0);
+        assert!(gcode.starts_with("$J=G90 G53 "));
+        assert_eq!(
+            crate::limits::gcode_target([-9.0, -9.0, -9.0], &gcode),
+            Some([a.x, a.y, a.z])
+        );
     }
-    gcode_path
 }
-*/
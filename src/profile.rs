@@ -0,0 +1,100 @@
+use super::config::{self, ACTIONS, BATHS};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// one folder per plating line under the data root, each with its own config, recipes and logs
+pub const PROFILES: &str = "profiles";
+
+// value given after a flag, ie. --profile 'Line 2'
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1).cloned())
+}
+
+// command line without --data-dir and --profile, so positional arguments stay in place
+pub fn positional_args() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut skip = false;
+    for arg in env::args() {
+        if skip {
+            skip = false;
+        } else if arg == "--data-dir" || arg == "--profile" {
+            skip = true;
+        } else {
+            args.push(arg);
+        }
+    }
+    args
+}
+
+// where bathtub keeps its files: --data-dir, then BATHTUB_DATA, then the working directory
+pub fn data_root() -> PathBuf {
+    match arg_value("--data-dir").or(env::var("BATHTUB_DATA").ok()) {
+        Some(dir) => PathBuf::from(dir),
+        None => env::current_dir().unwrap_or(PathBuf::from(".")),
+    }
+}
+
+// names of the profiles under root, empty if root is a single line without profiles
+pub fn list(root: &Path) -> Vec<String> {
+    let mut profiles: Vec<String> = match fs::read_dir(root.join(PROFILES)) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    profiles.sort();
+    profiles
+}
+
+// the profile to open without asking: --profile, then BATHTUB_PROFILE, then the only profile.
+// Ok(None) means the user has to choose
+pub fn chosen(root: &Path) -> Result<Option<String>, String> {
+    let profiles = list(root);
+    match arg_value("--profile").or(env::var("BATHTUB_PROFILE").ok()) {
+        Some(name) if profiles.contains(&name) => Ok(Some(name)),
+        Some(name) => Err(format!("Profile '{}' not found in {}", name, root.join(PROFILES).display())),
+        None if profiles.len() == 1 => Ok(profiles.into_iter().next()),
+        None => Ok(None),
+    }
+}
+
+// every relative path in bathtub (config, recipes, logs, ...) is inside the directory entered
+pub fn enter(root: &Path, profile: Option<&str>) -> Result<(), String> {
+    let dir = match profile {
+        Some(name) => root.join(PROFILES).join(name),
+        None => root.to_path_buf(),
+    };
+    env::set_current_dir(&dir).map_err(|err| format!("Unable to open {}: {}", dir.display(), err))
+}
+
+// a new line starts with a HOME node and no actions, set up in Advanced
+pub fn create(root: &Path, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() || name.contains(|c| c == '/' || c == '\\') || name.starts_with('.') {
+        return Err("Profile names cannot be empty, start with '.' or contain '/' or '\\'".to_string());
+    }
+    let dir = root.join(PROFILES).join(name);
+    if dir.exists() {
+        return Err(format!("Profile '{}' already exists", name));
+    }
+    fs::create_dir_all(dir.join("config")).map_err(|err| err.to_string())?;
+    for file in &[BATHS, ACTIONS] {
+        fs::write(dir.join(file), config::default(file)).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+// name of the profile bathtub is running in, if it is using profiles
+pub fn name() -> Option<String> {
+    let dir = env::current_dir().ok()?;
+    match dir.parent()?.file_name()?.to_str()? {
+        PROFILES => Some(dir.file_name()?.to_string_lossy().to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+// where the running recipe is up to, kept until the recipe finishes or is stopped by the user so a
+// run cut short by a lost connection or a crash can be resumed
+pub const RUN_PROGRESS: &str = "./run_progress.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Progress {
+    pub recipe: String,
+    pub revision: Option<u32>,
+    // log file the run writes to, a resumed run keeps writing to it
+    pub log: String,
+    // index into the recipe's steps
+    pub step: usize,
+    pub step_num: String,
+    // how long the step's action had been running, 0 until the action starts
+    pub elapsed_ms: u64,
+    pub updated: String,
+}
+
+impl Progress {
+    pub fn new(recipe: String, revision: Option<u32>, log: String) -> Progress {
+        Progress {
+            recipe,
+            revision,
+            log,
+            step: 0,
+            step_num: String::new(),
+            elapsed_ms: 0,
+            updated: Local::now().to_rfc2822(),
+        }
+    }
+
+    pub fn load() -> Option<Progress> {
+        toml::from_str(&fs::read_to_string(RUN_PROGRESS).ok()?).ok()
+    }
+
+    // write to a temporary file first so a crash mid write cannot leave half a file behind
+    pub fn save(&mut self) {
+        self.updated = Local::now().to_rfc2822();
+        if let Ok(toml_str) = toml::to_string(self) {
+            let tmp = format!("{}.tmp", RUN_PROGRESS);
+            if fs::write(&tmp, toml_str).is_ok() {
+                fs::rename(&tmp, RUN_PROGRESS).unwrap_or(());
+            }
+        }
+    }
+
+    pub fn clear() {
+        fs::remove_file(RUN_PROGRESS).unwrap_or(());
+    }
+}
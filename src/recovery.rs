@@ -0,0 +1,170 @@
+use super::grbl::{Command as Cmd, Grbl, GrblError, Realtime, RESET_WAIT};
+use super::nodes::{Node, Nodes};
+use super::status::Status;
+use super::RecipeState;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+// where the gantry was last seen and what bathtub was doing, rewritten as the gantry moves so it
+// is still there after a crash
+pub const LAST_STATE: &str = "./last_state.toml";
+
+// how close GRBL's position must be to a node to count as being at it
+pub const NODE_TOLERANCE: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LastState {
+    pub node: Option<String>,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub recipe_state: String,
+    pub updated: String,
+}
+
+impl LastState {
+    pub fn new(status: &Status, nodes: &Nodes, recipe_state: RecipeState) -> LastState {
+        LastState {
+            node: nearest_node(nodes, status.x, status.y, status.z).map(|n| n.name.clone()),
+            x: status.x,
+            y: status.y,
+            z: status.z,
+            recipe_state: format!("{:?}", recipe_state),
+            updated: Local::now().to_rfc2822(),
+        }
+    }
+
+    pub fn load() -> Option<LastState> {
+        toml::from_str(&fs::read_to_string(LAST_STATE).ok()?).ok()
+    }
+
+    // write to a temporary file first so a crash mid write cannot leave half a file behind
+    pub fn save(&self) {
+        if let Ok(toml_str) = toml::to_string(self) {
+            let tmp = format!("{}.tmp", LAST_STATE);
+            if fs::write(&tmp, toml_str).is_ok() {
+                fs::rename(&tmp, LAST_STATE).unwrap_or(());
+            }
+        }
+    }
+
+    // only worth writing again once the gantry has moved or bathtub is doing something else
+    pub fn differs(&self, other: &LastState) -> bool {
+        (self.x - other.x).abs() >= NODE_TOLERANCE
+            || (self.y - other.y).abs() >= NODE_TOLERANCE
+            || (self.z - other.z).abs() >= NODE_TOLERANCE
+            || self.node != other.node
+            || self.recipe_state != other.recipe_state
+    }
+
+    pub fn lowered(&self, nodes: &Nodes) -> bool {
+        self.z < nodes.hover_z(self.x, self.y) - NODE_TOLERANCE
+    }
+}
+
+// write where the gantry is now if it differs from last
+pub fn record(last: &mut Option<LastState>, status: &Status, nodes: &Nodes, rs: RecipeState) {
+    let now = LastState::new(status, nodes, rs);
+    if last.as_ref().map_or(true, |l| now.differs(l)) {
+        now.save();
+        *last = Some(now);
+    }
+}
+
+// closest node within NODE_TOLERANCE on every axis, the same match the node monitor uses
+pub fn nearest_node<'a>(nodes: &'a Nodes, x: f32, y: f32, z: f32) -> Option<&'a Node> {
+    nodes
+        .node
+        .iter()
+        .filter(|n| {
+            (x - n.x).abs() < NODE_TOLERANCE
+                && (y - n.y).abs() < NODE_TOLERANCE
+                && (z - n.z).abs() < NODE_TOLERANCE
+        })
+        .min_by(|a, b| {
+            let dist = |n: &Node| (x - n.x).powi(2) + (y - n.y).powi(2) + (z - n.z).powi(2);
+            dist(a).total_cmp(&dist(b))
+        })
+}
+
+// GRBL loses its position when it is reset, it then waits in Alarm until homed
+pub fn position_lost(status: &Status) -> bool {
+    status.status.starts_with("Alarm")
+}
+
+// why the gantry has to be retracted and homed before anything else moves, None when bathtub was
+// left with the head raised and nothing running
+pub fn recovery_reason(
+    status: &Status,
+    last: &Option<LastState>,
+    nodes: &Nodes,
+) -> Option<String> {
+    if !position_lost(status) && status.z < nodes.hover_z(status.x, status.y) - NODE_TOLERANCE {
+        return Some(format!(
+            "GRBL reports the head is lowered at ({:.3}, {:.3}, {:.3}).",
+            status.x, status.y, status.z
+        ));
+    }
+    match last {
+        Some(last) if last.recipe_state != format!("{:?}", RecipeState::Stopped) => Some(format!(
+            "Bathtub closed while {} at {} ({}).",
+            last.recipe_state,
+            last.node.as_ref().unwrap_or(&"an unknown position".to_string()),
+            last.updated
+        )),
+        Some(last) if last.lowered(nodes) => Some(format!(
+            "The head was last seen lowered at {} ({}).",
+            last.node.as_ref().unwrap_or(&"an unknown position".to_string()),
+            last.updated
+        )),
+        _ => None,
+    }
+}
+
+// reset_first for alarms that lock grbl until it is reset, see Fault::needs_reset
+pub async fn recover(
+    grbl: Grbl,
+    nodes: Nodes,
+    feed: f32,
+    reset_first: bool,
+) -> Result<(), GrblError> {
+    // waiting for the gantry and homing can take minutes, kept off the executor's threads
+    tokio::task::spawn_blocking(move || {
+        if reset_first {
+            grbl.realtime(Realtime::SoftReset);
+            thread::sleep(RESET_WAIT);
+        }
+        retract_and_home(&grbl, &nodes, feed)
+    })
+    .await
+    .unwrap()
+}
+
+// $X, for alarms where grbl still knows where it is
+pub async fn unlock(grbl: Grbl, reset_first: bool) -> Result<(), GrblError> {
+    if reset_first {
+        grbl.realtime(Realtime::SoftReset);
+        tokio::time::sleep(RESET_WAIT).await;
+    }
+    grbl.request(Cmd::new("$X".to_string())).await.map(|_| ())
+}
+
+// jog straight up to hover height, wait for the gantry to stop, then home. GRBL will not jog
+// until homed when it has lost its position, homing raises Z first. Blocks until homed, async
+// callers go through recover()
+pub fn retract_and_home(grbl: &Grbl, nodes: &Nodes, feed: f32) -> Result<(), GrblError> {
+    let status = grbl.get_status().filter(|s| !position_lost(s));
+    grbl.clear_responses();
+    if let Some(s) = status {
+        grbl.push_command(Cmd::new(format!("$J=G90 G53 Z{} F{}", nodes.hover_z(s.x, s.y), feed)));
+        // let the jog start before waiting for idle
+        thread::sleep(Duration::from_millis(500));
+        while grbl.is_ok() && grbl.get_status().map_or(true, |s| s.status != "Idle") {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+    grbl.request_blocking(Cmd::new("$H".to_string())).map(|_| ())
+}
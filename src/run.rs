@@ -7,10 +7,16 @@ use iced::{
 
 use super::actions::Actions;
 use super::advanced::{validate_nums, ValidateNums};
+use super::dryrun::{dry_run, format_secs, DryRun};
+use super::estimate::{clock, step_estimates, Eta};
 use super::build::{attention_icon, ns, pause_icon, play_icon, Input, Recipe, SaveRecipe};
-use super::logger::Logger;
+use super::events::{Event, InputWhen};
+use super::grbl::Realtime;
+use super::logger::{get_username, Logger};
 use super::nodes::Nodes;
+use super::overrides::Overrides;
 use super::paths::gen_node_paths;
+use super::progress::Progress;
 use super::style::style::Theme;
 use chrono::prelude::*;
 use regex::Regex;
@@ -18,6 +24,7 @@ use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
+use std::mem::discriminant;
 use std::rc::Rc;
 use std::sync::{Arc, Condvar, Mutex};
 
@@ -31,6 +38,11 @@ pub struct Run {
     stop_confirm_btn: button::State,
     pause_btn: button::State,
     resume_btn: button::State,
+    preview_btn: button::State,
+    close_preview_btn: button::State,
+    // of the selected recipe, worked out again when it or the nodes change
+    dry_run: Option<DryRun>,
+    eta: Eta,
     pub search_options: Vec<String>,
     search_state: pick_list::State<String>,
     pub search_value: Option<String>,
@@ -48,6 +60,20 @@ pub struct Run {
     ref_actions: Rc<RefCell<Actions>>,
     node_map: Rc<RefCell<HashMap<String, usize>>>,
     pub current_step: Option<usize>,
+    // left on disk by a run that was cut short
+    pub interrupted: Option<Progress>,
+    // why interrupted cannot be resumed, worked out when it is set and when the tab is opened
+    interrupted_blocked: Option<String>,
+    // where the next run starts, taken by main.rs when the run begins
+    pub starting: Option<Progress>,
+    // log of the recipe run in progress, its progress is only read back if it matches
+    pub running: Option<String>,
+    resume_redo_btn: button::State,
+    resume_finish_btn: button::State,
+    discard_btn: button::State,
+    overrides: Overrides,
+    // (feed, rapid) overrides grbl last reported, set every tick in main.rs
+    pub overrides_percent: Option<(u16, u16)>,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +82,7 @@ pub enum RunState {
     BeforeRequiredInput,
     AfterRequiredInput,
     StopConfirm,
+    DryRun,
 }
 
 #[derive(Debug, Clone)]
@@ -70,9 +97,14 @@ pub enum RunMessage {
     Resume,
     UpdateSearch,
     SearchChanged(String),
+    // true to redo the whole action of the interrupted step
+    ResumeInterrupted(bool),
+    DiscardInterrupted,
     RequiredBeforeInput(usize, RequiredInputMessage),
     RequiredAfterInput(usize, RequiredInputMessage),
     Step,
+    DryRun,
+    Override(Realtime),
 }
 
 impl Run {
@@ -84,6 +116,7 @@ impl Run {
         ref_actions: Rc<RefCell<Actions>>,
         node_map: Rc<RefCell<HashMap<String, usize>>>,
     ) -> Self {
+        let interrupted = Progress::load();
         Run {
             scroll: scrollable::State::new(),
             large_start_btn: button::State::new(),
@@ -94,6 +127,10 @@ impl Run {
             stop_confirm_btn: button::State::new(),
             pause_btn: button::State::new(),
             resume_btn: button::State::new(),
+            preview_btn: button::State::new(),
+            close_preview_btn: button::State::new(),
+            dry_run: None,
+            eta: Eta::new(Vec::new()),
             search_options: Vec::new(),
             search_state: pick_list::State::default(),
             search_value: None,
@@ -111,6 +148,15 @@ impl Run {
             ref_actions,
             node_map,
             current_step: None,
+            interrupted_blocked: interrupted.as_ref().and_then(resume_blocked),
+            interrupted,
+            starting: None,
+            running: None,
+            resume_redo_btn: button::State::new(),
+            resume_finish_btn: button::State::new(),
+            discard_btn: button::State::new(),
+            overrides: Overrides::new(),
+            overrides_percent: None,
         }
     }
 
@@ -124,6 +170,8 @@ impl Run {
                 cvar.notify_all();
             }
             RunMessage::UpdateSearch => {
+                // the recipe may have been changed since
+                self.interrupted_blocked = self.interrupted.as_ref().and_then(resume_blocked);
                 // check for and update with new recipe files
                 self.search_options =
                     fs::read_dir("./recipes")
@@ -165,9 +213,27 @@ impl Run {
                         self.search_value.as_ref().unwrap()
                     );
                     self.logger.set_log_file(log_title.clone());
+                    self.interrupted = None;
+                    self.starting = Some(Progress::new(
+                        self.search_value.clone().unwrap(),
+                        self.recipe.as_ref().and_then(|r| r.revision),
+                        log_title.clone(),
+                    ));
+                    self.logger
+                        .send_event(Event::RunStarted {
+                            recipe: self.search_value.clone().unwrap(),
+                            operator: get_username(),
+                            revision: self.recipe.as_ref().and_then(|r| r.revision),
+                        })
+                        .unwrap();
                     self.logger
                         .send_line("--------------------".to_string())
                         .unwrap();
+                    self.logger
+                        .send_line(revision_line(
+                            self.recipe.as_ref().and_then(|r| r.revision),
+                        ))
+                        .unwrap();
                     for input in &self.required_before_inputs {
                         self.logger
                             .send_line(format!(
@@ -177,6 +243,13 @@ impl Run {
                                 &input.input_value
                             ))
                             .unwrap();
+                        self.logger
+                            .send_event(Event::Input {
+                                when: InputWhen::Before,
+                                title: input.title.clone(),
+                                value: input.input_value.clone(),
+                            })
+                            .unwrap();
                     }
                     self.logger
                         .send_line("--------------------".to_string())
@@ -206,11 +279,70 @@ impl Run {
                                 input.input_value
                             ))
                             .unwrap();
+                        self.logger
+                            .send_event(Event::Input {
+                                when: InputWhen::After,
+                                title: input.title.clone(),
+                                value: input.input_value.clone(),
+                            })
+                            .unwrap();
                     }
                     self.logger.set_log_file(String::new());
                     self.state = RunState::Standard;
                 }
             }
+            RunMessage::ResumeInterrupted(redo) => {
+                if let Some(mut progress) = self.interrupted.take() {
+                    self.search_value = Some(progress.recipe.clone());
+                    update_recipe(self);
+                    if redo {
+                        progress.elapsed_ms = 0;
+                    }
+                    self.logger.set_log_file(progress.log.clone());
+                    self.logger
+                        .send_line(format!(
+                            "{} => Resuming from step {}{}",
+                            Local::now().to_rfc2822(),
+                            progress.step_num,
+                            match redo {
+                                true => ", redoing its action".to_string(),
+                                false => format!(
+                                    ", skipping {} of its action already done",
+                                    format_secs(progress.elapsed_ms as f32 / 1000.0)
+                                ),
+                            }
+                        ))
+                        .unwrap();
+                    self.logger
+                        .send_event(Event::RunResumed {
+                            step: progress.step_num.clone(),
+                            skipped_secs: progress.elapsed_ms / 1000,
+                        })
+                        .unwrap();
+                    self.starting = Some(progress);
+                    self.state = RunState::Standard;
+                    command = Command::perform(do_nothing(), RunMessage::Run);
+                }
+            }
+            RunMessage::DiscardInterrupted => {
+                if let Some(progress) = self.interrupted.take() {
+                    self.logger.set_log_file(progress.log.clone());
+                    self.logger
+                        .send_line(format!(
+                            "{} => Interrupted run discarded at step {}",
+                            Local::now().to_rfc2822(),
+                            progress.step_num
+                        ))
+                        .unwrap();
+                    self.logger.set_log_file(String::new());
+                    Progress::clear();
+                }
+            }
+            RunMessage::DryRun => {
+                if self.dry_run.is_some() {
+                    self.state = RunState::DryRun;
+                }
+            }
             RunMessage::Cancel => {
                 for input in &mut self.required_before_inputs {
                     input.input_value = "".to_string();
@@ -228,12 +360,42 @@ impl Run {
             RunMessage::Stop => {}
             RunMessage::Pause(_) => {}
             RunMessage::Resume => {}
+            RunMessage::Override(_) => {}
         };
         command
     }
 
+    // called every tick so the countdown follows the running step and stops while paused
+    pub fn set_interrupted(&mut self, progress: Option<Progress>) {
+        self.interrupted_blocked = progress.as_ref().and_then(resume_blocked);
+        self.interrupted = progress;
+    }
+
+    pub fn update_dry_run(&mut self) {
+        self.dry_run = self.recipe.as_ref().map(|recipe| {
+            dry_run(
+                &recipe.steps,
+                &*self.ref_nodes.borrow(),
+                &*self.node_map.borrow(),
+                &*self.ref_actions.borrow(),
+            )
+        });
+    }
+
+    pub fn update_eta(&mut self) {
+        let (recipe_state, _) = &*self.recipe_state;
+        let paused = match *recipe_state.lock().unwrap() {
+            RecipeState::RecipePaused => true,
+            RecipeState::RequireInput => true,
+            _ => false,
+        };
+        self.eta.set_paused(paused);
+        self.eta.set_step(self.current_step);
+    }
+
     pub fn view(&mut self) -> Element<RunMessage> {
         let ref_nodes = &self.ref_nodes;
+        let eta = &self.eta;
         let ref_actions = &self.ref_actions;
         let node_map = &self.node_map;
         let current_step = &self.current_step;
@@ -273,7 +435,7 @@ impl Run {
                         let (recipe_state, _) = &*self.recipe_state;
                         match *recipe_state.lock().unwrap() {
                             RecipeState::Stopped => {
-                                if let Some(recipe) = &self.recipe {
+                                if let (Some(recipe), Some(preview)) = (&self.recipe, &self.dry_run) {
                                     Row::new().push(
                                     if recipe.steps.len() <= 1 {
                                         start_btn("Recipes need to have more than 1 step.", &mut self.large_start_btn, Theme::GreenDisabled)
@@ -292,14 +454,36 @@ impl Run {
                                                   This is an isssue with the neighbors of each node.\n\
                                                   Neighbors are configurable one way safe paths between nodes.\n\
                                                   Paths are built while the recipe is running,\n\
-                                                  and will traverse the fewest neighbors between steps.\n\
+                                                  and will take the shortest distance between steps.\n\
                                                   Change the neighbors in 'Advanced' -> 'Nodes'",
                                                   &mut self.start_btn, Theme::GreenDisabled)
+                                    } else if preview.violations.len() > 0 {
+                                        start_btn("This recipe leaves the safe work area.\n\
+                                                  Check config/limits.toml or press 'Preview' for more information.",
+                                                  &mut self.start_btn, Theme::GreenDisabled)
+                                    } else if preview.errors.len() > 0 {
+                                        start_btn("This recipe cannot be completed starting from HOME.\n\
+                                                  Press 'Preview' for more information.",
+                                                  &mut self.start_btn, Theme::GreenDisabled)
                                     } else if *self.homing_required.borrow() {
                                         start_btn("Will run homing cycle first!", &mut self.start_btn, Theme::Yellow)
                                     } else {
                                         start_btn("", &mut self.start_btn, Theme::Green)
                                     })
+                                    .push(Space::with_width(Length::Units(20)))
+                                    .push(
+                                        Button::new(
+                                            &mut self.preview_btn,
+                                            Text::new("Preview")
+                                                .size(30)
+                                                .horizontal_alignment(HorizontalAlignment::Center)
+                                                .font(CQ_MONO),
+                                        )
+                                        .style(Theme::Blue)
+                                        .on_press(RunMessage::DryRun)
+                                        .padding(10)
+                                        .width(Length::Units(200)),
+                                    )
                                 } else {
                                     Row::new()
                                 }
@@ -422,6 +606,28 @@ impl Run {
                     }
                     None => Row::new(),
                 };
+                let overrides: Element<_> = match *self.recipe_state.0.lock().unwrap() {
+                    RecipeState::RecipeRunning | RecipeState::RecipePaused => self
+                        .overrides
+                        .view(self.overrides_percent)
+                        .map(RunMessage::Override),
+                    _ => Column::new().into(),
+                };
+                let eta_text = match self.recipe {
+                    Some(_) if self.current_step.is_some() => Text::new(format!(
+                        "Finishes in {} (around {}){}",
+                        format_secs(self.eta.total_remaining()),
+                        clock(self.eta.total_remaining()).trim(),
+                        if self.eta.is_paused() { " - paused" } else { "" }
+                    ))
+                    .size(20),
+                    Some(_) => Text::new(format!(
+                        "Estimated duration: {}",
+                        format_secs(self.eta.total_remaining())
+                    ))
+                    .size(20),
+                    None => Text::new(""),
+                };
                 let recipe: Element<_> = match self.recipe.as_mut() {
                     Some(recipe) => recipe
                         .steps
@@ -436,8 +642,20 @@ impl Run {
                                         .push(Space::with_height(Length::Fill))
                                         .push(
                                             Row::new()
-                                                .width(Length::Units(500))
+                                                .width(Length::Units(620))
                                                 .push(step.view().map(move |_msg| RunMessage::Step))
+                                                .push(
+                                                    Text::new(match eta.step_remaining(i) {
+                                                        Some(secs) if *current_step == Some(i) => {
+                                                            format!("{} left", format_secs(secs))
+                                                        }
+                                                        Some(secs) => format!("~{}", format_secs(secs)),
+                                                        None => String::new(),
+                                                    })
+                                                    .size(16)
+                                                    .width(Length::Units(120))
+                                                    .vertical_alignment(VerticalAlignment::Center),
+                                                )
                                                 .push(if let Some(num) = current_step {
                                                     if *num == i && is_waiting_for_input {
                                                         Column::new().push(
@@ -482,11 +700,82 @@ impl Run {
                     None => Column::new().into(),
                 };
 
+                let interrupted: Element<_> = match &self.interrupted {
+                    Some(progress) if discriminant(&*self.recipe_state.0.lock().unwrap())
+                        == discriminant(&RecipeState::Stopped) =>
+                    {
+                        let blocked = &self.interrupted_blocked;
+                        let mut buttons = Row::new().spacing(20);
+                        if blocked.is_none() {
+                            buttons = buttons
+                                .push(
+                                    Button::new(
+                                        &mut self.resume_finish_btn,
+                                        Text::new(format!("Resume from step {}", progress.step_num))
+                                            .horizontal_alignment(HorizontalAlignment::Center)
+                                            .font(CQ_MONO),
+                                    )
+                                    .style(Theme::Green)
+                                    .on_press(RunMessage::ResumeInterrupted(false))
+                                    .padding(10),
+                                )
+                                .push(
+                                    Button::new(
+                                        &mut self.resume_redo_btn,
+                                        Text::new("Redo Whole Step")
+                                            .horizontal_alignment(HorizontalAlignment::Center)
+                                            .font(CQ_MONO),
+                                    )
+                                    .style(Theme::Blue)
+                                    .on_press(RunMessage::ResumeInterrupted(true))
+                                    .padding(10),
+                                );
+                        }
+                        buttons = buttons.push(
+                            Button::new(
+                                &mut self.discard_btn,
+                                Text::new("Discard")
+                                    .horizontal_alignment(HorizontalAlignment::Center)
+                                    .font(CQ_MONO),
+                            )
+                            .style(Theme::Red)
+                            .on_press(RunMessage::DiscardInterrupted)
+                            .padding(10),
+                        );
+                        Container::new(
+                            Column::new()
+                                .spacing(10)
+                                .push(
+                                    Text::new(format!(
+                                        "'{}' was interrupted at step {} ({}).\n{} of the step's action had run.{}",
+                                        progress.recipe,
+                                        progress.step_num,
+                                        progress.updated,
+                                        format_secs(progress.elapsed_ms as f32 / 1000.0),
+                                        match &blocked {
+                                            Some(reason) => format!("\n{}", reason),
+                                            None => String::new(),
+                                        }
+                                    ))
+                                    .size(20),
+                                )
+                                .push(buttons),
+                        )
+                        .padding(20)
+                        .width(Length::Fill)
+                        .style(Theme::Yellow)
+                        .into()
+                    }
+                    _ => Column::new().into(),
+                };
                 let content = Column::new()
                     .max_width(800)
                     .spacing(20)
+                    .push(interrupted)
                     .push(search)
                     .push(run)
+                    .push(overrides)
+                    .push(eta_text)
                     .push(recipe)
                     .align_items(Align::Center);
 
@@ -534,6 +823,97 @@ impl Run {
                     .push(Container::new(content).width(Length::Fill).center_x())
                     .into()
             }
+            RunState::DryRun => {
+                let dry_run = self.dry_run.as_ref().unwrap();
+                let mut content = Column::new()
+                    .max_width(800)
+                    .spacing(20)
+                    .align_items(Align::Center)
+                    .push(
+                        Text::new(format!(
+                            "Preview - {}",
+                            self.search_value.as_ref().unwrap_or(&String::new())
+                        ))
+                        .font(CQ_MONO)
+                        .size(40),
+                    )
+                    .push(
+                        Text::new(format!(
+                            "Estimated duration: {}{}",
+                            format_secs(dry_run.total_secs()),
+                            if dry_run.waits_for_input {
+                                "\n(plus time waiting for user input)"
+                            } else {
+                                ""
+                            }
+                        ))
+                        .horizontal_alignment(HorizontalAlignment::Center)
+                        .size(25),
+                    );
+                for error in &dry_run.errors {
+                    content = content.push(
+                        Container::new(Text::new(error).size(20))
+                            .width(Length::Fill)
+                            .padding(10)
+                            .style(Theme::Red),
+                    );
+                }
+                content = dry_run.steps.iter().enumerate().fold(content, |col, (i, step)| {
+                    col.push(
+                        Container::new(
+                            Column::new()
+                                .spacing(5)
+                                .push(
+                                    Text::new(format!(
+                                        "{}) {} @ {}",
+                                        step.step_num, step.action, step.destination
+                                    ))
+                                    .font(CQ_MONO)
+                                    .size(20),
+                                )
+                                .push(Text::new(format!(
+                                    "Travel {}, action {}{}",
+                                    format_secs(step.travel_secs),
+                                    format_secs(step.action_secs),
+                                    if step.repeats {
+                                        " (commands repeat until time is up)"
+                                    } else {
+                                        ""
+                                    }
+                                )))
+                                .push(Text::new(format!("Path: {}", step.path.join(" > "))))
+                                .push(
+                                    Text::new(step.gcode.join("\n"))
+                                        .font(CQ_MONO)
+                                        .size(16),
+                                ),
+                        )
+                        .width(Length::Fill)
+                        .padding(10)
+                        .style(if i % 2 == 0 {
+                            Theme::LightGray
+                        } else {
+                            Theme::LighterGray
+                        }),
+                    )
+                });
+                content = content.push(
+                    Button::new(
+                        &mut self.close_preview_btn,
+                        Text::new("Close")
+                            .font(CQ_MONO)
+                            .horizontal_alignment(HorizontalAlignment::Center),
+                    )
+                    .style(Theme::Blue)
+                    .on_press(RunMessage::Cancel)
+                    .padding(10)
+                    .width(Length::Units(200)),
+                );
+                Scrollable::new(&mut self.scroll)
+                    .padding(40)
+                    .push(Container::new(content).width(Length::Fill).center_x())
+                    .into()
+            }
             RunState::BeforeRequiredInput => {
                 let content = Column::new()
                     .max_width(800)
@@ -794,31 +1174,8 @@ pub async fn do_nothing() {
     ()
 }
 fn update_recipe(tab: &mut Run) {
-    match &fs::read_to_string(format!(
-        "./recipes/{}.toml",
-        tab.search_value.as_ref().unwrap_or(&String::new())
-    )) {
-        Ok(toml_str) => {
-            let save_rec: SaveRecipe = toml::from_str(toml_str).unwrap();
-            let rec = Recipe {
-                required_inputs: Input {
-                    before: if let Some(b) = save_rec.required_inputs.before {
-                        b
-                    } else {
-                        Vec::new()
-                    },
-                    after: if let Some(a) = save_rec.required_inputs.after {
-                        a
-                    } else {
-                        Vec::new()
-                    },
-                },
-                steps: if let Some(s) = save_rec.steps {
-                    s
-                } else {
-                    Vec::new()
-                },
-            };
+    match read_recipe(tab.search_value.as_ref().unwrap_or(&String::new())) {
+        Ok(rec) => {
             tab.required_before_inputs = rec.required_inputs.before.iter().fold(
                 Vec::with_capacity(rec.required_inputs.before.len()),
                 |mut v, input| {
@@ -835,11 +1192,79 @@ fn update_recipe(tab: &mut Run) {
             );
             tab.continue_btns = vec![button::State::new(); rec.steps.len()];
             tab.recipe = Some(rec);
+            tab.update_dry_run();
+            let rec = tab.recipe.as_ref().unwrap();
+            tab.eta = Eta::new(step_estimates(
+                tab.search_value.as_ref().unwrap(),
+                rec.revision,
+                &rec.steps,
+                tab.dry_run.as_ref().unwrap(),
+            ));
         }
         // TODO: Display Error when unable to read file
         Err(_err) => {
             tab.recipe = None;
+            tab.dry_run = None;
+        }
+    }
+}
+
+// why an interrupted run cannot be picked up again, if it can't
+fn resume_blocked(progress: &Progress) -> Option<String> {
+    match read_recipe(&progress.recipe) {
+        Ok(recipe) if recipe.revision != progress.revision => Some(format!(
+            "'{}' has been changed since, it can only be started from the beginning.",
+            progress.recipe
+        )),
+        Ok(recipe) if progress.step >= recipe.steps.len() => {
+            Some(format!("'{}' no longer has this step.", progress.recipe))
+        }
+        Ok(_) => None,
+        Err(_) => Some(format!("'{}' no longer exists.", progress.recipe)),
+    }
+}
+
+// log line for the revision being run, recipes saved before history was kept have none
+pub fn revision_line(revision: Option<u32>) -> String {
+    format!(
+        "{} => Recipe revision: {}",
+        Local::now().to_rfc2822(),
+        match revision {
+            Some(revision) => revision.to_string(),
+            None => "**Unavailable**".to_string(),
+        }
+    )
+}
+
+// read ./recipes/<name>.toml, missing inputs or steps are left empty
+pub fn read_recipe(name: &str) -> Result<Recipe, String> {
+    let path = format!("./recipes/{}.toml", name);
+    match &fs::read_to_string(&path) {
+        Ok(toml_str) => {
+            let save_rec: SaveRecipe =
+                toml::from_str(toml_str).map_err(|err| format!("{}: {}", path, err))?;
+            Ok(Recipe {
+                revision: save_rec.revision,
+                required_inputs: Input {
+                    before: if let Some(b) = save_rec.required_inputs.before {
+                        b
+                    } else {
+                        Vec::new()
+                    },
+                    after: if let Some(a) = save_rec.required_inputs.after {
+                        a
+                    } else {
+                        Vec::new()
+                    },
+                },
+                steps: if let Some(s) = save_rec.steps {
+                    s
+                } else {
+                    Vec::new()
+                },
+            })
         }
+        Err(_err) => Err(format!("Unable to read {}", path)),
     }
 }
 
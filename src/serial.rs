@@ -0,0 +1,162 @@
+use super::config::{self, SERIAL};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serialport::{
+    ClearBuffer, DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, SerialPortType,
+    StopBits,
+};
+use std::fs;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BAUD: u32 = 115_200;
+
+// which port grbl was last found on and how fast to talk to it, from config/serial.toml
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SerialSettings {
+    pub baud: Option<u32>,
+    pub port: Option<PortId>,
+}
+
+// usb ports are matched by vid, pid and serial number, so grbl is found again on a
+// different port name. Other ports only by name
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PortId {
+    pub name: String,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+}
+
+impl PortId {
+    pub fn from_info(info: &SerialPortInfo) -> PortId {
+        match &info.port_type {
+            SerialPortType::UsbPort(usb) => PortId {
+                name: info.port_name.clone(),
+                vid: Some(usb.vid),
+                pid: Some(usb.pid),
+                serial_number: usb.serial_number.clone(),
+            },
+            _ => PortId {
+                name: info.port_name.clone(),
+                vid: None,
+                pid: None,
+                serial_number: None,
+            },
+        }
+    }
+
+    pub fn matches(&self, other: &PortId) -> bool {
+        match (self.vid, other.vid) {
+            (Some(_), Some(_)) => {
+                self.vid == other.vid
+                    && self.pid == other.pid
+                    && self.serial_number == other.serial_number
+            }
+            _ => self.name == other.name,
+        }
+    }
+}
+
+impl SerialSettings {
+    // a missing or broken file means the defaults, every port is probed anyway
+    pub fn load() -> SerialSettings {
+        config::read(SERIAL).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        fs::create_dir_all("config").unwrap_or(());
+        fs::write(SERIAL, toml::to_string_pretty(self).unwrap()).unwrap_or(());
+    }
+
+    pub fn baud(&self) -> u32 {
+        self.baud.unwrap_or(DEFAULT_BAUD)
+    }
+}
+
+// ports to show in the port picker, with what is known about the device on each
+pub fn available() -> Vec<(PortId, String)> {
+    serialport::available_ports()
+        .unwrap_or(Vec::new())
+        .iter()
+        .map(|info| {
+            let label = match &info.port_type {
+                SerialPortType::UsbPort(usb) => format!(
+                    "{} ({:04x}:{:04x}{})",
+                    info.port_name,
+                    usb.vid,
+                    usb.pid,
+                    usb.product
+                        .as_ref()
+                        .map(|p| format!(" {}", p))
+                        .unwrap_or(String::new())
+                ),
+                _ => info.port_name.clone(),
+            };
+            (PortId::from_info(info), label)
+        })
+        .collect()
+}
+
+// find grbl: the remembered port first, then every other port. Only a port that answers with
+// the grbl 1.1 banner is used, so barcode scanners and other arduinos are left alone
+pub fn find_grbl(timeout: Duration) -> Result<(Box<dyn SerialPort>, String), String> {
+    let mut settings = SerialSettings::load();
+    let mut ports: Vec<PortId> = available().into_iter().map(|(id, _)| id).collect();
+    if ports.is_empty() {
+        return Err("No serial ports found".to_string());
+    }
+    if let Some(preferred) = &settings.port {
+        ports.sort_by_key(|id| !preferred.matches(id));
+    }
+    let mut problems = Vec::new();
+    for id in ports {
+        match probe(&id.name, settings.baud()) {
+            Ok(mut port) => {
+                port.set_timeout(timeout).unwrap_or(());
+                if settings.port.as_ref() != Some(&id) {
+                    settings.port = Some(id.clone());
+                    settings.save();
+                }
+                return Ok((port, id.name));
+            }
+            Err(err) => problems.push(format!("{}: {}", id.name, err)),
+        }
+    }
+    Err(problems.join("\n"))
+}
+
+fn probe(name: &str, baud: u32) -> Result<Box<dyn SerialPort>, String> {
+    let banner = Regex::new(r"Grbl 1\.1[a-z]? \['\$' for help\]").unwrap();
+    let mut port = serialport::new(name, baud)
+        .parity(Parity::None)
+        .data_bits(DataBits::Eight)
+        .stop_bits(StopBits::One)
+        .flow_control(FlowControl::None)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .map_err(|err| err.to_string())?;
+    // opening the port resets most arduinos and grbl prints its banner, if it does not
+    // (already running, no reset line) a soft reset asks for it
+    let start = Instant::now();
+    let mut reset_sent = false;
+    let mut seen = Vec::new();
+    let mut buf = [0u8; 64];
+    while start.elapsed() < Duration::from_secs(4) {
+        if !reset_sent && start.elapsed() > Duration::from_secs(2) {
+            port.write_all(&[0x18]).map_err(|err| err.to_string())?;
+            reset_sent = true;
+        }
+        if let Ok(n) = port.read(&mut buf) {
+            seen.extend_from_slice(&buf[..n]);
+        }
+        if banner.is_match(&String::from_utf8_lossy(&seen)) {
+            // grbl may follow the banner with [MSG:...] lines, not an answer to anything sent
+            thread::sleep(Duration::from_millis(200));
+            port.clear(ClearBuffer::Input).unwrap_or(());
+            return Ok(port);
+        }
+    }
+    Err(format!("no GRBL 1.1 banner at {} baud", baud))
+}
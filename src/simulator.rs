@@ -0,0 +1,675 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// number of blocks grbl 1.1 can hold in its planner before it stops answering 'ok'
+const PLANNER_SIZE: usize = 15;
+// grbl's serial receive buffer, bytes that arrive when it is full are lost
+const RX_BUFFER_SIZE: usize = 128;
+
+const DEFAULT_SETTINGS: [(u16, &str); 34] = [
+    (0, "10"),
+    (1, "25"),
+    (2, "0"),
+    (3, "0"),
+    (4, "0"),
+    (5, "0"),
+    (6, "0"),
+    (10, "1"),
+    (11, "0.010"),
+    (12, "0.002"),
+    (13, "0"),
+    (20, "0"),
+    (21, "0"),
+    (22, "1"),
+    (23, "0"),
+    (24, "25.000"),
+    (25, "500.000"),
+    (26, "250"),
+    (27, "1.000"),
+    (30, "1000"),
+    (31, "0"),
+    (32, "0"),
+    (100, "250.000"),
+    (101, "250.000"),
+    (102, "250.000"),
+    (110, "500.000"),
+    (111, "500.000"),
+    (112, "500.000"),
+    (120, "10.000"),
+    (121, "10.000"),
+    (122, "10.000"),
+    (130, "200.000"),
+    (131, "200.000"),
+    (132, "200.000"),
+];
+
+// in-process stand-in for a grbl 1.1 controller, used in place of the usb serial connection
+pub struct Simulator {
+    timeout: Duration,
+    line: Vec<u8>,
+    output: VecDeque<u8>,
+    waiting: VecDeque<String>,
+    machine: Machine,
+    // after a hard or soft limit grbl only listens to reset and status requests
+    critical: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MachineState {
+    Idle,
+    Run,
+    Jog,
+    Hold,
+    Home,
+    Alarm,
+}
+
+// overrides only change G0 and G1 moves, never jogs or homing
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Motion {
+    Jog,
+    Rapid,
+    Feed,
+}
+
+#[derive(Debug, Clone)]
+struct Move {
+    target: [f32; 3],
+    feed: f32,
+    motion: Motion,
+}
+
+#[derive(Debug)]
+struct Machine {
+    state: MachineState,
+    position: [f32; 3],
+    moves: VecDeque<Move>,
+    feed: f32,
+    absolute: bool,
+    settings: BTreeMap<u16, String>,
+    startup: [String; 2],
+    last_tick: Instant,
+    feed_override: u16,
+    rapid_override: u16,
+}
+
+impl Simulator {
+    pub fn new(timeout: Duration) -> Simulator {
+        let mut settings = BTreeMap::new();
+        for (key, value) in DEFAULT_SETTINGS.iter() {
+            settings.insert(*key, value.to_string());
+        }
+        let mut machine = Machine {
+            state: MachineState::Idle,
+            position: [0.0, 0.0, 0.0],
+            moves: VecDeque::new(),
+            feed: 0.0,
+            absolute: true,
+            settings,
+            startup: [String::new(), String::new()],
+            last_tick: Instant::now(),
+            feed_override: 100,
+            rapid_override: 100,
+        };
+        // grbl boots locked when homing is enabled
+        if machine.setting(22) != 0.0 {
+            machine.state = MachineState::Alarm;
+        }
+        Simulator {
+            timeout,
+            line: Vec::new(),
+            output: VecDeque::new(),
+            waiting: VecDeque::new(),
+            machine,
+            critical: false,
+        }
+    }
+
+    // bytes of lines received and not yet run
+    fn buffered(&self) -> usize {
+        self.line.len() + self.waiting.iter().map(|line| line.len() + 1).sum::<usize>()
+    }
+
+    fn respond(&mut self, response: &str) {
+        self.output.extend(format!("{}\r\n", response).bytes());
+    }
+
+    // advance the gantry and answer any lines that were waiting on it
+    fn tick(&mut self) {
+        if self.machine.tick() {
+            self.respond("ok");
+        }
+        while self.machine.state != MachineState::Home && !self.critical {
+            let ready = match self.waiting.front() {
+                Some(line) => !line.starts_with("$J=") || self.machine.moves.len() < PLANNER_SIZE,
+                None => false,
+            };
+            if !ready {
+                break;
+            }
+            let line = self.waiting.pop_front().unwrap();
+            if let Some(response) = self.execute(&line) {
+                self.respond(&response);
+            }
+        }
+    }
+
+    fn realtime(&mut self, byte: u8) {
+        match byte {
+            b'?' => {
+                let report = self.machine.status_report();
+                self.respond(&report);
+            }
+            // a feed hold while jogging is a jog cancel
+            0x85 | b'!' if self.machine.state == MachineState::Jog => {
+                self.machine.moves.clear();
+                self.machine.state = MachineState::Idle;
+                self.waiting.retain(|line| !line.starts_with("$J="));
+            }
+            0x85 => self.waiting.retain(|line| !line.starts_with("$J=")),
+            // grbl ignores a feed hold when nothing is moving
+            b'!' => {
+                if self.machine.state == MachineState::Run {
+                    self.machine.state = MachineState::Hold;
+                }
+            }
+            b'~' => {
+                if self.machine.state == MachineState::Hold {
+                    self.machine.state = MachineState::Run;
+                }
+            }
+            0x18 => self.reset(),
+            0x90 => self.machine.feed_override = 100,
+            0x91..=0x94 => {
+                let change: i32 = match byte {
+                    0x91 => 10,
+                    0x92 => -10,
+                    0x93 => 1,
+                    _ => -1,
+                };
+                self.machine.feed_override =
+                    (self.machine.feed_override as i32 + change).max(10).min(200) as u16;
+            }
+            0x95 => self.machine.rapid_override = 100,
+            0x96 => self.machine.rapid_override = 50,
+            0x97 => self.machine.rapid_override = 25,
+            // remaining realtime commands and utf-8 lead bytes are ignored
+            _ => {}
+        }
+    }
+
+    // everything received and planned is lost, stopping mid move loses the position too
+    fn reset(&mut self) {
+        let machine = &mut self.machine;
+        if machine.moves.len() > 0 && machine.state != MachineState::Hold {
+            machine.state = MachineState::Alarm;
+            self.output.extend("ALARM:3\r\n".bytes());
+        } else if machine.state != MachineState::Alarm {
+            machine.state = MachineState::Idle;
+        }
+        machine.moves.clear();
+        self.critical = false;
+        machine.feed_override = 100;
+        machine.rapid_override = 100;
+        self.waiting.clear();
+        self.line.clear();
+        self.respond("");
+        self.respond("Grbl 1.1h ['$' for help]");
+        if self.machine.state == MachineState::Alarm {
+            self.respond("[MSG:'$H'|'$X' to unlock]");
+        }
+    }
+
+    // run a full line, returns None when the response is sent later ($H)
+    fn execute(&mut self, line: &str) -> Option<String> {
+        let machine = &mut self.machine;
+        if line.is_empty() {
+            return Some("ok".to_string());
+        }
+        if !line.starts_with('$') {
+            let response = machine.gcode(line);
+            if response.starts_with("ALARM:") {
+                self.critical = true;
+                self.waiting.clear();
+                return Some(format!("{}\r\n[MSG:Reset to continue]", response));
+            }
+            return Some(response);
+        }
+        match line {
+            "$" => Some(
+                "[HLP:$$ $# $G $I $N $x=val $Nx=line $J=line $SLP $C $X $H ~ ! ? ctrl-x]\r\nok"
+                    .to_string(),
+            ),
+            "$$" => {
+                let mut response = machine
+                    .settings
+                    .iter()
+                    .fold(String::new(), |mut s, (key, value)| {
+                        s.push_str(&format!("${}={}\r\n", key, value));
+                        s
+                    });
+                response.push_str("ok");
+                Some(response)
+            }
+            "$I" => Some("[VER:1.1h.20190830:]\r\n[OPT:V,15,128]\r\nok".to_string()),
+            "$N" => Some(format!(
+                "$N0={}\r\n$N1={}\r\nok",
+                machine.startup[0], machine.startup[1]
+            )),
+            "$G" => Some(format!(
+                "[GC:G0 G54 G17 G21 {} G94 M5 M9 T0 F{} S0]\r\nok",
+                if machine.absolute { "G90" } else { "G91" },
+                machine.feed
+            )),
+            "$X" => {
+                if machine.state == MachineState::Alarm {
+                    machine.state = MachineState::Idle;
+                    Some("[MSG:Caution: Unlocked]\r\nok".to_string())
+                } else {
+                    Some("ok".to_string())
+                }
+            }
+            "$H" => {
+                if machine.setting(22) == 0.0 {
+                    Some("error:5".to_string())
+                } else if machine.state != MachineState::Idle
+                    && machine.state != MachineState::Alarm
+                {
+                    Some("error:8".to_string())
+                } else {
+                    machine.home();
+                    None
+                }
+            }
+            _ => {
+                if line.starts_with("$J=") {
+                    Some(machine.jog(&line[3..]))
+                } else if line.starts_with("$N0=") || line.starts_with("$N1=") {
+                    let index = if line.starts_with("$N0=") { 0 } else { 1 };
+                    machine.startup[index] = line[4..].to_string();
+                    Some("ok".to_string())
+                } else {
+                    Some(machine.write_setting(&line[1..]))
+                }
+            }
+        }
+    }
+}
+
+impl Machine {
+    fn setting(&self, key: u16) -> f32 {
+        self.settings
+            .get(&key)
+            .and_then(|value| value.parse::<f32>().ok())
+            .unwrap_or(0.0)
+    }
+
+    // moves the gantry along the planner for the time since the last tick,
+    // returns true when a homing cycle just finished
+    fn tick(&mut self) -> bool {
+        let mut travel = self.last_tick.elapsed().as_secs_f32();
+        self.last_tick = Instant::now();
+        // held moves stay in the planner until cycle start
+        if self.state == MachineState::Hold {
+            travel = 0.0;
+        }
+        while let Some(mv) = self.moves.front() {
+            let feed = self.overridden(mv);
+            let delta: Vec<f32> = (0..3).map(|i| mv.target[i] - self.position[i]).collect();
+            let distance = delta.iter().map(|d| d * d).sum::<f32>().sqrt();
+            let reach = feed / 60.0 * travel;
+            if reach >= distance {
+                self.position = mv.target;
+                travel -= if feed > 0.0 {
+                    distance / (feed / 60.0)
+                } else {
+                    travel
+                };
+                self.moves.pop_front();
+            } else {
+                for i in 0..3 {
+                    self.position[i] += delta[i] / distance * reach;
+                }
+                break;
+            }
+        }
+        if self.moves.len() == 0 {
+            match self.state {
+                MachineState::Jog | MachineState::Run => self.state = MachineState::Idle,
+                MachineState::Home => {
+                    self.state = MachineState::Idle;
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    fn overridden(&self, mv: &Move) -> f32 {
+        match mv.motion {
+            Motion::Jog => mv.feed,
+            Motion::Rapid => mv.feed * self.rapid_override as f32 / 100.0,
+            Motion::Feed => mv.feed * self.feed_override as f32 / 100.0,
+        }
+    }
+
+    fn status_report(&self) -> String {
+        let feed = match self.moves.front() {
+            Some(mv) if self.state != MachineState::Hold => self.overridden(mv),
+            _ => 0.0,
+        };
+        let state = match self.state {
+            // the moves held in the planner mean it is stopped and ready to resume
+            MachineState::Hold => "Hold:0".to_string(),
+            state => format!("{:?}", state),
+        };
+        format!(
+            "<{}|MPos:{:.3},{:.3},{:.3}|FS:{},0|Ov:{},{},100>",
+            state,
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            feed,
+            self.feed_override,
+            self.rapid_override
+        )
+    }
+
+    // seek each axis to the switches then back off by the pull-off distance
+    fn home(&mut self) {
+        let seek = self.setting(25);
+        let pull_off = -self.setting(27);
+        let mut target = self.position;
+        target[2] = 0.0;
+        let motion = Motion::Jog;
+        self.moves.push_back(Move { target, feed: seek, motion });
+        target = [0.0, 0.0, 0.0];
+        self.moves.push_back(Move { target, feed: seek, motion });
+        target = [pull_off, pull_off, pull_off];
+        self.moves.push_back(Move {
+            target,
+            feed: self.setting(24),
+            motion,
+        });
+        self.state = MachineState::Home;
+    }
+
+    // parse the words of a motion line into a target, errors use grbl's codes
+    fn target(&self, words: &str, absolute: bool) -> Result<([f32; 3], Option<f32>), String> {
+        let mut absolute = absolute;
+        let mut axes: [Option<f32>; 3] = [None, None, None];
+        let mut feed = None;
+        let chars: Vec<char> = words.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let letter = chars[i];
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '-')
+            {
+                i += 1;
+            }
+            let value = match chars[start..i].iter().collect::<String>().parse::<f32>() {
+                Ok(value) => value,
+                Err(_) => return Err("error:2".to_string()),
+            };
+            match letter {
+                'X' | 'Y' | 'Z' => axes[(letter as u8 - b'X') as usize] = Some(value),
+                'F' => feed = Some(value),
+                'G' => match value as u16 {
+                    90 => absolute = true,
+                    91 => absolute = false,
+                    0 | 1 | 21 | 53 | 94 => {}
+                    _ => return Err("error:20".to_string()),
+                },
+                _ => return Err("error:20".to_string()),
+            }
+        }
+        let from = self.moves.back().map_or(self.position, |mv| mv.target);
+        let mut target = from;
+        for axis in 0..3 {
+            if let Some(value) = axes[axis] {
+                target[axis] = if absolute { value } else { from[axis] + value };
+            }
+        }
+        Ok((target, feed))
+    }
+
+    fn within_travel(&self, target: &[f32; 3]) -> bool {
+        self.setting(20) == 0.0
+            || (0..3).all(|axis| target[axis] <= 0.0 && target[axis] >= -self.setting(130 + axis as u16))
+    }
+
+    fn jog(&mut self, words: &str) -> String {
+        if self.state != MachineState::Idle && self.state != MachineState::Jog {
+            return "error:8".to_string();
+        }
+        match self.target(words, self.absolute) {
+            Ok((_, None)) => "error:22".to_string(),
+            Ok((target, Some(feed))) => {
+                if !self.within_travel(&target) {
+                    return "error:15".to_string();
+                }
+                self.moves.push_back(Move {
+                    target,
+                    feed,
+                    motion: Motion::Jog,
+                });
+                self.state = MachineState::Jog;
+                "ok".to_string()
+            }
+            Err(err) => err,
+        }
+    }
+
+    fn gcode(&mut self, line: &str) -> String {
+        if self.state == MachineState::Alarm {
+            return "error:9".to_string();
+        }
+        let (target, feed) = match self.target(line, self.absolute) {
+            Ok(target) => target,
+            Err(err) => return err,
+        };
+        if line.contains("G90") {
+            self.absolute = true;
+        } else if line.contains("G91") {
+            self.absolute = false;
+        }
+        if let Some(feed) = feed {
+            self.feed = feed;
+        }
+        if target != self.moves.back().map_or(self.position, |mv| mv.target) {
+            let (feed, motion) = if line.starts_with("G0") && !line.starts_with("G01") {
+                (self.setting(110), Motion::Rapid)
+            } else {
+                (self.feed, Motion::Feed)
+            };
+            if feed <= 0.0 {
+                return "error:22".to_string();
+            }
+            // soft limits stop everything, the planner is flushed but the position is kept
+            if !self.within_travel(&target) {
+                self.moves.clear();
+                self.state = MachineState::Alarm;
+                return "ALARM:2".to_string();
+            }
+            self.moves.push_back(Move {
+                target,
+                feed,
+                motion,
+            });
+            // a move sent while held waits for cycle start
+            if self.state != MachineState::Hold {
+                self.state = MachineState::Run;
+            }
+        }
+        "ok".to_string()
+    }
+
+    fn write_setting(&mut self, assignment: &str) -> String {
+        let mut split = assignment.splitn(2, '=');
+        let key = match split.next().and_then(|key| key.parse::<u16>().ok()) {
+            Some(key) => key,
+            None => return "error:3".to_string(),
+        };
+        if !self.settings.contains_key(&key) {
+            return "error:3".to_string();
+        }
+        if self.state != MachineState::Idle && self.state != MachineState::Alarm {
+            return "error:8".to_string();
+        }
+        match split.next().and_then(|value| value.parse::<f32>().ok()) {
+            Some(value) => {
+                let value = if self.settings[&key].contains('.') {
+                    format!("{:.3}", value)
+                } else {
+                    format!("{}", value as i32)
+                };
+                self.settings.insert(key, value);
+                "ok".to_string()
+            }
+            None => "error:2".to_string(),
+        }
+    }
+}
+
+impl Write for Simulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tick();
+        for byte in buf {
+            match *byte {
+                b'?' | b'!' | b'~' | 0x18 | 0x80..=0xFF => self.realtime(*byte),
+                _ if self.buffered() >= RX_BUFFER_SIZE - 1 => {}
+                b'\n' => {
+                    let line = String::from_utf8_lossy(&self.line[..]).to_uppercase();
+                    self.line.clear();
+                    self.waiting.push_back(line);
+                }
+                b'\r' | b' ' => {}
+                _ => self.line.push(*byte),
+            }
+        }
+        self.tick();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Simulator {
+    // behaves like the serial port, waits for a response until the timeout
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        loop {
+            self.tick();
+            if self.output.len() > 0 {
+                let len = buf.len().min(self.output.len());
+                for (i, byte) in self.output.drain(..len).enumerate() {
+                    buf[i] = byte;
+                }
+                return Ok(len);
+            }
+            if start.elapsed() >= self.timeout {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked() -> Simulator {
+        let mut simulator = Simulator::new(Duration::from_millis(20));
+        send(&mut simulator, b"$X\n");
+        simulator
+    }
+
+    // writes the bytes and reads back every line answered before the timeout
+    fn send(simulator: &mut Simulator, bytes: &[u8]) -> Vec<String> {
+        simulator.write_all(bytes).unwrap();
+        let mut output = String::new();
+        let mut buf = [0; 256];
+        while let Ok(len) = simulator.read(&mut buf) {
+            output.push_str(&String::from_utf8_lossy(&buf[..len]));
+        }
+        output.lines().map(|line| line.to_string()).collect()
+    }
+
+    fn state(simulator: &mut Simulator) -> String {
+        let report = send(simulator, b"?").remove(0);
+        report[1..report.find('|').unwrap()].to_string()
+    }
+
+    #[test]
+    fn ok_and_error_replies() {
+        let mut simulator = Simulator::new(Duration::from_millis(20));
+        assert_eq!(send(&mut simulator, b"G0 X-1\n"), vec!["error:9"]);
+        assert_eq!(
+            send(&mut simulator, b"$X\n"),
+            vec!["[MSG:Caution: Unlocked]", "ok"]
+        );
+        assert_eq!(send(&mut simulator, b"\n"), vec!["ok"]);
+        assert_eq!(send(&mut simulator, b"$J=X-1\n"), vec!["error:22"]);
+        assert_eq!(send(&mut simulator, b"G1 Q1 F100\n"), vec!["error:20"]);
+        assert_eq!(send(&mut simulator, b"$99=1\n"), vec!["error:3"]);
+        assert_eq!(
+            send(&mut simulator, b"g1 x-1 f100\n$G\n"),
+            vec!["ok", "[GC:G0 G54 G17 G21 G90 G94 M5 M9 T0 F100 S0]", "ok"]
+        );
+    }
+
+    #[test]
+    fn status_report_format() {
+        let mut simulator = Simulator::new(Duration::from_millis(20));
+        assert_eq!(
+            send(&mut simulator, b"?"),
+            vec!["<Alarm|MPos:0.000,0.000,0.000|FS:0,0|Ov:100,100,100>"]
+        );
+        send(&mut simulator, b"$X\n$J=X-5 F6000\n");
+        let report = send(&mut simulator, b"?").remove(0);
+        assert!(report.starts_with("<Jog|MPos:-"), "{}", report);
+        assert!(report.ends_with("|FS:6000,0|Ov:100,100,100>"), "{}", report);
+    }
+
+    #[test]
+    fn realtime_bytes() {
+        let mut simulator = unlocked();
+        send(&mut simulator, b"!");
+        assert_eq!(state(&mut simulator), "Idle");
+        send(&mut simulator, b"G1 X-10 F60\n");
+        assert_eq!(state(&mut simulator), "Run");
+        send(&mut simulator, b"!");
+        assert_eq!(state(&mut simulator), "Hold:0");
+        send(&mut simulator, b"~");
+        assert_eq!(state(&mut simulator), "Run");
+        send(&mut simulator, &[0x91]);
+        let report = send(&mut simulator, b"?").remove(0);
+        assert!(report.ends_with("|Ov:110,100,100>"), "{}", report);
+        // reset mid move loses the position
+        assert_eq!(
+            send(&mut simulator, &[0x18]),
+            vec![
+                "ALARM:3",
+                "",
+                "Grbl 1.1h ['$' for help]",
+                "[MSG:'$H'|'$X' to unlock]"
+            ]
+        );
+        assert_eq!(state(&mut simulator), "Alarm");
+
+        // jog cancel drops the jog and the jogs waiting behind it
+        let mut simulator = unlocked();
+        send(&mut simulator, b"$J=X-10 F60\n");
+        assert_eq!(state(&mut simulator), "Jog");
+        send(&mut simulator, &[0x85]);
+        assert_eq!(state(&mut simulator), "Idle");
+    }
+}
@@ -0,0 +1,222 @@
+// grbl 1.1 status reports, ie. <Hold:0|MPos:-1.000,-2.000,-3.000|Bf:15,128|FS:0,0|Pn:XZ|Ov:100,100,100>
+#[derive(Debug, Clone)]
+pub struct Status {
+    // Idle, Run, Hold, Jog, Alarm, Door, Check, Home or Sleep
+    pub status: String,
+    // the number after Hold: or Door:
+    pub sub_state: Option<u8>,
+    // machine position, worked out from WPos and WCO when $10 has grbl report work positions
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    // work coordinate offset, only sent now and then so the last one seen is kept
+    pub wco: [f32; 3],
+    // free planner blocks and free bytes in the serial receive buffer, if $10 asks for them
+    pub buffer: Option<(u16, u16)>,
+    pub feed: f32,
+    pub spindle: f32,
+    pub pins: Pins,
+    // percent, only sent now and then so the last ones seen are kept
+    pub feed_override: u16,
+    pub rapid_override: u16,
+    pub spindle_override: u16,
+}
+
+// inputs grbl sees as triggered, from Pn:
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pins {
+    pub x_limit: bool,
+    pub y_limit: bool,
+    pub z_limit: bool,
+    pub probe: bool,
+    pub door: bool,
+    pub hold: bool,
+    pub soft_reset: bool,
+    pub cycle_start: bool,
+}
+
+impl Pins {
+    // (name, triggered) in the order grbl lists them
+    pub fn all(&self) -> Vec<(&'static str, bool)> {
+        vec![
+            ("X Limit", self.x_limit),
+            ("Y Limit", self.y_limit),
+            ("Z Limit", self.z_limit),
+            ("Probe", self.probe),
+            ("Door", self.door),
+            ("Hold", self.hold),
+            ("Soft Reset", self.soft_reset),
+            ("Cycle Start", self.cycle_start),
+        ]
+    }
+
+    pub fn triggered(&self) -> Vec<&'static str> {
+        self.all()
+            .into_iter()
+            .filter(|(_, on)| *on)
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+impl Status {
+    // state with what the sub-state means, ie. "Hold (ready to resume)"
+    pub fn state_text(&self) -> String {
+        let meaning = match (&self.status[..], self.sub_state) {
+            ("Hold", Some(0)) => "ready to resume",
+            ("Hold", Some(_)) => "stopping",
+            ("Door", Some(0)) => "door closed, ready to resume",
+            ("Door", Some(1)) => "stopped, door open",
+            ("Door", Some(2)) => "door opened, parking",
+            ("Door", Some(_)) => "door closed, restoring",
+            _ => return self.status.clone(),
+        };
+        format!("{} ({})", self.status, meaning)
+    }
+
+    pub fn work_position(&self) -> [f32; 3] {
+        [
+            self.x - self.wco[0],
+            self.y - self.wco[1],
+            self.z - self.wco[2],
+        ]
+    }
+}
+
+// None for anything that is not a status report with a position
+pub fn parse(report: &str, last: Option<&Status>) -> Option<Status> {
+    let report = report.trim();
+    if !report.starts_with('<') || !report.ends_with('>') {
+        return None;
+    }
+    let mut fields = report[1..report.len() - 1].split('|');
+    let mut state = fields.next()?.splitn(2, ':');
+    let mut status = Status {
+        status: state.next()?.to_string(),
+        sub_state: state.next().and_then(|sub| sub.parse::<u8>().ok()),
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        wco: last.map_or([0.0; 3], |l| l.wco),
+        buffer: None,
+        feed: 0.0,
+        spindle: 0.0,
+        pins: Pins::default(),
+        feed_override: last.map_or(100, |l| l.feed_override),
+        rapid_override: last.map_or(100, |l| l.rapid_override),
+        spindle_override: last.map_or(100, |l| l.spindle_override),
+    };
+    let mut mpos = None;
+    let mut wpos = None;
+    for field in fields {
+        let mut split = field.splitn(2, ':');
+        let name = split.next()?;
+        let value = split.next().unwrap_or("");
+        match name {
+            "MPos" => mpos = Some(xyz(value)?),
+            "WPos" => wpos = Some(xyz(value)?),
+            "WCO" => status.wco = xyz(value)?,
+            "Bf" => {
+                let bf = numbers(value)?;
+                status.buffer = Some((*bf.get(0)? as u16, *bf.get(1)? as u16));
+            }
+            "F" => status.feed = value.parse::<f32>().ok()?,
+            "FS" => {
+                let fs = numbers(value)?;
+                status.feed = *fs.get(0)?;
+                status.spindle = *fs.get(1)?;
+            }
+            "Pn" => {
+                for pin in value.chars() {
+                    match pin {
+                        'X' => status.pins.x_limit = true,
+                        'Y' => status.pins.y_limit = true,
+                        'Z' => status.pins.z_limit = true,
+                        'P' => status.pins.probe = true,
+                        'D' => status.pins.door = true,
+                        'H' => status.pins.hold = true,
+                        'R' => status.pins.soft_reset = true,
+                        'S' => status.pins.cycle_start = true,
+                        _ => {}
+                    }
+                }
+            }
+            "Ov" => {
+                let ov = numbers(value)?;
+                status.feed_override = *ov.get(0)? as u16;
+                status.rapid_override = *ov.get(1)? as u16;
+                status.spindle_override = *ov.get(2)? as u16;
+            }
+            // line numbers, accessory state and anything newer than 1.1
+            _ => {}
+        }
+    }
+    let position = match (mpos, wpos) {
+        (Some(mpos), _) => mpos,
+        (None, Some(wpos)) => [
+            wpos[0] + status.wco[0],
+            wpos[1] + status.wco[1],
+            wpos[2] + status.wco[2],
+        ],
+        (None, None) => return None,
+    };
+    status.x = position[0];
+    status.y = position[1];
+    status.z = position[2];
+    Some(status)
+}
+
+fn numbers(value: &str) -> Option<Vec<f32>> {
+    value.split(',').map(|n| n.parse::<f32>().ok()).collect()
+}
+
+// the first three axes, grbl sends more when built with them
+fn xyz(value: &str) -> Option<[f32; 3]> {
+    let n = numbers(value)?;
+    Some([*n.get(0)?, *n.get(1)?, *n.get(2)?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_position() {
+        let status = parse(
+            "<Hold:0|MPos:-1.000,-2.000,-3.000|Bf:15,128|FS:250,0|Pn:XZ|Ov:120,50,100>",
+            None,
+        )
+        .unwrap();
+        assert_eq!(status.status, "Hold");
+        assert_eq!(status.sub_state, Some(0));
+        assert_eq!(status.state_text(), "Hold (ready to resume)");
+        assert_eq!([status.x, status.y, status.z], [-1.0, -2.0, -3.0]);
+        assert_eq!(status.buffer, Some((15, 128)));
+        assert_eq!(status.feed, 250.0);
+        assert_eq!(status.pins.triggered(), vec!["X Limit", "Z Limit"]);
+        assert_eq!((status.feed_override, status.rapid_override), (120, 50));
+    }
+
+    #[test]
+    fn work_position_with_last_offset() {
+        let first = parse(
+            "<Idle|WPos:1.000,1.000,1.000|FS:0,0|WCO:-2.000,-3.000,-4.000>",
+            None,
+        )
+        .unwrap();
+        assert_eq!([first.x, first.y, first.z], [-1.0, -2.0, -3.0]);
+        assert_eq!(first.work_position(), [1.0, 1.0, 1.0]);
+        // WCO and Ov are left out of most reports
+        let next = parse("<Jog|WPos:0.000,0.000,0.000|FS:250,0>", Some(&first)).unwrap();
+        assert_eq!([next.x, next.y, next.z], [-2.0, -3.0, -4.0]);
+        assert_eq!(next.feed_override, 100);
+        assert!(next.pins.triggered().is_empty());
+    }
+
+    #[test]
+    fn not_a_report() {
+        assert!(parse("ok", None).is_none());
+        assert!(parse("<Idle|FS:0,0>", None).is_none());
+        assert!(parse("<Idle|MPos:1.000,oops,3.000>", None).is_none());
+    }
+}
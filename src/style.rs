@@ -58,6 +58,7 @@ pub mod style {
             match theme {
                 Theme::Active => yellow::Tooltip.into(),
                 Theme::Red => red::Container.into(),
+                Theme::Green => green::Container.into(),
                 Theme::Yellow => yellow::Container.into(),
                 Theme::LightGray => light_gray::Container.into(),
                 Theme::LighterGray => lighter_gray::Container.into(),
@@ -367,7 +368,21 @@ pub mod style {
     }
 
     mod green {
-        use iced::{button, Color};
+        use iced::{button, container, Color};
+
+        pub struct Container;
+
+        impl container::StyleSheet for Container {
+            fn style(&self) -> container::Style {
+                container::Style {
+                    text_color: Some(Color::WHITE),
+                    background: Color::from_rgb8(96, 196, 23).into(),
+                    border_color: Color::from_rgb8(76, 176, 3),
+                    border_width: 5.0,
+                    border_radius: 8.0,
+                }
+            }
+        }
 
         pub struct Button;
 
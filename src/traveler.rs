@@ -0,0 +1,239 @@
+use super::events::{Event, InputWhen, Record};
+use super::logger::{replace_os_char, RUN_LOGS};
+use chrono::prelude::*;
+use std::fs;
+use std::path::Path;
+
+pub const EXPORTS: &str = "./exports";
+
+// one run read back from its json lines log
+struct TravelerRun {
+    log: String,
+    recipe: String,
+    revision: String,
+    operator: String,
+    started: String,
+    status: String,
+    before: Vec<(String, String)>,
+    after: Vec<(String, String)>,
+    steps: Vec<TravelerStep>,
+}
+
+struct TravelerStep {
+    step: String,
+    destination: String,
+    action: String,
+    planned_secs: u64,
+    action_started: Option<DateTime<FixedOffset>>,
+    paused_secs: f32,
+    // action already run before the run was interrupted and resumed
+    skipped_secs: f32,
+    actual_secs: Option<f32>,
+}
+
+// write every run log in log_titles to one csv, one row per step. Returns the path of the csv
+// and how many runs it holds, logs from before json lines were added are skipped
+pub fn export(log_titles: &Vec<String>) -> Result<(String, usize), ()> {
+    let runs: Vec<TravelerRun> = log_titles
+        .iter()
+        .filter_map(|title| read_run(title))
+        .collect();
+    if runs.is_empty() {
+        return Ok((String::new(), 0));
+    }
+    // inputs differ between recipes, give every input title its own column
+    let mut before_titles: Vec<String> = Vec::new();
+    let mut after_titles: Vec<String> = Vec::new();
+    for run in &runs {
+        for (title, _) in &run.before {
+            if !before_titles.contains(title) {
+                before_titles.push(title.clone());
+            }
+        }
+        for (title, _) in &run.after {
+            if !after_titles.contains(title) {
+                after_titles.push(title.clone());
+            }
+        }
+    }
+
+    fs::create_dir_all(EXPORTS).map_err(|_| ())?;
+    let path = format!(
+        "{}/{}",
+        EXPORTS,
+        replace_os_char(format!("{}; Traveler.csv", Local::now().to_rfc2822()))
+    );
+    let mut writer = csv::Writer::from_path(Path::new(&path)).map_err(|_| ())?;
+    let mut header = vec![
+        "Log".to_string(),
+        "Recipe".to_string(),
+        "Revision".to_string(),
+        "Operator".to_string(),
+        "Started".to_string(),
+        "Status".to_string(),
+    ];
+    header.extend(before_titles.iter().map(|t| format!("Before: {}", t)));
+    header.extend(after_titles.iter().map(|t| format!("After: {}", t)));
+    header.extend(
+        vec![
+            "Step",
+            "Destination",
+            "Action",
+            "Planned Dwell (s)",
+            "Actual Dwell (s)",
+        ]
+        .into_iter()
+        .map(|h| h.to_string()),
+    );
+    writer.write_record(&header).map_err(|_| ())?;
+
+    for run in &runs {
+        let mut run_cols = vec![
+            run.log.clone(),
+            run.recipe.clone(),
+            run.revision.clone(),
+            run.operator.clone(),
+            run.started.clone(),
+            run.status.clone(),
+        ];
+        run_cols.extend(before_titles.iter().map(|t| input_value(&run.before, t)));
+        run_cols.extend(after_titles.iter().map(|t| input_value(&run.after, t)));
+        if run.steps.is_empty() {
+            let mut row = run_cols.clone();
+            row.extend(vec![String::new(); 5]);
+            writer.write_record(&row).map_err(|_| ())?;
+        }
+        for step in &run.steps {
+            let mut row = run_cols.clone();
+            row.push(step.step.clone());
+            row.push(step.destination.clone());
+            row.push(step.action.clone());
+            row.push(step.planned_secs.to_string());
+            row.push(match step.actual_secs {
+                Some(secs) => format!("{:.1}", secs),
+                None => String::new(),
+            });
+            writer.write_record(&row).map_err(|_| ())?;
+        }
+    }
+    writer.flush().map_err(|_| ())?;
+    Ok((path, runs.len()))
+}
+
+fn input_value(inputs: &Vec<(String, String)>, title: &String) -> String {
+    inputs
+        .iter()
+        .find(|(t, _)| t == title)
+        .map(|(_, v)| v.clone())
+        .unwrap_or(String::new())
+}
+
+fn read_run(log_title: &String) -> Option<TravelerRun> {
+    let content =
+        fs::read_to_string(Path::new(&format!("{}/{}.jsonl", RUN_LOGS, log_title))).ok()?;
+    let mut run: Option<TravelerRun> = None;
+    let mut paused_at: Option<DateTime<FixedOffset>> = None;
+    let mut skipped_secs = 0.0;
+    for line in content.lines() {
+        let record: Record = match serde_json::from_str(line) {
+            Ok(record) => record,
+            Err(_) => continue,
+        };
+        let time = match DateTime::parse_from_rfc3339(&record.time) {
+            Ok(time) => time,
+            Err(_) => continue,
+        };
+        if let Event::RunStarted {
+            recipe,
+            operator,
+            revision,
+        } = &record.event
+        {
+            run = Some(TravelerRun {
+                log: log_title.clone(),
+                recipe: recipe.clone(),
+                revision: revision.map(|r| r.to_string()).unwrap_or(String::new()),
+                operator: operator.clone().unwrap_or("**Unavailable**".to_string()),
+                started: time.to_rfc2822(),
+                status: "Incomplete".to_string(),
+                before: Vec::new(),
+                after: Vec::new(),
+                steps: Vec::new(),
+            });
+            continue;
+        }
+        let run = run.as_mut()?;
+        match record.event {
+            Event::Input { when, title, value } => match when {
+                InputWhen::Before => run.before.push((title, value)),
+                InputWhen::After => run.after.push((title, value)),
+            },
+            Event::StepStarted {
+                step,
+                destination,
+                action,
+                planned_secs,
+            } => {
+                // a resumed run starts the interrupted step again
+                if let Some(last) = run.steps.last() {
+                    if last.step == step && last.actual_secs.is_none() {
+                        run.steps.pop();
+                    }
+                }
+                run.steps.push(TravelerStep {
+                    step,
+                    destination,
+                    action,
+                    planned_secs,
+                    action_started: None,
+                    paused_secs: 0.0,
+                    skipped_secs,
+                    actual_secs: None,
+                });
+                skipped_secs = 0.0;
+            }
+            Event::ActionStarted { .. } => {
+                if let Some(step) = run.steps.last_mut() {
+                    step.action_started = Some(time);
+                }
+            }
+            Event::StepFinished { .. } => {
+                if let Some(step) = run.steps.last_mut() {
+                    if let Some(started) = step.action_started {
+                        step.actual_secs = Some(
+                            ((time - started).num_milliseconds() as f32 / 1000.0
+                                - step.paused_secs
+                                + step.skipped_secs)
+                                .max(0.0),
+                        );
+                    }
+                }
+            }
+            Event::Paused => paused_at = Some(time),
+            Event::Resumed => {
+                if let (Some(at), Some(step)) = (paused_at, run.steps.last_mut()) {
+                    if step.action_started.is_some() {
+                        step.paused_secs += (time - at).num_milliseconds() as f32 / 1000.0;
+                    }
+                }
+                paused_at = None;
+            }
+            // a faulted run is stopped or interrupted by the fault, keep what it was
+            Event::Stopped if run.status.starts_with("Faulted") => {}
+            Event::Stopped => run.status = "Stopped".to_string(),
+            Event::Fault { fault, .. } => run.status = format!("Faulted ({})", fault),
+            Event::Interrupted { .. } if run.status.starts_with("Faulted") => {}
+            Event::Interrupted { .. } => run.status = "Interrupted".to_string(),
+            Event::RunResumed {
+                skipped_secs: secs,
+                ..
+            } => {
+                run.status = "Incomplete".to_string();
+                skipped_secs = secs as f32;
+            }
+            Event::Done => run.status = "Done".to_string(),
+            _ => {}
+        }
+    }
+    run
+}
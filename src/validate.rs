@@ -0,0 +1,213 @@
+use super::nodes::Nodes;
+use std::collections::HashSet;
+
+// suffixes of nodes Bathtub generates, a node in baths.toml with one would be mistaken for them
+const RESERVED_SUFFIXES: [&str; 3] = ["_hover", "_approach", "_lift"];
+
+// problems with the nodes from baths.toml. Errors must be fixed before the nodes are used,
+// warnings are likely mistakes but will not stop anything from running
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+// config is the nodes as written in baths.toml, before add_height_nodes
+pub fn validate(config: &Nodes) -> Report {
+    let mut report = Report::default();
+    let names: Vec<&String> = config.node.iter().map(|n| &n.name).collect();
+    if !names.iter().any(|n| *n == "HOME") {
+        report
+            .errors
+            .push("'HOME' required by Bathtub, not found in config/baths.toml".to_string());
+    }
+    let mut seen = HashSet::new();
+    for node in &config.node {
+        if !seen.insert(&node.name) {
+            report
+                .errors
+                .push(format!("'{}' is used as a name more than once", node.name));
+        }
+        if node.name == "paused_node" {
+            report
+                .errors
+                .push("'paused_node' is reserved by Bathtub, rename it".to_string());
+        }
+        if let Some(suffix) = RESERVED_SUFFIXES.iter().find(|s| node.name.ends_with(*s)) {
+            report.errors.push(format!(
+                "'{}' ends with '{}', which is reserved for nodes Bathtub generates",
+                node.name, suffix
+            ));
+        }
+        for neighbor in &node.neighbors {
+            match config.node.iter().find(|n| &n.name == neighbor) {
+                None => report.errors.push(format!(
+                    "'{}' has neighbor '{}', which does not exist",
+                    node.name, neighbor
+                )),
+                Some(other) if !other.neighbors.contains(&node.name) => {
+                    report.warnings.push(format!(
+                        "'{}' can move to '{}' but not back",
+                        node.name, neighbor
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+    for (i, a) in config.node.iter().enumerate() {
+        for b in &config.node[i + 1..] {
+            if (a.x - b.x).abs() < 0.001 && (a.y - b.y).abs() < 0.001 && (a.z - b.z).abs() < 0.001
+            {
+                report.errors.push(format!(
+                    "'{}' and '{}' are at the same position",
+                    a.name, b.name
+                ));
+            }
+        }
+    }
+    // walk the generated graph from HOME, the same graph paths are found on
+    let mut graph = config.clone();
+    graph.add_height_nodes();
+    let mut reached: HashSet<&String> = HashSet::new();
+    let mut queue: Vec<&String> = graph
+        .node
+        .iter()
+        .filter(|n| n.name == "HOME")
+        .map(|n| &n.name)
+        .collect();
+    while let Some(name) = queue.pop() {
+        if reached.insert(name) {
+            if let Some(node) = graph.node.iter().find(|n| &n.name == name) {
+                queue.extend(node.neighbors.iter());
+            }
+        }
+    }
+    if !reached.is_empty() {
+        for node in &config.node {
+            if !reached.contains(&node.name) {
+                report
+                    .warnings
+                    .push(format!("'{}' cannot be reached from 'HOME'", node.name));
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml_str: &str) -> Nodes {
+        toml::from_str(toml_str).unwrap()
+    }
+
+    #[test]
+    fn valid_config() {
+        let report = validate(&config(
+            "[[node]]
+            name = 'HOME'
+            x = -1.0
+            y = -1.0
+            z = -1.0
+            hide = true
+            neighbors = ['A']
+
+            [[node]]
+            name = 'A'
+            x = -10.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = ['HOME']",
+        ));
+        assert!(report.errors.is_empty());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let report = validate(&config(
+            "[[node]]
+            name = 'A'
+            x = -10.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = ['Missing']
+
+            [[node]]
+            name = 'A'
+            x = -20.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = []
+
+            [[node]]
+            name = 'B_hover'
+            x = -10.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = []
+
+            [[node]]
+            name = 'paused_node'
+            x = -30.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = []",
+        ));
+        assert_eq!(
+            report.errors,
+            vec![
+                "'HOME' required by Bathtub, not found in config/baths.toml",
+                "'A' has neighbor 'Missing', which does not exist",
+                "'A' is used as a name more than once",
+                "'B_hover' ends with '_hover', which is reserved for nodes Bathtub generates",
+                "'paused_node' is reserved by Bathtub, rename it",
+                "'A' and 'B_hover' are at the same position",
+            ]
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let report = validate(&config(
+            "[[node]]
+            name = 'HOME'
+            x = -1.0
+            y = -1.0
+            z = -1.0
+            hide = true
+            neighbors = ['A']
+
+            [[node]]
+            name = 'A'
+            x = -10.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = []
+
+            [[node]]
+            name = 'B'
+            x = -20.0
+            y = -1.0
+            z = -5.0
+            hide = false
+            neighbors = []",
+        ));
+        assert!(report.errors.is_empty());
+        assert_eq!(
+            report.warnings,
+            vec![
+                "'HOME' can move to 'A' but not back",
+                "'B' cannot be reached from 'HOME'",
+            ]
+        );
+    }
+}